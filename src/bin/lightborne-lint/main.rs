@@ -0,0 +1,289 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use bevy_ecs_ldtk::{
+    ldtk::{LdtkJson, Level},
+    prelude::LdtkFields,
+};
+
+// the solver only needs the beam rules, not the colors beams are drawn with
+#[allow(dead_code)]
+#[path = "../../game/light/color.rs"]
+mod color;
+#[path = "../../game/constants.rs"]
+mod constants;
+mod solver;
+#[path = "../../ldtk/validation.rs"]
+mod validation;
+
+use validation::{
+    level_entities, level_name, terrain_cell_counts, validate_project, CRYSTAL_INT_CELLS,
+    LIGHT_COLORS,
+};
+
+const LEVELS_DIR: &str = "assets/levels";
+
+struct LevelStats {
+    name: String,
+    selectable: bool,
+    allowed_colors: Vec<String>,
+    sensors: usize,
+    shards: Vec<String>,
+    /// Number of crystal tiles of each color, active or not.
+    crystals: Vec<(&'static str, usize)>,
+}
+
+impl LevelStats {
+    fn new(level: &Level) -> Self {
+        let cells = terrain_cell_counts(level);
+        let crystals = CRYSTAL_INT_CELLS
+            .iter()
+            .map(|(color, values)| {
+                let count = values.iter().filter_map(|value| cells.get(value)).sum();
+                (*color, count)
+            })
+            .filter(|(_, count)| *count > 0)
+            .collect();
+
+        let mut allowed_colors = level
+            .iter_enums_field("AllowedColors")
+            .map(|colors| colors.cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        allowed_colors.sort_by_key(|color| {
+            LIGHT_COLORS
+                .iter()
+                .position(|known| *known == color.as_str())
+        });
+
+        LevelStats {
+            name: level_name(level),
+            selectable: level.get_bool_field("Selectable").is_ok_and(|value| *value),
+            allowed_colors,
+            sensors: level_entities(level)
+                .filter(|entity| entity.identifier == "Sensor")
+                .count(),
+            shards: level_entities(level)
+                .filter(|entity| entity.identifier == "CrystalShard")
+                .filter_map(|entity| entity.get_enum_field("light_color").ok().cloned())
+                .collect(),
+            crystals,
+        }
+    }
+}
+
+fn list(items: &[String]) -> String {
+    if items.is_empty() {
+        "-".to_string()
+    } else {
+        items.join(", ")
+    }
+}
+
+/// Levels that can't be reached by selecting a level and walking through its neighbours.
+fn unreachable_levels(project: &LdtkJson) -> Vec<&Level> {
+    let by_iid = project
+        .levels
+        .iter()
+        .map(|level| (level.iid.as_str(), level))
+        .collect::<HashMap<_, _>>();
+
+    let mut reached = HashSet::new();
+    let mut queue = project
+        .levels
+        .iter()
+        .filter(|level| level.get_bool_field("Selectable").is_ok_and(|value| *value))
+        .collect::<VecDeque<_>>();
+    while let Some(level) = queue.pop_front() {
+        if !reached.insert(level.iid.as_str()) {
+            continue;
+        }
+        for neighbour in level.neighbours.iter() {
+            if let Some(next) = by_iid.get(neighbour.level_iid.as_str()) {
+                queue.push_back(next);
+            }
+        }
+    }
+
+    project
+        .levels
+        .iter()
+        .filter(|level| !reached.contains(level.iid.as_str()))
+        .collect()
+}
+
+fn solve(level: &Level) {
+    let name = level_name(level);
+    let report = match solver::solve_level(level) {
+        Ok(solver::SolverOutcome::Checked(report)) => report,
+        Ok(solver::SolverOutcome::NotChecked(unmodeled)) => {
+            println!(
+                "    {}: not checked, the solver doesn't model {}",
+                name,
+                unmodeled.join(", ")
+            );
+            return;
+        }
+        Err(err) => {
+            println!("    {}: skipped, {}", name, err);
+            return;
+        }
+    };
+
+    match &report.solution {
+        Some(shots) if shots.is_empty() && report.sensors > 0 => println!(
+            "    {}: SHORTCUT, the end is reachable without any beams despite {} sensor(s)",
+            name, report.sensors
+        ),
+        Some(shots) if shots.is_empty() => println!("    {}: solvable without beams", name),
+        Some(shots) => println!(
+            "    {}: solvable with {}{}",
+            name,
+            if report.free_aim { "free aim, " } else { "" },
+            shots
+                .iter()
+                .map(|shot| shot.to_string())
+                .collect::<Vec<_>>()
+                .join(", then ")
+        ),
+        None if report.gave_up => println!(
+            "    {}: gave up after {} configurations",
+            name, report.states
+        ),
+        None => println!(
+            "    {}: UNSOLVED, the end is unreachable in all {} configurations",
+            name, report.states
+        ),
+    }
+    for sensor in report.unused_sensors.iter() {
+        println!(
+            "    {}: sensor at [{}, {}] is never activated",
+            name, sensor.x, sensor.y
+        );
+    }
+}
+
+/// Lints a single project, returning whether it is free of problems.
+fn lint(path: &Path, solve_levels: bool) -> bool {
+    println!("{}", path.display());
+
+    let project = match std::fs::read_to_string(path)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| {
+            serde_json::from_str::<LdtkJson>(&contents).map_err(|err| err.to_string())
+        }) {
+        Ok(project) => project,
+        Err(err) => {
+            println!("  failed to read project: {}\n", err);
+            return false;
+        }
+    };
+
+    let stats = project
+        .levels
+        .iter()
+        .map(LevelStats::new)
+        .collect::<Vec<_>>();
+    println!(
+        "  {} levels, {} selectable",
+        stats.len(),
+        stats.iter().filter(|level| level.selectable).count()
+    );
+    println!(
+        "  {:<8} {:<8} {:<28} {:<20} crystals",
+        "level", "sensors", "allowed colors", "shards"
+    );
+    for level in stats.iter() {
+        let crystals = level
+            .crystals
+            .iter()
+            .map(|(color, count)| format!("{} {}", color, count))
+            .collect::<Vec<_>>();
+        println!(
+            "  {:<8} {:<8} {:<28} {:<20} {}",
+            level.name,
+            level.sensors,
+            list(&level.allowed_colors),
+            list(&level.shards),
+            list(&crystals)
+        );
+    }
+
+    let unselectable = stats
+        .iter()
+        .filter(|level| !level.selectable)
+        .map(|level| level.name.clone())
+        .collect::<Vec<_>>();
+    println!("  unselectable: {}", list(&unselectable));
+
+    let unreachable = unreachable_levels(&project)
+        .into_iter()
+        .map(level_name)
+        .collect::<Vec<_>>();
+    println!("  unreachable: {}", list(&unreachable));
+
+    let problems = validate_project(&project);
+    if problems.is_empty() {
+        println!("  no problems");
+    } else {
+        println!("  {} problem(s):", problems.len());
+        for problem in problems.iter() {
+            println!("    {}", problem);
+        }
+    }
+
+    // the solver relies on everything validation checks
+    if solve_levels && problems.is_empty() {
+        println!("  solutions:");
+        for level in project.levels.iter() {
+            solve(level);
+        }
+    }
+    println!();
+
+    problems.is_empty() && unreachable.is_empty()
+}
+
+/// Checks LDtk projects without launching the game.
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("usage: lightborne-lint [--solve] [PROJECT.ldtk...]");
+        println!("Lints every project in {} if none are given.", LEVELS_DIR);
+        println!("--solve also searches for the beams that beat each level.");
+        return ExitCode::SUCCESS;
+    }
+    let solve_levels = args.iter().any(|arg| arg == "--solve");
+    args.retain(|arg| arg != "--solve");
+
+    let paths = if args.is_empty() {
+        let mut paths = match std::fs::read_dir(LEVELS_DIR) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "ldtk"))
+                .collect::<Vec<_>>(),
+            Err(err) => {
+                eprintln!("Failed to read {}: {}", LEVELS_DIR, err);
+                return ExitCode::FAILURE;
+            }
+        };
+        paths.sort();
+        paths
+    } else {
+        args.into_iter().map(PathBuf::from).collect()
+    };
+
+    let failed = paths
+        .iter()
+        .filter(|path| !lint(path, solve_levels))
+        .count();
+    if failed > 0 {
+        println!("{} of {} project(s) have problems", failed, paths.len());
+        ExitCode::FAILURE
+    } else {
+        println!("{} project(s) ok", paths.len());
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,1490 @@
+// NOTE: there is no physics world outside of the game, so the solver only approximates it on the
+// int grid, with the constants and beam color rules of the game included by `main.rs`.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+};
+
+use bevy::math::{IVec2, Vec2};
+use bevy_ecs_ldtk::{
+    ldtk::{EntityInstance, Level},
+    prelude::LdtkFields,
+};
+
+use crate::{
+    color::LightColor,
+    constants::{
+        COYOTE_TIME_TICKS, JUMP_BOOST_TICKS, LIGHT_MAX_SEGMENTS, LYRA_COLLIDER_OFFSET,
+        LYRA_COLLIDER_SIZE, LYRA_HURTBOX_SIZE, LYRA_RESPAWN_EPSILON, NUM_INCREMENTS,
+        PLAYER_GRAVITY, PLAYER_JUMP_VEL, PLAYER_MAX_H_VEL, PLAYER_MAX_Y_VEL, PLAYER_MOVE_VEL,
+        SHOULD_JUMP_TICKS,
+    },
+    validation::{level_entities, CRYSTAL_COLORS, CRYSTAL_INT_CELLS, LIGHT_COLORS},
+};
+
+const LYRA_HALF_SIZE: Vec2 = Vec2::new(LYRA_COLLIDER_SIZE.x / 2., LYRA_COLLIDER_SIZE.y / 2.);
+const LYRA_HURTBOX_HALF_SIZE: Vec2 = Vec2::new(LYRA_HURTBOX_SIZE.x / 2., LYRA_HURTBOX_SIZE.y / 2.);
+
+/// How far beams go. They keep growing in the game, so this only needs to cross the level.
+const BEAM_LENGTH: f32 = 10000.;
+/// Length of the longest movement the solver tries from a standing position.
+const MAX_MOVE_TICKS: u32 = 256;
+/// Angle between the beams tried when snapped beams aren't enough, in degrees.
+const FREE_AIM_STEP: f32 = 1.;
+/// Crystal configurations explored before giving up on a level.
+const MAX_STATES: usize = 4096;
+/// Entities with mechanics the solver doesn't model.
+const UNMODELED_ENTITIES: &[&str] = &[
+    "Prism",
+    "ColorFilter",
+    "Portal",
+    "MovingPlatform",
+    "Door",
+    "CrystalSignal",
+];
+
+/// Index of the blue crystals in [`CRYSTAL_COLORS`], which blue beams go through.
+const BLUE_CRYSTAL: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Cell {
+    Empty,
+    Wall,
+    Spike,
+    OneWay,
+    Mirror,
+    Crystal { color: usize, active: bool },
+}
+
+impl Cell {
+    fn from_int_grid(value: i32) -> Self {
+        match value {
+            1 => Cell::Wall,
+            2 => Cell::Spike,
+            15 => Cell::OneWay,
+            16 => Cell::Mirror,
+            // glass and lenses are solid for Lyra, and levels with them aren't solved
+            17 | 18 => Cell::Wall,
+            _ => CRYSTAL_INT_CELLS
+                .iter()
+                .position(|(_, values)| values.contains(&value))
+                .map_or(Cell::Empty, |color| Cell::Crystal {
+                    color,
+                    active: value == CRYSTAL_INT_CELLS[color].1[0],
+                }),
+        }
+    }
+}
+
+/// Bitset of the [`CRYSTAL_COLORS`] toggled by active sensors.
+type Toggled = u8;
+/// Bitset of [`LightColor`]s.
+type Colors = u8;
+
+fn crystal_active(color: usize, active: bool, toggled: Toggled) -> bool {
+    active != (toggled & (1 << color) != 0)
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Aabb {
+    min: Vec2,
+    max: Vec2,
+}
+
+impl Aabb {
+    fn new(center: Vec2, half_size: Vec2) -> Self {
+        Aabb {
+            min: center - half_size,
+            max: center + half_size,
+        }
+    }
+
+    fn overlaps(&self, other: &Aabb) -> bool {
+        self.min.x < other.max.x
+            && other.min.x < self.max.x
+            && self.min.y < other.max.y
+            && other.min.y < self.max.y
+    }
+
+    /// Distance along `dir` at which a ray from `origin` enters the box, and the normal of the side
+    /// it enters through. Rays starting inside hit immediately, like solid casts in avian.
+    fn cast_ray(&self, origin: Vec2, dir: Vec2) -> Option<(f32, Vec2)> {
+        let mut near = (f32::NEG_INFINITY, Vec2::ZERO);
+        let mut far = f32::INFINITY;
+        for axis in 0..2 {
+            let (o, d, min, max) = (origin[axis], dir[axis], self.min[axis], self.max[axis]);
+            if d.abs() < 1e-6 {
+                if o < min || o > max {
+                    return None;
+                }
+                continue;
+            }
+            let (t1, t2) = ((min - o) / d, (max - o) / d);
+            let (t_enter, t_exit) = (t1.min(t2), t1.max(t2));
+            if t_enter > near.0 {
+                let mut normal = Vec2::ZERO;
+                normal[axis] = -d.signum();
+                near = (t_enter, normal);
+            }
+            far = far.min(t_exit);
+        }
+        if near.0 > far || far < 0. {
+            return None;
+        }
+        if near.0 < 0. {
+            return Some((0., -dir));
+        }
+        Some(near)
+    }
+}
+
+/// The `Terrain` int grid, with `y` going up from the bottom of the level like in the game.
+struct Grid {
+    width: i32,
+    height: i32,
+    size: f32,
+    cells: Vec<Cell>,
+}
+
+impl Grid {
+    fn new(level: &Level) -> Option<Self> {
+        let layer = level
+            .layer_instances
+            .iter()
+            .flatten()
+            .find(|layer| layer.identifier == "Terrain")?;
+        Some(Grid {
+            width: layer.c_wid,
+            height: layer.c_hei,
+            size: layer.grid_size as f32,
+            cells: layer
+                .int_grid_csv
+                .iter()
+                .map(|value| Cell::from_int_grid(*value))
+                .collect(),
+        })
+    }
+
+    fn cell(&self, x: i32, y: i32) -> Cell {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return Cell::Empty;
+        }
+        self.cells[((self.height - 1 - y) * self.width + x) as usize]
+    }
+
+    fn cell_box(&self, x: i32, y: i32) -> Aabb {
+        Aabb {
+            min: Vec2::new(x as f32, y as f32) * self.size,
+            max: Vec2::new(x as f32 + 1., y as f32 + 1.) * self.size,
+        }
+    }
+
+    fn cell_at(&self, point: Vec2) -> IVec2 {
+        (point / self.size).floor().as_ivec2()
+    }
+
+    /// Cells overlapping `aabb`, with their boxes.
+    fn cells_in(&self, aabb: &Aabb) -> impl Iterator<Item = (Cell, Aabb)> + '_ {
+        let aabb = *aabb;
+        let min = self.cell_at(aabb.min);
+        let max = self.cell_at(aabb.max);
+        (min.y..=max.y)
+            .flat_map(move |y| (min.x..=max.x).map(move |x| (x, y)))
+            .map(|(x, y)| (self.cell(x, y), self.cell_box(x, y)))
+            .filter(move |(cell, cell_box)| *cell != Cell::Empty && cell_box.overlaps(&aabb))
+    }
+
+    /// The collider a beam of `color` hits in a cell, if any.
+    fn beam_collider(&self, x: i32, y: i32, color: LightColor, toggled: Toggled) -> Option<Aabb> {
+        let cell_box = self.cell_box(x, y);
+        match self.cell(x, y) {
+            Cell::Empty => None,
+            Cell::Wall | Cell::Mirror | Cell::Spike => Some(cell_box),
+            // one-way platforms only collide with their top quarter
+            Cell::OneWay => Some(Aabb {
+                min: Vec2::new(cell_box.min.x, cell_box.max.y - self.size / 4.),
+                ..cell_box
+            }),
+            Cell::Crystal { color: crystal, .. }
+                if crystal == BLUE_CRYSTAL && !color.hits_blue_crystals() =>
+            {
+                None
+            }
+            Cell::Crystal { color, active } => {
+                crystal_active(color, active, toggled).then_some(cell_box)
+            }
+        }
+    }
+
+    fn in_bounds(&self, point: Vec2) -> bool {
+        point.x >= 0.
+            && point.y >= 0.
+            && point.x <= self.width as f32 * self.size
+            && point.y <= self.height as f32 * self.size
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BeamHit {
+    Terrain {
+        mirror: bool,
+    },
+    Sensor(usize),
+    /// A segment of another beam, which absorbs the beam if it is black.
+    Beam {
+        black: bool,
+    },
+}
+
+/// The path of a single beam and the sensors it hits.
+#[derive(Clone, Debug)]
+struct BeamPath {
+    color: LightColor,
+    points: Vec<Vec2>,
+    sensors: Vec<usize>,
+}
+
+/// How the sensors of a group are combined, like `SensorLogic` in the game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SensorLogic {
+    Or,
+    And,
+    Xor,
+}
+
+impl SensorLogic {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Or" => Some(SensorLogic::Or),
+            "And" => Some(SensorLogic::And),
+            "Xor" => Some(SensorLogic::Xor),
+            _ => None,
+        }
+    }
+
+    fn eval(&self, mut states: impl Iterator<Item = bool>) -> bool {
+        match self {
+            SensorLogic::Or => states.any(|active| active),
+            SensorLogic::And => states.all(|active| active),
+            SensorLogic::Xor => states.filter(|active| *active).count() % 2 == 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Sensor {
+    grid: IVec2,
+    aabb: Aabb,
+    /// Index of the color of the crystals the sensor toggles, if it toggles any.
+    toggle_color: Option<usize>,
+    /// Colors that all need to hit the sensor at once for it to be lit. Any color will do if empty.
+    required_colors: Colors,
+    /// Whether the sensor is lit while it isn't hit instead.
+    inverted: bool,
+    /// Id of the group of sensors toggling their crystals together, and how they are combined.
+    group: Option<(i32, SensorLogic)>,
+}
+
+impl Sensor {
+    /// Whether beams of the colors in `hit_by` light the sensor, like `LightSensor::is_lit`.
+    fn is_lit(&self, hit_by: Colors) -> bool {
+        let lit = if self.required_colors == 0 {
+            hit_by != 0
+        } else {
+            hit_by & self.required_colors == self.required_colors
+        };
+        lit != self.inverted
+    }
+}
+
+/// A beam shot by Lyra from one of the standing positions.
+#[derive(Clone, Copy, Debug)]
+struct Shot {
+    color: LightColor,
+    node: usize,
+    degrees: f32,
+}
+
+/// A beam that is always on, either shot by Lyra or from a `LightSource`.
+#[derive(Clone, Copy, Debug)]
+struct BeamSource {
+    color: LightColor,
+    start: Vec2,
+    dir: Vec2,
+}
+
+/// Lyra's body during a simulated movement, mirroring [`MovementInfo`] and her velocity.
+#[derive(Clone, Copy, Debug)]
+struct Body {
+    /// Center of her collider.
+    pos: Vec2,
+    vel: Vec2,
+    grounded: bool,
+    should_jump: isize,
+    coyote: isize,
+    boost: isize,
+}
+
+impl Body {
+    fn new(pos: Vec2) -> Self {
+        Body {
+            pos,
+            vel: Vec2::ZERO,
+            grounded: false,
+            should_jump: 0,
+            coyote: 0,
+            boost: 0,
+        }
+    }
+
+    fn aabb(&self) -> Aabb {
+        // shrunk a little, so that standing next to a wall doesn't count as overlapping it
+        Aabb::new(self.pos, LYRA_HALF_SIZE - 0.01)
+    }
+
+    fn hurtbox(&self) -> Aabb {
+        Aabb::new(self.pos, LYRA_HURTBOX_HALF_SIZE)
+    }
+
+    fn bottom(&self) -> f32 {
+        self.pos.y - LYRA_HALF_SIZE.y
+    }
+}
+
+enum StepResult {
+    Alive,
+    Dead,
+    LeftLevel,
+}
+
+/// Inputs held during a simulated movement.
+#[derive(Clone, Copy)]
+struct Program {
+    jump: bool,
+    /// Tick at which the jump button is released.
+    jump_cut: Option<u32>,
+    direction: f32,
+    /// Ticks during which the direction is held.
+    held: (u32, u32),
+}
+
+const PROGRAMS: &[Program] = &{
+    const fn walk(direction: f32, ticks: u32) -> Program {
+        Program {
+            jump: false,
+            jump_cut: None,
+            direction,
+            held: (0, ticks),
+        }
+    }
+    const fn jump(jump_cut: Option<u32>, direction: f32, held: (u32, u32)) -> Program {
+        Program {
+            jump: true,
+            jump_cut,
+            direction,
+            held,
+        }
+    }
+    const FOREVER: u32 = MAX_MOVE_TICKS;
+    [
+        walk(-1., FOREVER),
+        walk(1., FOREVER),
+        // steps off ledges, falling straight down
+        walk(-1., 6),
+        walk(1., 6),
+        jump(None, 0., (0, 0)),
+        jump(None, -1., (0, FOREVER)),
+        jump(None, 1., (0, FOREVER)),
+        jump(None, -1., (10, FOREVER)),
+        jump(None, 1., (10, FOREVER)),
+        jump(None, -1., (0, 12)),
+        jump(None, 1., (0, 12)),
+        jump(Some(4), 0., (0, 0)),
+        jump(Some(4), -1., (0, FOREVER)),
+        jump(Some(4), 1., (0, FOREVER)),
+        jump(Some(10), -1., (0, FOREVER)),
+        jump(Some(10), 1., (0, FOREVER)),
+    ]
+};
+
+/// Where Lyra can go from a standing position, for one crystal configuration.
+#[derive(Default)]
+struct Moves {
+    nodes: Vec<usize>,
+    reaches_end: bool,
+}
+
+/// Every place Lyra can go from somewhere, for one crystal configuration.
+#[derive(Clone, Debug, Default)]
+struct Reachable {
+    nodes: Vec<usize>,
+    reaches_end: bool,
+}
+
+struct Puzzle {
+    grid: Grid,
+    start: Vec2,
+    end: Vec<Aabb>,
+    sensors: Vec<Sensor>,
+    emitters: Vec<BeamSource>,
+    colors: Vec<LightColor>,
+
+    /// Standing positions found so far, as the center of Lyra's collider.
+    nodes: Vec<Vec2>,
+    node_ids: HashMap<IVec2, usize>,
+    moves: HashMap<(Toggled, usize), Moves>,
+}
+
+/// Center of an entity, in the coordinates of the [`Grid`].
+fn entity_center(entity: &EntityInstance, px_hei: i32) -> Vec2 {
+    let size = Vec2::new(entity.width as f32, entity.height as f32);
+    let center = entity.px.as_vec2() + (Vec2::splat(0.5) - entity.pivot) * size;
+    Vec2::new(center.x, px_hei as f32 - center.y)
+}
+
+fn entity_aabb(entity: &EntityInstance, px_hei: i32) -> Aabb {
+    let size = Vec2::new(entity.width as f32, entity.height as f32);
+    Aabb::new(entity_center(entity, px_hei), size / 2.)
+}
+
+impl Puzzle {
+    fn new(level: &Level) -> Result<Self, String> {
+        let grid = Grid::new(level).ok_or("level has no Terrain layer")?;
+        let px_hei = level.px_hei;
+
+        let start = level_entities(level)
+            .find(|entity| entity.identifier == "Start")
+            .map(|flag| {
+                let pivot = flag.px.as_vec2();
+                Vec2::new(pivot.x, px_hei as f32 - pivot.y + LYRA_RESPAWN_EPSILON)
+            })
+            .ok_or("level has no Start flag")?;
+        let end = level_entities(level)
+            .filter(|entity| entity.identifier == "EndMarker")
+            .map(|marker| entity_aabb(marker, px_hei))
+            .collect::<Vec<_>>();
+        if end.is_empty() {
+            return Err("level has no EndMarker".into());
+        }
+
+        let sensors = level_entities(level)
+            .filter(|entity| entity.identifier == "Sensor")
+            .filter_map(|sensor| {
+                let toggle_color = sensor.get_enum_field("toggle_color").ok()?;
+                let toggle_color = CRYSTAL_COLORS
+                    .iter()
+                    .position(|color| *color == toggle_color.as_str())?;
+                // sensors that only send signals still stop beams
+                let toggle_crystals = sensor
+                    .get_bool_field("toggle_crystals")
+                    .map_or(true, |toggle_crystals| *toggle_crystals);
+                let required_colors = sensor
+                    .iter_enums_field("required_colors")
+                    .map(|colors| {
+                        colors
+                            .filter_map(|color| LightColor::from_name(color))
+                            .fold(0, |colors, color| colors | 1 << color as u8)
+                    })
+                    .unwrap_or(0);
+                let group = sensor
+                    .get_int_field("group")
+                    .ok()
+                    .filter(|group| **group > 0)
+                    .map(|group| {
+                        let logic = sensor
+                            .get_enum_field("logic")
+                            .ok()
+                            .and_then(|logic| SensorLogic::from_name(logic))
+                            .unwrap_or(SensorLogic::Or);
+                        (*group, logic)
+                    });
+                Some(Sensor {
+                    grid: sensor.grid,
+                    aabb: Aabb::new(entity_center(sensor, px_hei), Vec2::splat(4.)),
+                    toggle_color: toggle_crystals.then_some(toggle_color),
+                    required_colors,
+                    inverted: sensor
+                        .get_bool_field("inverted")
+                        .is_ok_and(|inverted| *inverted),
+                    group,
+                })
+            })
+            .collect();
+
+        let emitters = level_entities(level)
+            .filter(|entity| entity.identifier == "LightSource")
+            .flat_map(|emitter| {
+                let (Ok(x), Ok(y), Ok(direction), Ok(both)) = (
+                    emitter.get_float_field("XOffset"),
+                    emitter.get_float_field("YOffset"),
+                    emitter.get_point_field("Direction"),
+                    emitter.get_bool_field("BothDirections"),
+                ) else {
+                    return Vec::new();
+                };
+                let color = emitter
+                    .get_enum_field("light_color")
+                    .ok()
+                    .and_then(|color| LightColor::from_name(color))
+                    .unwrap_or(LightColor::White);
+                let offset = Vec2::new(*x, -*y);
+                let target = (*direction - emitter.grid).as_vec2() * Vec2::new(1., -1.) * grid.size;
+                let dir = (target - offset).normalize_or_zero();
+                let start = entity_center(emitter, px_hei) + offset;
+                let mut sources = vec![BeamSource { color, start, dir }];
+                if *both {
+                    sources.push(BeamSource {
+                        color,
+                        start,
+                        dir: -dir,
+                    });
+                }
+                sources
+            })
+            .filter(|source| source.dir != Vec2::ZERO)
+            .collect();
+
+        // shards are picked up along the way, but the solver doesn't know when
+        let mut colors = level
+            .iter_enums_field("AllowedColors")
+            .map(|colors| colors.cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        colors.extend(
+            level_entities(level)
+                .filter(|entity| entity.identifier == "CrystalShard")
+                .filter_map(|shard| shard.get_enum_field("light_color").ok().cloned()),
+        );
+        let mut colors = colors
+            .iter()
+            .filter_map(|color| LightColor::from_name(color))
+            .collect::<Vec<_>>();
+        colors.sort_by_key(|color| *color as usize);
+        colors.dedup();
+
+        Ok(Puzzle {
+            grid,
+            start,
+            end,
+            sensors,
+            emitters,
+            colors,
+            nodes: Vec::new(),
+            node_ids: HashMap::new(),
+            moves: HashMap::new(),
+        })
+    }
+
+    fn is_solid(&self, cell: Cell, toggled: Toggled) -> bool {
+        match cell {
+            Cell::Wall | Cell::Mirror => true,
+            Cell::Crystal { color, active } => crystal_active(color, active, toggled),
+            Cell::Empty | Cell::Spike | Cell::OneWay => false,
+        }
+    }
+
+    /// Whether a one-way platform cell holds up something whose bottom was at `bottom`.
+    fn holds_up(&self, cell_box: &Aabb, bottom: f32) -> bool {
+        bottom >= cell_box.max.y - 0.01
+    }
+
+    fn is_grounded(&self, body: &Body, toggled: Toggled) -> bool {
+        let bottom = body.bottom();
+        let feet = Aabb {
+            min: Vec2::new(body.pos.x - LYRA_HALF_SIZE.x + 0.1, bottom - 0.5),
+            max: Vec2::new(body.pos.x + LYRA_HALF_SIZE.x - 0.1, bottom),
+        };
+        self.grid.cells_in(&feet).any(|(cell, cell_box)| {
+            self.is_solid(cell, toggled) || cell == Cell::OneWay && self.holds_up(&cell_box, bottom)
+        })
+    }
+
+    /// A single fixed timestep of [`movement`](../../game/lyra/controller.rs) followed by the
+    /// physics step, for a grid of boxes.
+    fn step(
+        &self,
+        body: &mut Body,
+        toggled: Toggled,
+        direction: f32,
+        jump: bool,
+        jump_cut: bool,
+    ) -> StepResult {
+        if body.grounded {
+            body.coyote = COYOTE_TIME_TICKS;
+        }
+        if direction != 0. {
+            body.vel.x += direction * PLAYER_MOVE_VEL;
+        }
+        if jump {
+            body.should_jump = SHOULD_JUMP_TICKS;
+        }
+        if jump_cut && body.vel.y > 0. {
+            body.vel.y /= 3.;
+            body.boost = 0;
+            body.should_jump = 0;
+        }
+        if body.should_jump > 0 && body.coyote > 0 {
+            body.boost = JUMP_BOOST_TICKS;
+        }
+
+        if body.boost > 0 {
+            body.vel.y = PLAYER_JUMP_VEL;
+        } else if body.grounded && body.vel.y < 0.5 / 64. {
+            body.vel.y = 0.;
+        } else {
+            body.vel.y -= PLAYER_GRAVITY;
+        }
+        body.vel.y = body.vel.y.clamp(-PLAYER_MAX_Y_VEL, PLAYER_MAX_Y_VEL);
+        body.vel.x = body.vel.x.clamp(-PLAYER_MAX_H_VEL, PLAYER_MAX_H_VEL);
+        if direction == 0. {
+            body.vel.x *= 0.6;
+            if body.vel.x.abs() < 0.1 / 64. {
+                body.vel.x = 0.;
+            }
+        }
+        body.should_jump -= 1;
+        body.boost -= 1;
+        body.coyote -= 1;
+
+        // velocities never get close to the size of a cell, so nothing gets tunneled through
+        body.pos.x += body.vel.x;
+        let mut hit_wall = false;
+        for (cell, cell_box) in self.grid.cells_in(&body.aabb()) {
+            if !self.is_solid(cell, toggled) {
+                continue;
+            }
+            hit_wall = true;
+            if body.vel.x > 0. {
+                body.pos.x = body.pos.x.min(cell_box.min.x - LYRA_HALF_SIZE.x);
+            } else {
+                body.pos.x = body.pos.x.max(cell_box.max.x + LYRA_HALF_SIZE.x);
+            }
+        }
+        if hit_wall {
+            body.vel.x = 0.;
+        }
+
+        let bottom = body.bottom();
+        body.pos.y += body.vel.y;
+        for (cell, cell_box) in self.grid.cells_in(&body.aabb()) {
+            if body.vel.y > 0. && self.is_solid(cell, toggled) {
+                body.pos.y = body.pos.y.min(cell_box.min.y - LYRA_HALF_SIZE.y);
+                body.vel.y = 0.;
+            } else if body.vel.y <= 0.
+                && (self.is_solid(cell, toggled)
+                    || cell == Cell::OneWay && self.holds_up(&cell_box, bottom))
+            {
+                body.pos.y = body.pos.y.max(cell_box.max.y + LYRA_HALF_SIZE.y);
+                body.vel.y = 0.;
+            }
+        }
+        body.grounded = self.is_grounded(body, toggled);
+
+        let hurtbox = body.hurtbox();
+        let spiked = self.grid.cells_in(&hurtbox).any(|(cell, cell_box)| {
+            // roughly the triangle of the spike
+            let spike = Aabb {
+                min: cell_box.min + Vec2::new(2., 0.),
+                max: cell_box.max - Vec2::new(2., 2.),
+            };
+            cell == Cell::Spike && spike.overlaps(&hurtbox)
+        });
+        if spiked {
+            StepResult::Dead
+        } else if !self.grid.in_bounds(body.pos) {
+            StepResult::LeftLevel
+        } else {
+            StepResult::Alive
+        }
+    }
+
+    fn reaches_end(&self, body: &Body) -> bool {
+        let hurtbox = body.hurtbox();
+        self.end.iter().any(|end| end.overlaps(&hurtbox))
+    }
+
+    fn node_key(&self, body: &Body) -> IVec2 {
+        self.grid
+            .cell_at(Vec2::new(body.pos.x, body.bottom() - self.grid.size / 2.))
+    }
+
+    fn node_id(&mut self, body: &Body) -> usize {
+        let key = self.node_key(body);
+        if let Some(id) = self.node_ids.get(&key) {
+            return *id;
+        }
+        self.nodes.push(body.pos);
+        self.node_ids.insert(key, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    /// Lets Lyra fall from where she spawns.
+    fn start_node(&mut self) -> Result<usize, String> {
+        let mut body = Body::new(self.start + LYRA_COLLIDER_OFFSET);
+        for _ in 0..MAX_MOVE_TICKS {
+            if !matches!(self.step(&mut body, 0, 0., false, false), StepResult::Alive) {
+                break;
+            }
+            if body.grounded {
+                return Ok(self.node_id(&body));
+            }
+        }
+        Err("Lyra doesn't land anywhere after spawning".into())
+    }
+
+    fn moves(&mut self, toggled: Toggled, node: usize) -> &Moves {
+        if !self.moves.contains_key(&(toggled, node)) {
+            let moves = self.find_moves(toggled, node);
+            self.moves.insert((toggled, node), moves);
+        }
+        &self.moves[&(toggled, node)]
+    }
+
+    fn find_moves(&mut self, toggled: Toggled, node: usize) -> Moves {
+        let mut moves = Moves::default();
+        let start = Body {
+            grounded: true,
+            ..Body::new(self.nodes[node])
+        };
+        // crystals appeared where Lyra was standing
+        if self
+            .grid
+            .cells_in(&start.aabb())
+            .any(|(cell, _)| self.is_solid(cell, toggled))
+            || !self.is_grounded(&start, toggled)
+        {
+            return moves;
+        }
+        let start_key = self.node_key(&start);
+
+        for program in PROGRAMS {
+            let mut body = start;
+            for tick in 0..MAX_MOVE_TICKS {
+                let held = (program.held.0..program.held.1).contains(&tick);
+                let direction = if held { program.direction } else { 0. };
+                let jump = program.jump && tick == 0;
+                let jump_cut = program.jump_cut == Some(tick);
+                if !matches!(
+                    self.step(&mut body, toggled, direction, jump, jump_cut),
+                    StepResult::Alive
+                ) {
+                    break;
+                }
+                moves.reaches_end |= self.reaches_end(&body);
+                if body.grounded && self.node_key(&body) != start_key {
+                    let id = self.node_id(&body);
+                    if !moves.nodes.contains(&id) {
+                        moves.nodes.push(id);
+                    }
+                }
+                if body.grounded && body.vel == Vec2::ZERO && tick > 0 {
+                    break;
+                }
+            }
+        }
+        moves
+    }
+
+    fn reachable(&mut self, toggled: Toggled, from: usize) -> Reachable {
+        let mut reachable = Reachable::default();
+        let mut seen = HashSet::from([from]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(node) = queue.pop_front() {
+            reachable.nodes.push(node);
+            let moves = self.moves(toggled, node);
+            reachable.reaches_end |= moves.reaches_end;
+            for next in moves.nodes.clone() {
+                if seen.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        reachable.nodes.sort();
+        reachable
+    }
+
+    /// The first thing hit by a beam of `color`, mirroring the [`SpatialQuery`] ray cast in
+    /// `play_light_beam`.
+    fn cast_beam(
+        &self,
+        origin: Vec2,
+        dir: Vec2,
+        color: LightColor,
+        toggled: Toggled,
+        other_beams: &[BeamPath],
+    ) -> Option<(f32, Vec2, BeamHit)> {
+        let mut closest: Option<(f32, Vec2, BeamHit)> = None;
+        let mut consider = |hit: Option<(f32, Vec2)>, what: BeamHit| {
+            if let Some((t, normal)) = hit {
+                if closest.is_none_or(|(closest_t, _, _)| t < closest_t) {
+                    closest = Some((t, normal, what));
+                }
+            }
+        };
+
+        for (i, sensor) in self.sensors.iter().enumerate() {
+            consider(sensor.aabb.cast_ray(origin, dir), BeamHit::Sensor(i));
+        }
+
+        for beam in other_beams
+            .iter()
+            .filter(|beam| color.hits_beam(beam.color))
+        {
+            for segment in beam.points.windows(2) {
+                consider(
+                    cast_segment(origin, dir, segment[0], segment[1]),
+                    BeamHit::Beam {
+                        black: beam.color == LightColor::Black,
+                    },
+                );
+            }
+        }
+
+        // walk the grid cells along the ray in order, so the first cell hit is the closest one
+        let grid = &self.grid;
+        let mut cell = grid.cell_at(origin);
+        let step = IVec2::new(dir.x.signum() as i32, dir.y.signum() as i32);
+        let next_boundary = |cell: i32, step: i32| (cell + (step > 0) as i32) as f32 * grid.size;
+        let mut t_max = Vec2::new(
+            if dir.x.abs() < 1e-6 {
+                f32::INFINITY
+            } else {
+                (next_boundary(cell.x, step.x) - origin.x) / dir.x
+            },
+            if dir.y.abs() < 1e-6 {
+                f32::INFINITY
+            } else {
+                (next_boundary(cell.y, step.y) - origin.y) / dir.y
+            },
+        );
+        let t_delta = Vec2::new(grid.size / dir.x.abs(), grid.size / dir.y.abs());
+        loop {
+            let hit = grid
+                .beam_collider(cell.x, cell.y, color, toggled)
+                .and_then(|collider| collider.cast_ray(origin, dir));
+            if hit.is_some() {
+                let mirror = grid.cell(cell.x, cell.y) == Cell::Mirror;
+                consider(hit, BeamHit::Terrain { mirror });
+                break;
+            }
+            if t_max.x.min(t_max.y) > BEAM_LENGTH {
+                break;
+            }
+            if t_max.x < t_max.y {
+                cell.x += step.x;
+                t_max.x += t_delta.x;
+            } else {
+                cell.y += step.y;
+                t_max.y += t_delta.y;
+            }
+            let outside = (step.x < 0 && cell.x < 0)
+                || (step.x > 0 && cell.x >= grid.width)
+                || (step.y < 0 && cell.y < 0)
+                || (step.y > 0 && cell.y >= grid.height);
+            if outside {
+                break;
+            }
+        }
+
+        closest.filter(|(t, _, _)| *t <= BEAM_LENGTH)
+    }
+
+    /// Follows a beam through its bounces like `play_light_beam`.
+    fn trace(&self, source: &BeamSource, toggled: Toggled, other_beams: &[BeamPath]) -> BeamPath {
+        let mut path = BeamPath {
+            color: source.color,
+            points: vec![source.start],
+            sensors: Vec::new(),
+        };
+        let (mut pos, mut dir) = (source.start, source.dir);
+        let num_segments = source.color.num_bounces() + 1;
+        let mut extra_bounces_from_mirror = 0;
+        let mut i = 0;
+        while i < num_segments + extra_bounces_from_mirror && i < LIGHT_MAX_SEGMENTS {
+            let Some((t, normal, hit)) =
+                self.cast_beam(pos, dir, source.color, toggled, other_beams)
+            else {
+                path.points.push(pos + dir * BEAM_LENGTH);
+                break;
+            };
+            let point = pos + dir * t;
+            path.points.push(point);
+            dir = (dir - 2. * dir.dot(normal) * normal).normalize();
+            pos = point + dir * 0.01;
+            match hit {
+                BeamHit::Terrain { mirror: true } => extra_bounces_from_mirror += 1,
+                BeamHit::Sensor(sensor) => path.sensors.push(sensor),
+                BeamHit::Beam { black: true } => break,
+                _ => {}
+            }
+            i += 1;
+        }
+        path
+    }
+
+    fn shot_source(&self, shot: &Shot) -> BeamSource {
+        BeamSource {
+            color: shot.color,
+            start: self.nodes[shot.node] - LYRA_COLLIDER_OFFSET,
+            dir: Vec2::from_angle(shot.degrees.to_radians()),
+        }
+    }
+
+    /// Traces every beam until the sensors they hit toggle crystals that don't change their paths
+    /// anymore. Returns the toggled crystals and the active sensors of every configuration the
+    /// level ends up in: a single one, or several if crystals block the beams that toggle them and
+    /// keep flickering, which makes them usable for as long as the sensors take to deactivate.
+    fn settle(&self, shots: &[Shot]) -> Vec<(Toggled, Vec<bool>)> {
+        let sources = self
+            .emitters
+            .iter()
+            .copied()
+            .chain(shots.iter().map(|shot| self.shot_source(shot)))
+            .collect::<Vec<_>>();
+
+        let mut configurations = Vec::<(Toggled, Vec<bool>)>::new();
+        let mut toggled = 0;
+        // the next configuration only depends on the toggled crystals, so this always ends up in
+        // a cycle of configurations that were already seen
+        while !configurations.iter().any(|(seen, _)| *seen == toggled) {
+            let mut paths = Vec::<BeamPath>::new();
+            for source in sources.iter() {
+                let path = self.trace(source, toggled, &paths);
+                paths.push(path);
+            }
+            let mut hit_by: Vec<Colors> = vec![0; self.sensors.len()];
+            for path in paths.iter() {
+                for sensor in path.sensors.iter() {
+                    hit_by[*sensor] |= 1 << path.color as u8;
+                }
+            }
+            let active = self
+                .sensors
+                .iter()
+                .zip(hit_by)
+                .map(|(sensor, hit_by)| sensor.is_lit(hit_by))
+                .collect::<Vec<_>>();
+            configurations.push((toggled, active.clone()));
+            toggled = self.toggled_by(&active);
+        }
+        let cycle_start = configurations
+            .iter()
+            .position(|(seen, _)| *seen == toggled)
+            .expect("the loop only ends on a configuration that was seen");
+        configurations.split_off(cycle_start)
+    }
+
+    /// The crystals toggled by the `active` sensors. Like in the game, every sensor toggles when it
+    /// activates, and each group toggles once when its logic becomes true, so sensors that start
+    /// out active have toggled their crystals as soon as the level starts.
+    fn toggled_by(&self, active: &[bool]) -> Toggled {
+        let mut toggled = 0;
+        let mut groups: HashMap<i32, (SensorLogic, Option<usize>, Vec<bool>)> = HashMap::new();
+        for (sensor, active) in self.sensors.iter().zip(active.iter()) {
+            match sensor.group {
+                // validation makes sure the sensors of a group all toggle the same crystals
+                Some((id, logic)) => groups
+                    .entry(id)
+                    .or_insert_with(|| (logic, sensor.toggle_color, Vec::new()))
+                    .2
+                    .push(*active),
+                None if *active => {
+                    if let Some(color) = sensor.toggle_color {
+                        toggled ^= 1 << color;
+                    }
+                }
+                None => {}
+            }
+        }
+        let toggled_groups = groups
+            .into_values()
+            .filter(|(logic, _, states)| logic.eval(states.iter().copied()))
+            .filter_map(|(_, toggle_color, _)| toggle_color);
+        for color in toggled_groups {
+            toggled ^= 1 << color;
+        }
+        toggled
+    }
+
+    fn describe(&self, shot: &Shot) -> SolutionShot {
+        let pos = self.nodes[shot.node];
+        let bottom_cell = self
+            .grid
+            .cell_at(Vec2::new(pos.x, pos.y - LYRA_HALF_SIZE.y + 0.5));
+        SolutionShot {
+            color: LIGHT_COLORS[shot.color as usize],
+            grid: IVec2::new(bottom_cell.x, self.grid.height - 1 - bottom_cell.y),
+            degrees: shot.degrees,
+        }
+    }
+}
+
+/// The first intersection of a ray with a beam segment.
+fn cast_segment(origin: Vec2, dir: Vec2, a: Vec2, b: Vec2) -> Option<(f32, Vec2)> {
+    let segment = b - a;
+    let denominator = dir.perp_dot(segment);
+    if denominator.abs() < 1e-6 {
+        return None;
+    }
+    let t = (a - origin).perp_dot(segment) / denominator;
+    let u = (a - origin).perp_dot(dir) / denominator;
+    if t < 0. || !(0. ..=1.).contains(&u) {
+        return None;
+    }
+    let normal = segment.perp().normalize();
+    let normal = if normal.dot(dir) > 0. {
+        -normal
+    } else {
+        normal
+    };
+    Some((t, normal))
+}
+
+/// A beam of a solution, in LDtk coordinates.
+pub struct SolutionShot {
+    pub color: &'static str,
+    /// Grid cell of Lyra's feet when shooting.
+    pub grid: IVec2,
+    /// Counter-clockwise from the right.
+    pub degrees: f32,
+}
+
+impl fmt::Display for SolutionShot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} from [{}, {}] at {}°",
+            self.color, self.grid.x, self.grid.y, self.degrees
+        )
+    }
+}
+
+/// Whether the solver could check a level.
+pub enum SolverOutcome {
+    /// The level uses mechanics the solver doesn't model, listed here, so it got no verdict.
+    NotChecked(Vec<String>),
+    Checked(SolverReport),
+}
+
+/// What the solver found out about a level.
+pub struct SolverReport {
+    /// Beams to shoot, in order, to reach the `EndMarker` with as few beams as possible.
+    pub solution: Option<Vec<SolutionShot>>,
+    /// Whether the solution needs beams aimed without snapping.
+    pub free_aim: bool,
+    pub sensors: usize,
+    /// Grid cells of the sensors that are never active in any explored configuration.
+    pub unused_sensors: Vec<IVec2>,
+    /// Number of configurations explored.
+    pub states: usize,
+    /// Whether the search stopped at [`MAX_STATES`] before trying everything.
+    pub gave_up: bool,
+}
+
+/// A configuration of the level reached by shooting beams.
+struct State {
+    shots: Vec<Shot>,
+    reachable: Reachable,
+}
+
+struct Search {
+    solution: Option<Vec<Shot>>,
+    ever_active: Vec<bool>,
+    states: usize,
+}
+
+/// Breadth first search through the configurations reachable by shooting beams at `angles`, so
+/// that the first solution found uses as few beams as possible.
+fn search(puzzle: &mut Puzzle, start: usize, angles: &[f32]) -> Search {
+    let mut search = Search {
+        solution: None,
+        ever_active: vec![false; puzzle.sensors.len()],
+        states: 0,
+    };
+    let mut seen = HashSet::new();
+    let mut states = VecDeque::new();
+    for (toggled, active) in puzzle.settle(&[]) {
+        for (ever, now) in search.ever_active.iter_mut().zip(active.iter()) {
+            *ever |= *now;
+        }
+        let reachable = puzzle.reachable(toggled, start);
+        if seen.insert((0u8, toggled, active, reachable.nodes.clone())) {
+            states.push_back(State {
+                shots: Vec::new(),
+                reachable,
+            });
+        }
+    }
+
+    while let Some(state) = states.pop_front() {
+        search.states += 1;
+        if state.reachable.reaches_end {
+            search.solution = Some(state.shots);
+            break;
+        }
+        if search.states >= MAX_STATES {
+            break;
+        }
+
+        let used = state
+            .shots
+            .iter()
+            .fold(0u8, |used, shot| used | 1 << shot.color as u8);
+        for color in puzzle.colors.clone() {
+            if used & 1 << color as u8 != 0 {
+                continue;
+            }
+            // shots that end up in the same configuration only need to be explored once
+            let mut outcomes: HashMap<(Toggled, Vec<bool>), Vec<Vec<usize>>> = HashMap::new();
+            for &node in state.reachable.nodes.iter() {
+                for &degrees in angles {
+                    let mut shots = state.shots.clone();
+                    shots.push(Shot {
+                        color,
+                        node,
+                        degrees,
+                    });
+                    for (toggled, active) in puzzle.settle(&shots) {
+                        for (ever, now) in search.ever_active.iter_mut().zip(active.iter()) {
+                            *ever |= *now;
+                        }
+                        let regions = outcomes.entry((toggled, active.clone())).or_default();
+                        if regions
+                            .iter()
+                            .any(|region| region.binary_search(&node).is_ok())
+                        {
+                            continue;
+                        }
+                        let reachable = puzzle.reachable(toggled, node);
+                        regions.push(reachable.nodes.clone());
+                        if seen.insert((
+                            used | 1 << color as u8,
+                            toggled,
+                            active,
+                            reachable.nodes.clone(),
+                        )) {
+                            states.push_back(State {
+                                shots: shots.clone(),
+                                reachable,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+    search
+}
+
+/// The mechanics of a level that the solver doesn't model, which could make it report a shortcut
+/// that doesn't exist or miss a real solution.
+pub fn unmodeled(level: &Level) -> Vec<String> {
+    let mut unmodeled = Vec::new();
+    for entity in level_entities(level) {
+        let what = if UNMODELED_ENTITIES.contains(&entity.identifier.as_str()) {
+            entity.identifier.clone()
+        } else if entity.identifier == "LightSource"
+            && entity.get_int_field("signal_id").is_ok_and(|id| *id > 0)
+        {
+            "LightSource waiting on a signal".to_string()
+        } else if entity.identifier == "Sensor"
+            && entity
+                .get_enum_field("mode")
+                .is_ok_and(|mode| mode == "Latching")
+        {
+            "Latching sensor".to_string()
+        } else {
+            continue;
+        };
+        if !unmodeled.contains(&what) {
+            unmodeled.push(what);
+        }
+    }
+    let has_glass = level
+        .layer_instances
+        .iter()
+        .flatten()
+        .filter(|layer| layer.identifier == "Terrain")
+        .any(|layer| {
+            layer
+                .int_grid_csv
+                .iter()
+                .any(|value| *value == 17 || *value == 18)
+        });
+    if has_glass {
+        unmodeled.push("glass".to_string());
+    }
+    unmodeled
+}
+
+/// Searches for the fewest beams needed to reach the `EndMarker` of a level, first with snapped
+/// beams only, then aiming freely if that isn't enough. Levels with mechanics the solver doesn't
+/// model aren't searched.
+pub fn solve_level(level: &Level) -> Result<SolverOutcome, String> {
+    let unmodeled = unmodeled(level);
+    if !unmodeled.is_empty() {
+        return Ok(SolverOutcome::NotChecked(unmodeled));
+    }
+
+    let mut puzzle = Puzzle::new(level)?;
+    let start = puzzle.start_node()?;
+
+    let snapped = (0..NUM_INCREMENTS)
+        .map(|i| (i * 360) as f32 / NUM_INCREMENTS as f32)
+        .collect::<Vec<_>>();
+    let mut result = search(&mut puzzle, start, &snapped);
+    let mut free_aim = false;
+    if result.solution.is_none() {
+        let free = (0..(360. / FREE_AIM_STEP) as usize)
+            .map(|i| i as f32 * FREE_AIM_STEP)
+            .collect::<Vec<_>>();
+        result = search(&mut puzzle, start, &free);
+        free_aim = true;
+    }
+
+    Ok(SolverOutcome::Checked(SolverReport {
+        solution: result
+            .solution
+            .map(|shots| shots.iter().map(|shot| puzzle.describe(shot)).collect()),
+        free_aim,
+        sensors: puzzle.sensors.len(),
+        unused_sensors: puzzle
+            .sensors
+            .iter()
+            .zip(result.ever_active)
+            .filter(|(_, active)| !active)
+            .map(|(sensor, _)| sensor.grid)
+            .collect(),
+        states: result.states,
+        gave_up: result.states >= MAX_STATES,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A [`Puzzle`] without entities, on a grid drawn top row first like in LDtk: `#` walls, `^`
+    /// spikes, `a`/`b` active and inactive pink crystals.
+    fn puzzle(rows: &[&str]) -> Puzzle {
+        let cells = rows
+            .iter()
+            .flat_map(|row| row.chars())
+            .map(|c| match c {
+                '#' => Cell::Wall,
+                '^' => Cell::Spike,
+                'a' => Cell::from_int_grid(3),
+                'b' => Cell::from_int_grid(4),
+                _ => Cell::Empty,
+            })
+            .collect();
+        Puzzle {
+            grid: Grid {
+                width: rows[0].len() as i32,
+                height: rows.len() as i32,
+                size: 8.,
+                cells,
+            },
+            start: Vec2::ZERO,
+            end: Vec::new(),
+            sensors: Vec::new(),
+            emitters: Vec::new(),
+            colors: Vec::new(),
+            nodes: Vec::new(),
+            node_ids: HashMap::new(),
+            moves: HashMap::new(),
+        }
+    }
+
+    fn standing_at(x: f32) -> Body {
+        Body {
+            grounded: true,
+            ..Body::new(Vec2::new(x, 8. + LYRA_HALF_SIZE.y))
+        }
+    }
+
+    #[test]
+    fn cast_ray_enters_through_the_nearest_side() {
+        let aabb = Aabb::new(Vec2::new(10., 0.), Vec2::splat(2.));
+        assert_eq!(aabb.cast_ray(Vec2::ZERO, Vec2::X), Some((8., Vec2::NEG_X)));
+        assert_eq!(
+            aabb.cast_ray(Vec2::new(10., 10.), Vec2::NEG_Y),
+            Some((8., Vec2::Y))
+        );
+    }
+
+    #[test]
+    fn cast_ray_misses_boxes_behind_or_beside_it() {
+        let aabb = Aabb::new(Vec2::new(10., 0.), Vec2::splat(2.));
+        assert_eq!(aabb.cast_ray(Vec2::ZERO, Vec2::NEG_X), None);
+        assert_eq!(aabb.cast_ray(Vec2::new(0., 5.), Vec2::X), None);
+    }
+
+    #[test]
+    fn cast_ray_from_inside_hits_immediately() {
+        let aabb = Aabb::new(Vec2::new(10., 0.), Vec2::splat(2.));
+        assert_eq!(
+            aabb.cast_ray(Vec2::new(10., 0.), Vec2::X),
+            Some((0., Vec2::NEG_X))
+        );
+    }
+
+    #[test]
+    fn cast_segment_faces_the_ray() {
+        let hit = cast_segment(Vec2::ZERO, Vec2::X, Vec2::new(4., -1.), Vec2::new(4., 1.));
+        assert_eq!(hit, Some((4., Vec2::NEG_X)));
+        let hit = cast_segment(Vec2::ZERO, Vec2::X, Vec2::new(4., 1.), Vec2::new(4., -1.));
+        assert_eq!(hit, Some((4., Vec2::NEG_X)));
+    }
+
+    #[test]
+    fn cast_segment_misses_parallel_and_distant_segments() {
+        // parallel
+        assert_eq!(
+            cast_segment(Vec2::ZERO, Vec2::X, Vec2::new(0., 1.), Vec2::new(4., 1.)),
+            None
+        );
+        // past the end of the segment
+        assert_eq!(
+            cast_segment(Vec2::ZERO, Vec2::X, Vec2::new(4., 1.), Vec2::new(4., 3.)),
+            None
+        );
+        // behind the ray
+        assert_eq!(
+            cast_segment(Vec2::ZERO, Vec2::X, Vec2::new(-4., -1.), Vec2::new(-4., 1.)),
+            None
+        );
+    }
+
+    #[test]
+    fn step_walks_along_the_floor() {
+        let puzzle = puzzle(&["........", "........", "########"]);
+        let mut body = standing_at(16.);
+        assert!(matches!(
+            puzzle.step(&mut body, 0, 1., false, false),
+            StepResult::Alive
+        ));
+        assert_eq!(body.pos, Vec2::new(16. + PLAYER_MOVE_VEL, 16.));
+        assert!(body.grounded);
+    }
+
+    #[test]
+    fn step_stops_at_walls() {
+        let puzzle = puzzle(&["...#....", "...#....", "########"]);
+        let mut body = standing_at(18.);
+        for _ in 0..8 {
+            puzzle.step(&mut body, 0, 1., false, false);
+        }
+        assert_eq!(body.pos.x, 24. - LYRA_HALF_SIZE.x);
+        assert_eq!(body.vel.x, 0.);
+    }
+
+    #[test]
+    fn step_jumps_off_the_ground() {
+        let puzzle = puzzle(&["........", "........", "........", "########"]);
+        let mut body = standing_at(16.);
+        puzzle.step(&mut body, 0, 0., true, false);
+        assert_eq!(body.vel.y, PLAYER_JUMP_VEL);
+        assert!(body.pos.y > 16.);
+        assert!(!body.grounded);
+    }
+
+    #[test]
+    fn step_dies_on_spikes() {
+        let puzzle = puzzle(&["........", "........", "...^....", "########"]);
+        let mut body = standing_at(28.);
+        assert!(matches!(
+            puzzle.step(&mut body, 0, 0., false, false),
+            StepResult::Dead
+        ));
+    }
+
+    #[test]
+    fn step_leaves_the_level() {
+        let puzzle = puzzle(&["........", "........"]);
+        let mut body = Body::new(Vec2::new(16., 0.1));
+        assert!(matches!(
+            puzzle.step(&mut body, 0, 0., false, false),
+            StepResult::LeftLevel
+        ));
+    }
+
+    fn lit_sensor_puzzle(rows: &[&str]) -> Puzzle {
+        let mut puzzle = puzzle(rows);
+        puzzle.sensors.push(Sensor {
+            grid: IVec2::new(6, 1),
+            aabb: Aabb::new(Vec2::new(52., 12.), Vec2::splat(4.)),
+            toggle_color: Some(0),
+            required_colors: 0,
+            inverted: false,
+            group: None,
+        });
+        puzzle.emitters.push(BeamSource {
+            color: LightColor::Green,
+            start: Vec2::new(4., 12.),
+            dir: Vec2::X,
+        });
+        puzzle
+    }
+
+    #[test]
+    fn settle_toggles_the_crystals_of_lit_sensors() {
+        let puzzle = lit_sensor_puzzle(&["........", "........", "........"]);
+        assert_eq!(puzzle.settle(&[]), vec![(1, vec![true])]);
+    }
+
+    #[test]
+    fn settle_flickers_when_crystals_block_their_sensor() {
+        let puzzle = lit_sensor_puzzle(&["........", "...b....", "........"]);
+        assert_eq!(puzzle.settle(&[]), vec![(0, vec![true]), (1, vec![false])]);
+    }
+
+    #[test]
+    fn settle_ignores_sensors_that_dont_toggle_crystals() {
+        let mut puzzle = lit_sensor_puzzle(&["........", "...b....", "........"]);
+        puzzle.sensors[0].toggle_color = None;
+        assert_eq!(puzzle.settle(&[]), vec![(0, vec![true])]);
+    }
+
+    #[test]
+    fn settle_needs_every_required_color() {
+        let mut puzzle = lit_sensor_puzzle(&["........", "........", "........"]);
+        puzzle.sensors[0].required_colors = 1 << LightColor::Green as u8;
+        assert_eq!(puzzle.settle(&[]), vec![(1, vec![true])]);
+
+        puzzle.sensors[0].required_colors |= 1 << LightColor::Purple as u8;
+        assert_eq!(puzzle.settle(&[]), vec![(0, vec![false])]);
+    }
+
+    #[test]
+    fn settle_toggles_inverted_sensors_while_unlit() {
+        let mut puzzle = lit_sensor_puzzle(&["........", "........", "........"]);
+        puzzle.sensors[0].inverted = true;
+        assert_eq!(puzzle.settle(&[]), vec![(0, vec![false])]);
+
+        puzzle.emitters.clear();
+        assert_eq!(puzzle.settle(&[]), vec![(1, vec![true])]);
+    }
+
+    #[test]
+    fn settle_toggles_groups_by_their_logic() {
+        let mut puzzle = lit_sensor_puzzle(&["........", "........", "........"]);
+        // a second sensor of the group that no beam reaches
+        puzzle.sensors.push(Sensor {
+            grid: IVec2::new(6, 0),
+            aabb: Aabb::new(Vec2::new(52., 4.), Vec2::splat(4.)),
+            ..puzzle.sensors[0].clone()
+        });
+
+        for (logic, toggled) in [
+            (SensorLogic::Or, 1),
+            (SensorLogic::And, 0),
+            (SensorLogic::Xor, 1),
+        ] {
+            for sensor in puzzle.sensors.iter_mut() {
+                sensor.group = Some((1, logic));
+            }
+            assert_eq!(puzzle.settle(&[]), vec![(toggled, vec![true, false])]);
+        }
+    }
+}
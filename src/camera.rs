@@ -20,6 +20,7 @@ pub const CAMERA_HEIGHT: u32 = 180;
 pub const TERRAIN_LAYER: RenderLayers = RenderLayers::layer(0);
 pub const LYRA_LAYER: RenderLayers = RenderLayers::layer(1);
 pub const HIGHRES_LAYER: RenderLayers = RenderLayers::layer(2);
+pub const GHOST_LAYER: RenderLayers = RenderLayers::layer(3);
 
 /// The [`Plugin`] responsible for handling anything Camera related.
 pub struct CameraPlugin;
@@ -1,6 +1,10 @@
+use std::collections::BTreeMap;
+
 use bevy::prelude::*;
 use serde::Deserialize;
 
+use crate::input::{Binding, Bindings, InputAction};
+
 pub struct ConfigPlugin;
 
 impl Plugin for ConfigPlugin {
@@ -17,6 +21,8 @@ impl Plugin for ConfigPlugin {
 pub struct Config {
     pub level_config: LevelConfig,
     pub debug_config: DebugConfig,
+    #[serde(default)]
+    pub controls_config: ControlsConfig,
 }
 
 impl Default for Config {
@@ -26,6 +32,7 @@ impl Default for Config {
                 level_path: "levels/lightborne.ldtk".into(),
             },
             debug_config: DebugConfig::default(),
+            controls_config: ControlsConfig::default(),
         }
     }
 }
@@ -33,9 +40,69 @@ impl Default for Config {
 #[derive(Deserialize, Default)]
 pub struct DebugConfig {
     pub unlock_levels: bool,
+    /// Write the inputs of every level attempt to `recordings/<LevelId>_<attempt>.toml`.
+    #[serde(default)]
+    pub record_inputs: bool,
+    /// Play back a recording made with `record_inputs` instead of reading live inputs.
+    #[serde(default)]
+    pub replay_path: Option<String>,
 }
 
 #[derive(Deserialize)]
 pub struct LevelConfig {
     pub level_path: String,
 }
+
+/// Overrides for the default [`InputMap`](crate::input::InputMap). Each key takes either a single
+/// binding or a list of them. Unknown keys, like the `key_up` and `key_down` of older config
+/// files, are ignored.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct ControlsConfig {
+    pub key_left: Option<Bindings>,
+    pub key_right: Option<Bindings>,
+    pub key_jump: Option<Bindings>,
+    pub key_crouch: Option<Bindings>,
+    pub key_snap: Option<Bindings>,
+    pub key_aim: Option<Bindings>,
+    pub key_cancel_aim: Option<Bindings>,
+    pub key_restart: Option<Bindings>,
+    pub key_rewind: Option<Bindings>,
+    pub key_pause: Option<Bindings>,
+    pub key_confirm: Option<Bindings>,
+    pub key_next_color: Option<Bindings>,
+    pub key_prev_color: Option<Bindings>,
+    pub key_green: Option<Bindings>,
+    pub key_purple: Option<Bindings>,
+    pub key_white: Option<Bindings>,
+    pub key_blue: Option<Bindings>,
+    pub key_black: Option<Bindings>,
+}
+
+impl ControlsConfig {
+    pub fn bindings(&self) -> BTreeMap<InputAction, Vec<Binding>> {
+        [
+            (InputAction::MoveLeft, &self.key_left),
+            (InputAction::MoveRight, &self.key_right),
+            (InputAction::Jump, &self.key_jump),
+            (InputAction::Crouch, &self.key_crouch),
+            (InputAction::Snap, &self.key_snap),
+            (InputAction::Aim, &self.key_aim),
+            (InputAction::CancelAim, &self.key_cancel_aim),
+            (InputAction::Restart, &self.key_restart),
+            (InputAction::Rewind, &self.key_rewind),
+            (InputAction::Pause, &self.key_pause),
+            (InputAction::Confirm, &self.key_confirm),
+            (InputAction::NextColor, &self.key_next_color),
+            (InputAction::PrevColor, &self.key_prev_color),
+            (InputAction::Green, &self.key_green),
+            (InputAction::Purple, &self.key_purple),
+            (InputAction::White, &self.key_white),
+            (InputAction::Blue, &self.key_blue),
+            (InputAction::Black, &self.key_black),
+        ]
+        .into_iter()
+        .filter_map(|(action, bindings)| Some((action, bindings.clone()?.into())))
+        .collect()
+    }
+}
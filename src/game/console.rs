@@ -0,0 +1,344 @@
+use avian2d::prelude::{CollisionLayers, LayerMask, LinearVelocity};
+use bevy::{
+    input::keyboard::{Key, KeyboardInput},
+    prelude::*,
+};
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::{
+    game::{
+        defs::{
+            crystal::{CrystalColor, CrystalToggleEvent},
+            shard::CrystalShardMods,
+        },
+        light::LightColor,
+        lyra::{beam::BeamAction, controller::movement, kill::KillPlayer, Lyra, LyraHurtBox},
+        Layers, LevelSystems,
+    },
+    input::{ActionInput, InputAction},
+    ldtk::{LdtkLevelParam, LevelExt},
+    shared::{AnimationState, GameState, PlayState, ResetLevels},
+    ui::UiFont,
+};
+
+/// Number of lines of output kept in the console.
+const CONSOLE_LOG_LINES: usize = 8;
+/// Speed Lyra flies at with noclip on, in pixels per second.
+const NOCLIP_SPEED: f32 = 160.;
+
+const LIGHT_COLORS: [LightColor; 5] = [
+    LightColor::Green,
+    LightColor::Purple,
+    LightColor::White,
+    LightColor::Blue,
+    LightColor::Black,
+];
+
+const CRYSTAL_COLORS: [CrystalColor; 4] = [
+    CrystalColor::Pink,
+    CrystalColor::Red,
+    CrystalColor::White,
+    CrystalColor::Blue,
+];
+
+const HELP: &str = "level <LevelId>, give <LightColor>, toggle <CrystalColor>, kill, noclip, \
+    timescale [speed], reset";
+
+/// [`Plugin`] for the in-game console, only built with the `dev_mode` feature. The backquote key
+/// opens it and `help` lists its commands.
+pub struct DevConsolePlugin;
+
+impl Plugin for DevConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DevConsole>();
+        app.add_systems(
+            Update,
+            (
+                toggle_dev_console,
+                type_in_dev_console.run_if(dev_console_open),
+            )
+                .chain()
+                .run_if(in_state(GameState::InGame)),
+        );
+        // NOTE: commands run once the console has unfrozen the game, since killing the player
+        // does nothing outside of PlayState::Playing
+        app.add_systems(
+            Update,
+            run_console_command
+                .after(type_in_dev_console)
+                .run_if(in_state(PlayState::Playing)),
+        );
+        app.add_systems(
+            FixedUpdate,
+            fly_noclip
+                .after(movement)
+                .in_set(LevelSystems::Simulation)
+                .run_if(noclip_enabled),
+        );
+        app.add_systems(OnExit(GameState::InGame), close_dev_console);
+    }
+}
+
+#[derive(Resource, Default)]
+struct DevConsole {
+    open: bool,
+    input: String,
+    /// Command entered while the console was open, run once the game resumes.
+    pending: Option<String>,
+    log: Vec<String>,
+    /// Collision filters of Lyra's collider and hurtbox from before noclip was turned on.
+    noclip: Option<(LayerMask, LayerMask)>,
+}
+
+impl DevConsole {
+    fn print(&mut self, line: String) {
+        self.log.push(line);
+        if self.log.len() > CONSOLE_LOG_LINES {
+            self.log.remove(0);
+        }
+    }
+}
+
+#[derive(Component)]
+struct DevConsoleUi;
+
+fn dev_console_open(console: Res<DevConsole>) -> bool {
+    console.open
+}
+
+fn noclip_enabled(console: Res<DevConsole>) -> bool {
+    console.noclip.is_some()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn toggle_dev_console(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut console: ResMut<DevConsole>,
+    play_state: Res<State<PlayState>>,
+    mut next_play_state: ResMut<NextState<PlayState>>,
+    mut next_anim_state: ResMut<NextState<AnimationState>>,
+    ui_font: Res<UiFont>,
+    q_ui: Query<Entity, With<DevConsoleUi>>,
+) {
+    if !keys.just_pressed(KeyCode::Backquote) {
+        return;
+    }
+    if console.open {
+        console.open = false;
+        next_play_state.set(PlayState::Playing);
+        for entity in q_ui.iter() {
+            commands.entity(entity).despawn();
+        }
+        return;
+    }
+    if *play_state.get() != PlayState::Playing {
+        return;
+    }
+
+    console.open = true;
+    console.input.clear();
+    // NOTE: freezes the level like the level editor does, so typing doesn't move Lyra
+    next_play_state.set(PlayState::Animating);
+    next_anim_state.set(AnimationState::Frozen);
+    commands
+        .spawn(DevConsoleUi)
+        .insert(Text::new(""))
+        .insert(Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(16.),
+            left: Val::Px(16.),
+            padding: UiRect::all(Val::Px(8.)),
+            ..default()
+        })
+        .insert(BackgroundColor(Color::BLACK.with_alpha(0.6)))
+        .insert(ui_font.text_font().with_font_size(20.));
+}
+
+fn type_in_dev_console(
+    mut commands: Commands,
+    mut ev_keyboard: MessageReader<KeyboardInput>,
+    mut console: ResMut<DevConsole>,
+    mut next_play_state: ResMut<NextState<PlayState>>,
+    mut q_ui: Query<(Entity, &mut Text), With<DevConsoleUi>>,
+) {
+    for ev in ev_keyboard.read() {
+        if !ev.state.is_pressed() || ev.key_code == KeyCode::Backquote {
+            continue;
+        }
+        match &ev.logical_key {
+            Key::Enter | Key::Escape => {
+                if ev.logical_key == Key::Enter && !console.input.trim().is_empty() {
+                    console.pending = Some(std::mem::take(&mut console.input));
+                }
+                console.open = false;
+                next_play_state.set(PlayState::Playing);
+                for (entity, _) in q_ui.iter() {
+                    commands.entity(entity).despawn();
+                }
+                return;
+            }
+            Key::Backspace => {
+                console.input.pop();
+            }
+            _ => {
+                if let Some(text) = &ev.text {
+                    console
+                        .input
+                        .extend(text.chars().filter(|c| !c.is_control()));
+                }
+            }
+        }
+    }
+
+    if let Ok((_, mut text)) = q_ui.single_mut() {
+        let mut lines = console.log.clone();
+        lines.push(format!("> {}_", console.input));
+        text.0 = lines.join("\n");
+    }
+}
+
+fn close_dev_console(
+    mut commands: Commands,
+    mut console: ResMut<DevConsole>,
+    q_ui: Query<Entity, With<DevConsoleUi>>,
+) {
+    console.open = false;
+    console.pending = None;
+    // Lyra is despawned with the level, so her collision layers don't need restoring
+    console.noclip = None;
+    for entity in q_ui.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn parse_color<T: std::fmt::Debug + Copy>(colors: &[T], name: &str) -> Result<T, String> {
+    colors
+        .iter()
+        .find(|color| format!("{:?}", color).eq_ignore_ascii_case(name))
+        .copied()
+        .ok_or_else(|| format!("Unknown color {}, expected one of {:?}", name, colors))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_console_command(
+    mut commands: Commands,
+    mut console: ResMut<DevConsole>,
+    mut ldtk_level_param: LdtkLevelParam,
+    mut shard_mods: ResMut<CrystalShardMods>,
+    mut beam_actions: MessageWriter<BeamAction>,
+    mut time: ResMut<Time<Virtual>>,
+    mut q_lyra: Query<&mut CollisionLayers, With<Lyra>>,
+    mut q_hurtbox: Query<&mut CollisionLayers, (With<LyraHurtBox>, Without<Lyra>)>,
+) {
+    let Some(line) = console.pending.take() else {
+        return;
+    };
+    info!("Console command: {}", line);
+    console.print(format!("> {}", line));
+
+    let mut args = line.split_whitespace();
+    let name = args.next().unwrap_or_default();
+    let arg = args.next();
+    let result = match (name, arg) {
+        ("help", _) => Ok(HELP.to_string()),
+        ("level", Some(level_id)) => {
+            let level_iid = ldtk_level_param
+                .ldtk_param
+                .project()
+                .expect("Level data must exist")
+                .json_data()
+                .levels
+                .iter()
+                .find(|level| level.level_id() == level_id)
+                .map(|level| LevelIid::new(level.iid.clone()));
+            match level_iid {
+                Some(level_iid) => {
+                    // NOTE: the kill animation moves Lyra to the start flag of the newly selected
+                    // level and resets it once the screen is black
+                    *ldtk_level_param.level_selection = LevelSelection::Iid(level_iid);
+                    commands.trigger(KillPlayer { restart: true });
+                    Ok(format!("Moving to level {}", level_id))
+                }
+                None => Err(format!("No level with LevelId {}", level_id)),
+            }
+        }
+        ("give", Some(color)) => parse_color(&LIGHT_COLORS, color).map(|color| {
+            // NOTE: lasts until the level is reset, like picking up a crystal shard
+            shard_mods.0[color] = true;
+            beam_actions.write(BeamAction::SwitchColor(Some(color)));
+            format!("Gave {:?} light", color)
+        }),
+        ("toggle", Some(color)) => parse_color(&CRYSTAL_COLORS, color).and_then(|color| {
+            let level = ldtk_level_param
+                .cur_iid()
+                .ok_or_else(|| "No level is loaded".to_string())?;
+            commands.trigger(CrystalToggleEvent { level, color });
+            Ok(format!("Toggled {:?} crystals", color))
+        }),
+        ("kill", None) => {
+            commands.trigger(KillPlayer { restart: false });
+            Ok("Killed Lyra".to_string())
+        }
+        ("noclip", None) => {
+            match (q_lyra.single_mut(), q_hurtbox.single_mut()) {
+                (Ok(mut lyra_layers), Ok(mut hurtbox_layers)) => match console.noclip.take() {
+                    Some((lyra_filters, hurtbox_filters)) => {
+                        lyra_layers.filters = lyra_filters;
+                        hurtbox_layers.filters = hurtbox_filters;
+                        Ok("Noclip off".to_string())
+                    }
+                    None => {
+                        console.noclip = Some((lyra_layers.filters, hurtbox_layers.filters));
+                        lyra_layers.filters = LayerMask::NONE;
+                        // NOTE: sensor boxes are kept so level completion still works
+                        hurtbox_layers.filters &= LayerMask::from(Layers::SensorBox);
+                        Ok("Noclip on".to_string())
+                    }
+                },
+                _ => Err("Lyra isn't in the level".to_string()),
+            }
+        }
+        ("timescale", None) => Ok(format!("Timescale is {}", time.relative_speed())),
+        ("timescale", Some(speed)) => match speed.parse::<f32>() {
+            Ok(speed) if speed > 0. => {
+                time.set_relative_speed(speed);
+                Ok(format!("Timescale set to {}", speed))
+            }
+            _ => Err(format!(
+                "Timescale needs to be a positive number, got {}",
+                speed
+            )),
+        },
+        ("reset", None) => {
+            commands.trigger(ResetLevels);
+            Ok("Reset the level".to_string())
+        }
+        _ => Err(format!(
+            "Unknown command {}, expected one of {}",
+            line.trim(),
+            HELP
+        )),
+    };
+
+    match result {
+        Ok(output) => {
+            info!("{}", output);
+            console.print(output);
+        }
+        Err(error) => {
+            warn!("{}", error);
+            console.print(error);
+        }
+    }
+}
+
+fn fly_noclip(input: ActionInput, mut lyra: Single<&mut LinearVelocity, With<Lyra>>) {
+    let axis = |positive, negative| {
+        input.pressed(positive) as i32 as f32 - input.pressed(negative) as i32 as f32
+    };
+    lyra.0 = Vec2::new(
+        axis(InputAction::MoveRight, InputAction::MoveLeft),
+        axis(InputAction::Jump, InputAction::Crouch),
+    ) * NOCLIP_SPEED;
+}
@@ -0,0 +1,36 @@
+// NOTE: only depends on `bevy::math`, so that `lightborne-lint` can include this file too.
+
+use bevy::math::Vec2;
+
+/// The number of [`FixedUpdate`](bevy::app::FixedUpdate) steps the player can jump for after
+/// pressing the spacebar.
+pub const SHOULD_JUMP_TICKS: isize = 8;
+/// The number of [`FixedUpdate`](bevy::app::FixedUpdate) steps the player can jump for after
+/// falling off an edge.
+pub const COYOTE_TIME_TICKS: isize = 5;
+/// The number of [`FixedUpdate`](bevy::app::FixedUpdate) steps the player should receive upward
+/// velocity for.
+pub const JUMP_BOOST_TICKS: isize = 2;
+
+/// Max player horizontal velocity.
+pub const PLAYER_MAX_H_VEL: f32 = 1.5;
+/// Max player vertical velocity.
+pub const PLAYER_MAX_Y_VEL: f32 = 5.;
+/// The positive y velocity added to the player every jump boost tick.
+pub const PLAYER_JUMP_VEL: f32 = 2.2;
+/// The x velocity added to the player when A/D is held.
+pub const PLAYER_MOVE_VEL: f32 = 0.4;
+/// The y velocity subtracted from the player due to gravity.
+pub const PLAYER_GRAVITY: f32 = 0.15;
+
+/// How far above the `Start` flag Lyra spawns.
+pub const LYRA_RESPAWN_EPSILON: f32 = 3.0;
+/// Offset of Lyra's collider and hurtbox from her transform, which beams are shot from.
+pub const LYRA_COLLIDER_OFFSET: Vec2 = Vec2::new(0.0, -2.0);
+pub const LYRA_COLLIDER_SIZE: Vec2 = Vec2::new(12.0, 16.0);
+pub const LYRA_HURTBOX_SIZE: Vec2 = Vec2::new(6.0, 8.0);
+
+/// The number of angle increments for light beam alignment.
+pub const NUM_INCREMENTS: i32 = 16;
+/// The most segments a beam can have, including the extra bounces off mirrors.
+pub const LIGHT_MAX_SEGMENTS: usize = 15;
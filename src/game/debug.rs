@@ -0,0 +1,320 @@
+use avian2d::prelude::*;
+use bevy::{gizmos::config::GizmoConfigStore, prelude::*};
+
+use crate::{
+    game::{
+        defs::{
+            crystal::{CrystalCache, CrystalGroup},
+            one_way_platform::OneWayPlatform,
+            sensor::{ButtonColor, LightSensor},
+        },
+        light::{segments::PrevLightBeamPlayback, LightBeamSource},
+        lyra::{controller::MovementInfo, Lyra},
+        Layers,
+    },
+    ldtk::LdtkLevelParam,
+    shared::GameState,
+    ui::UiFont,
+};
+
+/// [`Plugin`] for the debug overlay, only built with the `dev_mode` feature. F3 cycles through its
+/// views.
+pub struct DebugOverlayPlugin;
+
+impl Plugin for DebugOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(PhysicsDebugPlugin);
+        app.init_resource::<DebugView>();
+        app.add_systems(Startup, apply_debug_view);
+        app.add_systems(
+            Update,
+            (
+                cycle_debug_view,
+                apply_debug_view.run_if(resource_changed::<DebugView>),
+                color_colliders_by_layer,
+            )
+                .chain(),
+        );
+        app.add_systems(
+            Update,
+            (
+                draw_physics_view.run_if(resource_equals(DebugView::Physics)),
+                draw_light_view.run_if(resource_equals(DebugView::Light)),
+                draw_lyra_view.run_if(resource_equals(DebugView::Lyra)),
+            )
+                .after(color_colliders_by_layer)
+                .run_if(in_state(GameState::InGame)),
+        );
+    }
+}
+
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugView {
+    #[default]
+    Off,
+    /// Colliders colored by their first [`Layers`] membership, and what one-way platforms let
+    /// through.
+    Physics,
+    /// Light beam intersections, sensors and crystal groups.
+    Light,
+    /// Lyra's movement state.
+    Lyra,
+}
+
+impl DebugView {
+    fn next(self) -> Self {
+        match self {
+            DebugView::Off => DebugView::Physics,
+            DebugView::Physics => DebugView::Light,
+            DebugView::Light => DebugView::Lyra,
+            DebugView::Lyra => DebugView::Off,
+        }
+    }
+}
+
+#[derive(Component)]
+struct DebugPanel;
+
+/// Colors of the collision layers, in order of precedence when a collider is in more than one.
+const LAYER_COLORS: [(Layers, &str, Color); 12] = [
+    (
+        Layers::PlayerCollider,
+        "PlayerCollider",
+        Color::srgb(1.0, 1.0, 0.0),
+    ),
+    (
+        Layers::PlayerHurtbox,
+        "PlayerHurtbox",
+        Color::srgb(1.0, 0.5, 0.0),
+    ),
+    (Layers::Spike, "Spike", Color::srgb(1.0, 0.0, 0.0)),
+    (Layers::DangerBox, "DangerBox", Color::srgb(0.8, 0.0, 0.4)),
+    (
+        Layers::LightSensor,
+        "LightSensor",
+        Color::srgb(0.0, 1.0, 0.5),
+    ),
+    (Layers::SensorBox, "SensorBox", Color::srgb(0.0, 0.6, 0.3)),
+    (
+        Layers::BlueCrystal,
+        "BlueCrystal",
+        Color::srgb(0.3, 0.6, 1.0),
+    ),
+    (Layers::Terrain, "Terrain", Color::srgb(0.6, 0.6, 0.6)),
+    (Layers::LightRay, "LightRay", Color::srgb(0.6, 1.0, 0.6)),
+    (Layers::WhiteRay, "WhiteRay", Color::srgb(1.0, 1.0, 1.0)),
+    (Layers::BlueRay, "BlueRay", Color::srgb(0.5, 0.8, 1.0)),
+    (Layers::BlackRay, "BlackRay", Color::srgb(0.3, 0.3, 0.3)),
+];
+/// Color of colliders without any of the [`LAYER_COLORS`].
+const UNKNOWN_LAYER_COLOR: Color = Color::srgb(1.0, 0.0, 1.0);
+
+fn cycle_debug_view(keys: Res<ButtonInput<KeyCode>>, mut view: ResMut<DebugView>) {
+    if keys.just_pressed(KeyCode::F3) {
+        *view = view.next();
+        info!("Debug view: {:?}", *view);
+    }
+}
+
+fn apply_debug_view(
+    mut commands: Commands,
+    view: Res<DebugView>,
+    mut config_store: ResMut<GizmoConfigStore>,
+    ui_font: Option<Res<UiFont>>,
+    q_panel: Query<Entity, With<DebugPanel>>,
+) {
+    config_store.config_mut::<PhysicsGizmos>().0.enabled = *view == DebugView::Physics;
+
+    for entity in q_panel.iter() {
+        commands.entity(entity).despawn();
+    }
+    let (DebugView::Physics | DebugView::Light | DebugView::Lyra) = *view else {
+        return;
+    };
+    let Some(ui_font) = ui_font else {
+        return;
+    };
+    commands
+        .spawn(DebugPanel)
+        .insert(Text::new(""))
+        .insert(Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(16.),
+            right: Val::Px(16.),
+            max_width: Val::Percent(40.),
+            ..default()
+        })
+        .insert(BackgroundColor(Color::BLACK.with_alpha(0.6)))
+        .insert(ui_font.text_font().with_font_size(18.));
+}
+
+fn color_colliders_by_layer(
+    mut commands: Commands,
+    q_colliders: Query<(Entity, &CollisionLayers), Changed<CollisionLayers>>,
+) {
+    for (entity, layers) in q_colliders.iter() {
+        let color = LAYER_COLORS
+            .iter()
+            .find(|(layer, _, _)| layers.memberships.0 & layer.to_bits() != 0)
+            .map_or(UNKNOWN_LAYER_COLOR, |(_, _, color)| *color);
+        commands
+            .entity(entity)
+            .insert(DebugRender::default().with_collider_color(color));
+    }
+}
+
+fn set_panel_text(q_panel: &mut Query<&mut Text, With<DebugPanel>>, text: String) {
+    if let Ok(mut panel) = q_panel.single_mut() {
+        panel.0 = text;
+    }
+}
+
+fn draw_physics_view(
+    mut gizmos: Gizmos,
+    q_platforms: Query<(&OneWayPlatform, &GlobalTransform)>,
+    q_transforms: Query<&GlobalTransform>,
+    mut q_panel: Query<&mut Text, With<DebugPanel>>,
+) {
+    let mut text = String::from("Physics (F3)\n");
+    for (_, name, color) in LAYER_COLORS.iter() {
+        let Srgba {
+            red, green, blue, ..
+        } = color.to_srgba();
+        text.push_str(&format!(
+            "{}: rgb({:.1}, {:.1}, {:.1})\n",
+            name, red, green, blue
+        ));
+    }
+
+    let mut intersecting = 0;
+    for (platform, platform_transform) in q_platforms.iter() {
+        for entity in platform.intersecting() {
+            intersecting += 1;
+            let Ok(transform) = q_transforms.get(entity) else {
+                continue;
+            };
+            gizmos.line_2d(
+                platform_transform.translation().xy(),
+                transform.translation().xy(),
+                Color::srgb(1.0, 0.5, 0.0),
+            );
+        }
+    }
+    text.push_str(&format!(
+        "one-way platforms passed through: {}",
+        intersecting
+    ));
+    set_panel_text(&mut q_panel, text);
+}
+
+fn draw_light_view(
+    mut gizmos: Gizmos,
+    q_sources: Query<(&LightBeamSource, &PrevLightBeamPlayback)>,
+    q_sensors: Query<(&LightSensor, &GlobalTransform)>,
+    q_crystal_groups: Query<(&CrystalGroup, &GlobalTransform)>,
+    crystal_cache: Res<CrystalCache>,
+    ldtk_level_param: LdtkLevelParam,
+    mut q_panel: Query<&mut Text, With<DebugPanel>>,
+) {
+    let mut text = String::from("Light (F3)\n");
+
+    for (source, playback) in q_sources.iter() {
+        let color = source.color.light_beam_color();
+        text.push_str(&format!(
+            "{:?} beam, traveled {:.1}\n",
+            source.color, source.time_traveled
+        ));
+        gizmos.circle_2d(source.start_pos, 2., color);
+        for (i, intersection) in playback.intersections.iter().enumerate() {
+            let Some(intersection) = intersection else {
+                continue;
+            };
+            gizmos.circle_2d(intersection.point, 2., color);
+            text.push_str(&format!(
+                "  #{} {:?} at ({:.1}, {:.1}), t = {:.1}\n",
+                i,
+                intersection.entity,
+                intersection.point.x,
+                intersection.point.y,
+                intersection.time
+            ));
+        }
+    }
+
+    for (sensor, transform) in q_sensors.iter() {
+        let pos = transform.translation().xy();
+        let color = ButtonColor::from(sensor.toggle_color);
+        // meter fills up from the bottom of the sensor
+        gizmos.rect_2d(pos, Vec2::splat(10.), color);
+        gizmos.line_2d(
+            pos + Vec2::new(6., -5.),
+            pos + Vec2::new(6., -5. + 10. * sensor.meter),
+            color,
+        );
+        let hit_by = sensor
+            .hit_by
+            .iter()
+            .filter(|(_, hit)| **hit)
+            .map(|(color, _)| format!("{:?}", color))
+            .collect::<Vec<_>>();
+        text.push_str(&format!(
+            "{:?} sensor at ({:.0}, {:.0}): meter {:.2}{}, hit by [{}]\n",
+            sensor.toggle_color,
+            pos.x,
+            pos.y,
+            sensor.meter,
+            if sensor.is_active { ", active" } else { "" },
+            hit_by.join(", ")
+        ));
+    }
+
+    if let Some(iid) = ldtk_level_param.cur_iid() {
+        for (color, groups) in crystal_cache.groups(&iid) {
+            let active = groups
+                .iter()
+                .filter_map(|group| q_crystal_groups.get(*group).ok())
+                .inspect(|(group, transform)| {
+                    let mark = if group.0.active { 3. } else { 1.5 };
+                    gizmos.circle_2d(
+                        transform.translation().xy(),
+                        mark,
+                        ButtonColor::from(*color),
+                    );
+                })
+                .filter(|(group, _)| group.0.active)
+                .count();
+            text.push_str(&format!(
+                "{:?} crystals: {} group(s), {} active\n",
+                color,
+                groups.len(),
+                active
+            ));
+        }
+    }
+    set_panel_text(&mut q_panel, text);
+}
+
+fn draw_lyra_view(
+    mut gizmos: Gizmos,
+    lyra: Single<(&MovementInfo, &LinearVelocity, &GlobalTransform), With<Lyra>>,
+    mut q_panel: Query<&mut Text, With<DebugPanel>>,
+) {
+    let (movement, velocity, transform) = *lyra;
+    let pos = transform.translation().xy();
+    gizmos.arrow_2d(pos, pos + velocity.0 / 8., Color::srgb(1.0, 1.0, 0.0));
+
+    set_panel_text(
+        &mut q_panel,
+        format!(
+            "Lyra (F3)\nposition: ({:.1}, {:.1})\nvelocity: ({:.1}, {:.1})\nshould_jump_ticks: {}\ncoyote_time_ticks: {}\njump_boost_ticks: {}\ncrouched: {}",
+            pos.x,
+            pos.y,
+            velocity.x,
+            velocity.y,
+            movement.should_jump_ticks,
+            movement.coyote_time_ticks,
+            movement.jump_boost_ticks,
+            movement.crouched
+        ),
+    );
+}
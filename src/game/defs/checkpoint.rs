@@ -0,0 +1,198 @@
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::{
+    game::{
+        lighting::LineLight2d,
+        lyra::{
+            kill::KillPlayer,
+            rewind::{PuzzleParam, PuzzleSnapshot},
+            Lyra,
+        },
+        Layers,
+    },
+    ldtk::{LdtkLevelParam, LdtkProjectReloaded},
+    shared::GameState,
+};
+
+pub struct CheckpointPlugin;
+
+impl Plugin for CheckpointPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<CheckpointBundle>("Checkpoint");
+        app.add_observer(hydrate_checkpoint);
+        app.add_observer(restore_checkpoint);
+        app.add_observer(forget_checkpoint_on_restart);
+        app.add_observer(forget_checkpoint_on_reload);
+        app.add_systems(
+            Update,
+            forget_checkpoint_on_level_switch.run_if(
+                resource_exists::<CheckpointSnapshot>.and(resource_changed::<LevelSelection>),
+            ),
+        );
+        app.add_systems(OnExit(GameState::InGame), forget_checkpoint);
+    }
+}
+
+/// [`Component`] for an area of a level that becomes Lyra's respawn point once she touches it.
+#[derive(Component, Debug)]
+pub struct Checkpoint {
+    size: Vec2,
+}
+
+impl From<&EntityInstance> for Checkpoint {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        Checkpoint {
+            size: Vec2::new(entity_instance.width as f32, entity_instance.height as f32),
+        }
+    }
+}
+
+#[derive(Bundle, LdtkEntity)]
+pub struct CheckpointBundle {
+    #[from_entity_instance]
+    checkpoint: Checkpoint,
+}
+
+/// [`Resource`] holding the state of the level from when Lyra last touched a [`Checkpoint`].
+/// Dying restores it instead of restarting the level from its start flag.
+#[derive(Resource)]
+pub struct CheckpointSnapshot {
+    checkpoint: Entity,
+    level_iid: LevelIid,
+    respawn_pos: Vec2,
+    puzzle: PuzzleSnapshot,
+}
+
+/// Event triggered after [`ResetLevels`](crate::shared::ResetLevels) when Lyra dies, bringing the
+/// level back to the [`CheckpointSnapshot`] if there is one for the current level.
+#[derive(Event)]
+pub struct RestoreCheckpoint;
+
+pub fn hydrate_checkpoint(
+    event: On<Add, Checkpoint>,
+    mut commands: Commands,
+    q_checkpoints: Query<&Checkpoint>,
+) {
+    let Ok(checkpoint) = q_checkpoints.get(event.entity) else {
+        return;
+    };
+    commands
+        .entity(event.entity)
+        .insert(Collider::rectangle(checkpoint.size.x, checkpoint.size.y))
+        .insert(CollisionLayers::new(
+            Layers::SensorBox,
+            Layers::PlayerHurtbox,
+        ))
+        .insert(Sensor);
+}
+
+/// Observer on Lyra's hurtbox that saves the level when she touches a new [`Checkpoint`].
+pub fn touch_checkpoint(
+    event: On<CollisionStart>,
+    mut commands: Commands,
+    q_checkpoints: Query<&GlobalTransform, With<Checkpoint>>,
+    snapshot: Option<Res<CheckpointSnapshot>>,
+    ldtk_level_param: LdtkLevelParam,
+    puzzle: PuzzleParam,
+) {
+    let Ok(transform) = q_checkpoints.get(event.collider2) else {
+        return;
+    };
+    if snapshot
+        .as_ref()
+        .is_some_and(|snapshot| snapshot.checkpoint == event.collider2)
+    {
+        return;
+    }
+    let Some(level_iid) = ldtk_level_param.cur_iid() else {
+        return;
+    };
+    let Some(puzzle_snapshot) = puzzle.snapshot(&level_iid) else {
+        return;
+    };
+    if let Some(snapshot) = snapshot {
+        commands
+            .entity(snapshot.checkpoint)
+            .try_remove::<LineLight2d>();
+    }
+
+    info!("Reached checkpoint {:?}", event.collider2);
+    commands.insert_resource(CheckpointSnapshot {
+        checkpoint: event.collider2,
+        level_iid,
+        respawn_pos: transform.translation().xy(),
+        puzzle: puzzle_snapshot,
+    });
+    commands.entity(event.collider2).insert(LineLight2d::point(
+        Vec4::new(1.0, 0.9, 0.6, 1.0),
+        40.0,
+        0.015,
+    ));
+}
+
+pub fn restore_checkpoint(
+    _: On<RestoreCheckpoint>,
+    snapshot: Option<Res<CheckpointSnapshot>>,
+    ldtk_level_param: LdtkLevelParam,
+    lyra: Single<(&mut Transform, &mut Position), With<Lyra>>,
+    mut puzzle: PuzzleParam,
+) {
+    let Some(snapshot) = snapshot else {
+        return;
+    };
+    if ldtk_level_param.cur_iid().as_ref() != Some(&snapshot.level_iid) {
+        return;
+    }
+
+    let (mut transform, mut position) = lyra.into_inner();
+    *transform = Transform::from_translation(snapshot.respawn_pos.extend(0.));
+    *position = Position(snapshot.respawn_pos);
+    info!("Moving lyra to checkpoint at {}", snapshot.respawn_pos);
+
+    puzzle.restore(&snapshot.level_iid, &snapshot.puzzle);
+}
+
+/// Restarting the level on purpose goes back to its start flag, so that a checkpoint reached in a
+/// bad state can't lock the player out of the level.
+pub fn forget_checkpoint_on_restart(
+    event: On<KillPlayer>,
+    commands: Commands,
+    snapshot: Option<Res<CheckpointSnapshot>>,
+) {
+    if event.restart {
+        forget_checkpoint(commands, snapshot);
+    }
+}
+
+pub fn forget_checkpoint_on_reload(
+    _: On<LdtkProjectReloaded>,
+    commands: Commands,
+    snapshot: Option<Res<CheckpointSnapshot>>,
+) {
+    forget_checkpoint(commands, snapshot);
+}
+
+pub fn forget_checkpoint_on_level_switch(
+    commands: Commands,
+    snapshot: Option<Res<CheckpointSnapshot>>,
+    ldtk_level_param: LdtkLevelParam,
+) {
+    if snapshot
+        .as_ref()
+        .is_some_and(|snapshot| ldtk_level_param.cur_iid().as_ref() != Some(&snapshot.level_iid))
+    {
+        forget_checkpoint(commands, snapshot);
+    }
+}
+
+pub fn forget_checkpoint(mut commands: Commands, snapshot: Option<Res<CheckpointSnapshot>>) {
+    let Some(snapshot) = snapshot else {
+        return;
+    };
+    commands
+        .entity(snapshot.checkpoint)
+        .try_remove::<LineLight2d>();
+    commands.remove_resource::<CheckpointSnapshot>();
+}
@@ -13,9 +13,10 @@ use crate::{
         },
         lighting::{Occluder2d, Occluder2dDisabled},
         particle::dust::DustSurface,
+        signal::{SignalListener, SignalReceived},
         Layers, LevelSystems,
     },
-    ldtk::LdtkLevelParam,
+    ldtk::{LdtkProjectReloaded, ParentLevelParam},
     shared::ResetLevels,
 };
 // use bevy_ecs_tilemap::tiles::TileTextureIndex;
@@ -46,7 +47,13 @@ impl Plugin for CrystalPlugin {
                 .in_set(LevelSystems::Processing),
         );
         app.add_observer(on_crystal_changed);
+        app.add_observer(on_crystal_signal);
         app.add_observer(reset_crystals);
+        app.add_observer(clear_crystal_cache);
+        #[cfg(feature = "dev_mode")]
+        app.add_observer(remerge_crystals);
+
+        app.register_ldtk_entity::<CrystalSignalBundle>("CrystalSignal");
 
         for i in 3..=10 {
             app.register_ldtk_int_cell_for_layer::<CrystalBundle>("Terrain", i);
@@ -82,6 +89,13 @@ pub struct Crystal {
     pub active: bool,
 }
 
+impl Crystal {
+    /// Whether the crystal was toggled away from its state at the start of the level.
+    pub fn is_toggled(&self) -> bool {
+        self.active != self.init_active
+    }
+}
+
 impl MergedTile for Crystal {
     type CompareData = (CrystalColor, bool);
 
@@ -147,6 +161,13 @@ pub struct CrystalCache {
     groups: HashMap<LevelIid, HashMap<CrystalColor, Vec<Entity>>>,
 }
 
+impl CrystalCache {
+    /// The [`CrystalGroup`] entities of a level by color.
+    pub fn groups(&self, iid: &LevelIid) -> impl Iterator<Item = (&CrystalColor, &Vec<Entity>)> {
+        self.groups.get(iid).into_iter().flatten()
+    }
+}
+
 fn invalidate_crystal_cache(
     mut ev_level: MessageReader<LevelEvent>,
     mut crystal_cache: ResMut<CrystalCache>,
@@ -164,6 +185,13 @@ fn invalidate_crystal_cache(
     }
 }
 
+/// Forgets every crystal when the LDtk project is reloaded, the respawned ones are added back as
+/// they spawn.
+fn clear_crystal_cache(_: On<LdtkProjectReloaded>, mut crystal_cache: ResMut<CrystalCache>) {
+    crystal_cache.tiles.clear();
+    crystal_cache.groups.clear();
+}
+
 fn init_crystal_cache_groups(
     q_crystal_groups: Query<(Entity, &ChildOf, &CrystalGroup), Added<CrystalGroup>>,
     q_level_iid: Query<&LevelIid>,
@@ -214,6 +242,51 @@ fn init_crystal_cache_tiles(
     }
 }
 
+/// [`Event`] that merges the crystals of a level again after tiles were added or removed by the
+/// level editor.
+#[cfg(feature = "dev_mode")]
+#[derive(Event)]
+pub struct RemergeCrystals {
+    pub level: Entity,
+}
+
+#[cfg(feature = "dev_mode")]
+fn remerge_crystals(
+    event: On<RemergeCrystals>,
+    mut commands: Commands,
+    q_level_iid: Query<&LevelIid>,
+    q_crystal_groups: Query<(Entity, &ChildOf), With<CrystalGroup>>,
+    q_crystals: Query<(Entity, &ChildOf, &Crystal)>,
+    q_parent: Query<&ChildOf, Without<Crystal>>,
+    mut crystal_cache: ResMut<CrystalCache>,
+) {
+    let Ok(level_iid) = q_level_iid.get(event.level) else {
+        return;
+    };
+    crystal_cache.tiles.remove(level_iid);
+    crystal_cache.groups.remove(level_iid);
+
+    for (entity, ChildOf(parent)) in q_crystal_groups.iter() {
+        if *parent == event.level {
+            commands.entity(entity).despawn();
+        }
+    }
+    for (entity, ChildOf(parent), crystal) in q_crystals.iter() {
+        let in_level = q_parent
+            .get(*parent)
+            .is_ok_and(|ChildOf(level)| *level == event.level);
+        if !in_level {
+            continue;
+        }
+        // NOTE: inserting it again marks it as added for spawn_merged_tiles and the crystal cache
+        commands.entity(entity).remove::<Crystal>().insert(Crystal {
+            color: crystal.color,
+            init_active: crystal.init_active,
+            active: crystal.init_active,
+        });
+    }
+}
+
 fn is_crystal_active(cell_value: IntGridCell) -> bool {
     match cell_value.value {
         3 | 5 | 7 | 9 => true,
@@ -298,9 +371,10 @@ pub fn reset_crystals(
     }
 }
 
-/// Event that will toggle all crystals of a certain color.
+/// Event that will toggle all crystals of a certain color in a level.
 #[derive(Event)]
 pub struct CrystalToggleEvent {
+    pub level: LevelIid,
     pub color: CrystalColor,
 }
 
@@ -312,14 +386,11 @@ pub fn on_crystal_changed(
     mut q_crystal: Query<(&mut Crystal, &mut TileTextureIndex)>,
     mut q_crystal_groups: Query<&mut CrystalGroup>,
     crystal_cache: Res<CrystalCache>,
-    ldtk_level_param: LdtkLevelParam,
 ) {
-    let iid = ldtk_level_param.cur_iid().expect("Cur level should exist");
-
-    let Some(crystal_tile_map) = crystal_cache.tiles.get(&iid) else {
+    let Some(crystal_tile_map) = crystal_cache.tiles.get(&event.level) else {
         return;
     };
-    let Some(crystal_group_map) = crystal_cache.groups.get(&iid) else {
+    let Some(crystal_group_map) = crystal_cache.groups.get(&event.level) else {
         return;
     };
 
@@ -340,3 +411,60 @@ pub fn on_crystal_changed(
         }
     }
 }
+
+/// [`Component`] for an LDtk entity that hooks the crystals of a color up to a
+/// [`LevelSignal`](crate::game::signal::LevelSignal) id, so that any signal can toggle them and not
+/// only the sensors of that color. The crystals are toggled away from their starting state for as
+/// long as the signal is active.
+#[derive(Component, Debug)]
+pub struct CrystalSignal {
+    pub color: CrystalColor,
+}
+
+impl From<&EntityInstance> for CrystalSignal {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let color = entity_instance
+            .get_enum_field("toggle_color")
+            .expect("toggle_color needs to be an enum field on all crystal signals")
+            .into();
+
+        CrystalSignal { color }
+    }
+}
+
+#[derive(Bundle, LdtkEntity)]
+pub struct CrystalSignalBundle {
+    #[from_entity_instance]
+    crystal_signal: CrystalSignal,
+    #[from_entity_instance]
+    listener: SignalListener,
+}
+
+pub fn on_crystal_signal(
+    event: On<SignalReceived>,
+    mut commands: Commands,
+    q_crystal_signals: Query<&CrystalSignal>,
+    q_crystal_groups: Query<&CrystalGroup>,
+    crystal_cache: Res<CrystalCache>,
+    parent_level: ParentLevelParam,
+) {
+    let Ok(crystal_signal) = q_crystal_signals.get(event.entity) else {
+        return;
+    };
+    let Some(iid) = parent_level.level_iid(event.entity) else {
+        return;
+    };
+    // crystals are toggled all at once per color, so one group tells the state of them all
+    let toggled = crystal_cache
+        .groups(iid)
+        .filter(|(color, _)| **color == crystal_signal.color)
+        .flat_map(|(_, groups)| groups.iter())
+        .filter_map(|group| q_crystal_groups.get(*group).ok())
+        .any(|group| group.0.is_toggled());
+    if toggled != event.active {
+        commands.trigger(CrystalToggleEvent {
+            level: iid.clone(),
+            color: crystal_signal.color,
+        });
+    }
+}
@@ -1,7 +1,16 @@
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
 
-use crate::game::lighting::LineLight2d;
+use crate::{
+    game::{
+        lighting::LineLight2d,
+        signal::{is_signal_id, SignalListener, SignalReceived},
+    },
+    shared::ResetLevels,
+};
+
+/// Intensity of a lit lantern, lanterns waiting on a signal are at zero until it is active.
+const LANTERN_INTENSITY: f32 = 1.0;
 
 pub struct DecorationPlugin;
 
@@ -10,6 +19,8 @@ impl Plugin for DecorationPlugin {
         app.register_ldtk_entity::<LdtkTreeBranchBundle>("Treebranch");
         app.register_ldtk_entity::<LdtkLanternBundle>("Lantern");
         app.register_ldtk_entity::<LdtkLantern2Bundle>("Lantern2");
+        app.add_observer(hydrate_lantern);
+        app.add_observer(reset_lanterns);
     }
 }
 
@@ -24,6 +35,8 @@ pub struct LdtkLanternBundle {
     light: LineLight2d,
     #[default]
     lantern: Lantern,
+    #[from_entity_instance]
+    listener: SignalListener,
 }
 
 #[derive(Bundle, LdtkEntity)]
@@ -34,10 +47,50 @@ pub struct LdtkLantern2Bundle {
     light: LineLight2d,
     #[default]
     lantern: Lantern,
+    #[from_entity_instance]
+    listener: SignalListener,
 }
 
 pub fn lantern_light(_: &EntityInstance) -> LineLight2d {
-    LineLight2d::point(Vec4::new(1.0, 0.8627, 0.7176, 1.0), 50.0, 0.015)
+    LineLight2d::point(
+        Vec4::new(1.0, 0.8627, 0.7176, LANTERN_INTENSITY),
+        50.0,
+        0.015,
+    )
+}
+
+/// Lanterns with a `signal_id` start out dark and are lit while their signal is active.
+pub fn hydrate_lantern(
+    event: On<Add, Lantern>,
+    mut commands: Commands,
+    mut q_lanterns: Query<(&SignalListener, &mut LineLight2d), With<Lantern>>,
+) {
+    let Ok((listener, mut light)) = q_lanterns.get_mut(event.entity) else {
+        return;
+    };
+    if !is_signal_id(listener.0) {
+        return;
+    }
+    light.color.w = 0.0;
+    commands.entity(event.entity).observe(
+        |event: On<SignalReceived>, mut q_lights: Query<&mut LineLight2d>| {
+            let Ok(mut light) = q_lights.get_mut(event.entity) else {
+                return;
+            };
+            light.color.w = if event.active { LANTERN_INTENSITY } else { 0.0 };
+        },
+    );
+}
+
+pub fn reset_lanterns(
+    _: On<ResetLevels>,
+    mut q_lanterns: Query<(&SignalListener, &mut LineLight2d), With<Lantern>>,
+) {
+    for (listener, mut light) in q_lanterns.iter_mut() {
+        if is_signal_id(listener.0) {
+            light.color.w = 0.0;
+        }
+    }
 }
 
 #[derive(Component, Default)]
@@ -0,0 +1,91 @@
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::{
+    game::{
+        lighting::{Occluder2d, Occluder2dDisabled},
+        signal::{SignalListener, SignalReceived},
+        Layers,
+    },
+    shared::ResetLevels,
+};
+
+pub struct DoorPlugin;
+
+impl Plugin for DoorPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<DoorBundle>("Door");
+        app.add_observer(hydrate_door);
+        app.add_observer(reset_doors);
+    }
+}
+
+/// [`Component`] for a block of terrain placed in LDtk that opens while its signal is active.
+#[derive(Component, Debug)]
+pub struct Door {
+    size: Vec2,
+}
+
+impl From<&EntityInstance> for Door {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        Door {
+            size: Vec2::new(entity_instance.width as f32, entity_instance.height as f32),
+        }
+    }
+}
+
+#[derive(Bundle, LdtkEntity)]
+pub struct DoorBundle {
+    #[from_entity_instance]
+    door: Door,
+    #[from_entity_instance]
+    listener: SignalListener,
+}
+
+fn set_door_open(commands: &mut Commands, entity: Entity, open: bool) {
+    if open {
+        commands
+            .entity(entity)
+            .insert(ColliderDisabled)
+            .insert(Occluder2dDisabled)
+            .insert(Visibility::Hidden);
+    } else {
+        commands
+            .entity(entity)
+            .remove::<ColliderDisabled>()
+            .remove::<Occluder2dDisabled>()
+            .insert(Visibility::Inherited);
+    }
+}
+
+pub fn hydrate_door(event: On<Add, Door>, mut commands: Commands, q_doors: Query<&Door>) {
+    let Ok(door) = q_doors.get(event.entity) else {
+        return;
+    };
+    commands
+        .entity(event.entity)
+        .insert(Collider::rectangle(door.size.x, door.size.y))
+        .insert(Occluder2d::new(door.size.x / 2., door.size.y / 2.))
+        .insert(Friction::new(0.))
+        .insert(CollisionLayers::new(
+            Layers::Terrain,
+            [
+                Layers::PlayerCollider,
+                Layers::LightRay,
+                Layers::BlueRay,
+                Layers::WhiteRay,
+                Layers::PlayerHurtbox,
+            ],
+        ))
+        .insert(Sprite::from_color(Color::srgb(0.35, 0.3, 0.4), door.size))
+        .observe(|event: On<SignalReceived>, mut commands: Commands| {
+            set_door_open(&mut commands, event.entity, event.active);
+        });
+}
+
+pub fn reset_doors(_: On<ResetLevels>, mut commands: Commands, q_doors: Query<Entity, With<Door>>) {
+    for entity in q_doors.iter() {
+        set_door_open(&mut commands, entity, false);
+    }
+}
@@ -0,0 +1,228 @@
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::game::Layers;
+
+/// Half the width and height of a glass cell.
+const GLASS_HALF_SIZE: f32 = 4.0;
+
+/// Radius of curvature of the faces of a lens. The focal length of a lens for a beam is this over
+/// the refraction index of the beam minus one, like a thin lens.
+const LENS_RADIUS: f32 = 12.0;
+
+pub struct GlassPlugin;
+
+impl Plugin for GlassPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_int_cell_for_layer::<GlassBundle>("Terrain", 17);
+        app.register_ldtk_int_cell_for_layer::<GlassBundle>("Terrain", 18);
+        app.add_observer(adjust_glass);
+    }
+}
+
+/// [`Component`] for terrain cells that beams go through instead of bouncing off. Glass blocks
+/// bend beams as they enter and leave them, lenses focus beams onto a point behind them.
+#[derive(Default, Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Glass {
+    #[default]
+    Block,
+    Lens,
+}
+
+impl From<IntGridCell> for Glass {
+    fn from(cell: IntGridCell) -> Self {
+        match cell.value {
+            18 => Glass::Lens,
+            _ => Glass::Block,
+        }
+    }
+}
+
+#[derive(Bundle, Default, LdtkIntCell)]
+pub struct GlassBundle {
+    #[from_int_grid_cell]
+    glass: Glass,
+}
+
+pub fn adjust_glass(event: On<Add, Glass>, mut commands: Commands, q_glass: Query<&Glass>) {
+    let Ok(glass) = q_glass.get(event.entity) else {
+        return;
+    };
+    let color = match glass {
+        Glass::Block => Color::srgba(0.55, 0.85, 0.95, 0.35),
+        Glass::Lens => Color::srgba(0.75, 0.95, 1.0, 0.6),
+    };
+    commands
+        .entity(event.entity)
+        .insert(Collider::rectangle(
+            GLASS_HALF_SIZE * 2.0,
+            GLASS_HALF_SIZE * 2.0,
+        ))
+        .insert(Friction::new(0.))
+        .insert(CollisionLayers::new(
+            Layers::Terrain,
+            [
+                Layers::LightRay,
+                Layers::PlayerHurtbox,
+                Layers::PlayerCollider,
+                Layers::BlueRay,
+                Layers::WhiteRay,
+                Layers::BlackRay,
+            ],
+        ))
+        .insert(Sprite::from_color(
+            color,
+            Vec2::splat(GLASS_HALF_SIZE * 2.0),
+        ));
+}
+
+/// Distance from a point inside the glass cell centered on `center` to where a beam going in
+/// `dir` leaves it, along with the normal of the side it leaves through.
+pub fn glass_exit(center: Vec2, pos: Vec2, dir: Dir2) -> (f32, Vec2) {
+    let exit = |pos: f32, dir: f32, center: f32| {
+        if dir > 0.0 {
+            ((center + GLASS_HALF_SIZE - pos) / dir, 1.0)
+        } else if dir < 0.0 {
+            ((center - GLASS_HALF_SIZE - pos) / dir, -1.0)
+        } else {
+            (f32::INFINITY, 0.0)
+        }
+    };
+    let (x_distance, x_normal) = exit(pos.x, dir.x, center.x);
+    let (y_distance, y_normal) = exit(pos.y, dir.y, center.y);
+    if x_distance < y_distance {
+        (x_distance.max(0.0), Vec2::new(x_normal, 0.0))
+    } else {
+        (y_distance.max(0.0), Vec2::new(0.0, y_normal))
+    }
+}
+
+/// The glass block sharing the side of a glass cell a beam leaves through at `exit_point`, with
+/// its center. Beams go straight from one to the other, like through a single bigger block.
+pub fn next_glass_block(
+    spatial_query: &SpatialQuery,
+    q_glass: &Query<(&Glass, &GlobalTransform)>,
+    exit_point: Vec2,
+    normal: Vec2,
+) -> Option<(Entity, Vec2)> {
+    spatial_query
+        .point_intersections(
+            exit_point + normal * GLASS_HALF_SIZE,
+            &SpatialQueryFilter::from_mask(Layers::Terrain),
+        )
+        .into_iter()
+        .find_map(|entity| match q_glass.get(entity) {
+            Ok((Glass::Block, transform)) => Some((entity, transform.translation().truncate())),
+            _ => None,
+        })
+}
+
+/// Bends `dir` through a surface with the given `normal` following Snell's law, where `eta` is
+/// the refraction index of the medium the beam leaves over that of the one it enters. Returns
+/// [`None`] if the beam is reflected back instead (total internal reflection).
+pub fn refract(dir: Dir2, normal: Vec2, eta: f32) -> Option<Dir2> {
+    let normal = if normal.dot(*dir) > 0.0 {
+        -normal
+    } else {
+        normal
+    };
+    let cos_in = -normal.dot(*dir);
+    let k = 1.0 - eta * eta * (1.0 - cos_in * cos_in);
+    if k < 0.0 {
+        return None;
+    }
+    Dir2::new(eta * *dir + (eta * cos_in - k.sqrt()) * normal).ok()
+}
+
+/// Bends `dir` towards the axis of a lens entered through a side with the given `normal`, like a
+/// thin lens. `offset` is where the beam hit relative to the center of the lens.
+pub fn focus(dir: Dir2, normal: Vec2, offset: Vec2, index: f32) -> Dir2 {
+    if index <= 1.0 {
+        return dir;
+    }
+    let axis = if normal.dot(*dir) > 0.0 {
+        normal
+    } else {
+        -normal
+    };
+    let along = dir.dot(axis);
+    if along <= 0.0 {
+        return dir;
+    }
+    let focal_length = LENS_RADIUS / (index - 1.0);
+    let height = offset - axis * offset.dot(axis);
+    let slope = (*dir - axis * along) / along;
+    Dir2::new(axis + slope - height / focal_length).unwrap_or(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dir(x: f32, y: f32) -> Dir2 {
+        Dir2::new(Vec2::new(x, y)).unwrap()
+    }
+
+    #[test]
+    fn glass_exit_leaves_through_the_side_it_faces() {
+        assert_eq!(
+            glass_exit(Vec2::ZERO, Vec2::ZERO, Dir2::X),
+            (4.0, Vec2::new(1.0, 0.0))
+        );
+        assert_eq!(
+            glass_exit(Vec2::ZERO, Vec2::new(1.0, 1.0), Dir2::NEG_Y),
+            (5.0, Vec2::new(0.0, -1.0))
+        );
+        let (distance, normal) = glass_exit(Vec2::ZERO, Vec2::ZERO, dir(0.6, 0.8));
+        assert!((distance - 5.0).abs() < 1e-5);
+        assert_eq!(normal, Vec2::new(0.0, 1.0));
+    }
+
+    #[test]
+    fn glass_exit_never_goes_backwards() {
+        let (distance, _) = glass_exit(Vec2::ZERO, Vec2::new(4.5, 0.0), Dir2::X);
+        assert_eq!(distance, 0.0);
+    }
+
+    #[test]
+    fn refract_keeps_head_on_beams_straight() {
+        let refracted = refract(Dir2::X, Vec2::NEG_X, 1.0 / 1.5).unwrap();
+        assert!(refracted.abs_diff_eq(Vec2::X, 1e-5));
+    }
+
+    #[test]
+    fn refract_follows_snells_law() {
+        let incoming = dir(1.0, -1.0);
+        let refracted = refract(incoming, Vec2::Y, 1.0 / 1.5).unwrap();
+        assert!((refracted.x - incoming.x / 1.5).abs() < 1e-5);
+        assert!(refracted.y < 0.0);
+        // the side of the normal doesn't matter
+        let flipped = refract(incoming, Vec2::NEG_Y, 1.0 / 1.5).unwrap();
+        assert!(flipped.abs_diff_eq(*refracted, 1e-5));
+    }
+
+    #[test]
+    fn refract_reflects_internally_past_the_critical_angle() {
+        assert_eq!(refract(dir(1.0, -1.0), Vec2::Y, 1.5), None);
+        assert!(refract(dir(1.0, -3.0), Vec2::Y, 1.5).is_some());
+    }
+
+    #[test]
+    fn focus_bends_beams_onto_the_focal_point() {
+        let index = 1.5;
+        let focal_length = LENS_RADIUS / (index - 1.0);
+        let focused = focus(Dir2::X, Vec2::NEG_X, Vec2::new(0.0, 2.0), index);
+        assert!(focused.y < 0.0);
+        // crosses the axis of the lens at the focal length
+        assert!((2.0 + focal_length * focused.y / focused.x).abs() < 1e-4);
+    }
+
+    #[test]
+    fn focus_leaves_beams_along_the_axis_alone() {
+        let focused = focus(Dir2::X, Vec2::NEG_X, Vec2::ZERO, 1.5);
+        assert!(focused.abs_diff_eq(Vec2::X, 1e-5));
+        let focused = focus(Dir2::X, Vec2::NEG_X, Vec2::new(0.0, 2.0), 1.0);
+        assert_eq!(focused, Dir2::X);
+    }
+}
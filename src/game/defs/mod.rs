@@ -1,21 +1,27 @@
 use bevy::prelude::*;
 
 use crate::game::defs::{
-    cruciera::CrucieraPlugin, crystal::CrystalPlugin, decoration::DecorationPlugin,
-    mirror::MirrorPlugin, one_way_platform::OneWayPlatformPlugin, sensor::LightSensorPlugin,
-    shard::CrystalShardPlugin, spikes::SpikesPlugin, terrain::TerrainPlugin,
-    tooltip_sign::TooltipSignPlugin,
+    checkpoint::CheckpointPlugin, cruciera::CrucieraPlugin, crystal::CrystalPlugin,
+    decoration::DecorationPlugin, door::DoorPlugin, glass::GlassPlugin, mirror::MirrorPlugin,
+    moving_platform::MovingPlatformPlugin, one_way_platform::OneWayPlatformPlugin,
+    portal::PortalPlugin, sensor::LightSensorPlugin, shard::CrystalShardPlugin,
+    spikes::SpikesPlugin, terrain::TerrainPlugin, tooltip_sign::TooltipSignPlugin,
 };
 
+pub mod checkpoint;
 mod cruciera;
 pub mod crystal;
 mod decoration;
+pub mod door;
+pub mod glass;
 mod merge_tile;
 pub mod mirror;
+pub mod moving_platform;
 pub mod one_way_platform;
+pub mod portal;
 pub mod sensor;
 pub mod shard;
-mod spikes;
+pub mod spikes;
 mod terrain;
 pub mod tooltip_sign;
 
@@ -33,6 +39,11 @@ impl Plugin for LevelPlugin {
         app.add_plugins(CrucieraPlugin);
         app.add_plugins(DecorationPlugin);
         app.add_plugins(MirrorPlugin);
+        app.add_plugins(GlassPlugin);
+        app.add_plugins(MovingPlatformPlugin);
+        app.add_plugins(CheckpointPlugin);
+        app.add_plugins(PortalPlugin);
+        app.add_plugins(DoorPlugin);
     }
 }
 
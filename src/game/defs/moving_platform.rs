@@ -0,0 +1,437 @@
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::{
+    game::{
+        lighting::Occluder2d,
+        lyra::{
+            controller::{movement, Grounded},
+            Lyra,
+        },
+        particle::dust::DustSurface,
+        signal::{SignalListener, SignalReceived},
+        Layers, LevelSystems,
+    },
+    shared::ResetLevels,
+};
+
+/// The number of line segments used to approximate each curved section of a platform's path.
+const CURVE_SEGMENTS: usize = 12;
+
+pub struct MovingPlatformPlugin;
+
+impl Plugin for MovingPlatformPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<MovingPlatformBundle>("MovingPlatform");
+        app.add_observer(hydrate_moving_platform);
+        app.add_observer(reset_moving_platforms);
+        app.add_observer(handle_moving_platform_event);
+        app.add_systems(
+            FixedUpdate,
+            (move_platforms, carry_lyra_on_platforms)
+                .chain()
+                .before(movement)
+                .in_set(LevelSystems::Simulation),
+        );
+    }
+}
+
+/// Whether a [`MovingPlatform`] starts moving as soon as the level is (re)spawned, or waits for a
+/// [`MovingPlatformEvent`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DefaultState {
+    Play,
+    #[default]
+    Pause,
+}
+
+impl From<&String> for DefaultState {
+    fn from(value: &String) -> Self {
+        match value.as_str() {
+            "Play" => DefaultState::Play,
+            "Pause" => DefaultState::Pause,
+            _ => panic!("String {} does not represent a DefaultState", value),
+        }
+    }
+}
+
+/// [`Component`] for a kinematic platform that travels along a path authored in LDtk.
+///
+/// The path is stored as a polyline of offsets from the platform's spawn position, with curved
+/// sections already flattened, so the platform only has to track how far along it has travelled.
+#[derive(Component, Debug)]
+pub struct MovingPlatform {
+    /// Size of the platform's collider.
+    size: Vec2,
+    /// Offsets from the spawn position, starting with [`Vec2::ZERO`].
+    points: Vec<Vec2>,
+    /// Total length of the polyline in `points`.
+    length: f32,
+    /// Speed in pixels per second.
+    speed: f32,
+    does_reverse: bool,
+    does_repeat: bool,
+    can_reactivate: bool,
+    default_state: DefaultState,
+    /// The id that [`MovingPlatformEvent`]s and [`LevelSignal`](crate::game::signal::LevelSignal)s
+    /// use to target this platform.
+    pub event_id: i32,
+    /// World position of the platform at spawn, cached on the first simulated tick.
+    origin: Option<Vec2>,
+    /// Distance travelled along the path, from 0 to `length`.
+    distance: f32,
+    /// `1.0` when travelling towards the end of the path, `-1.0` when travelling back.
+    direction: f32,
+    playing: bool,
+    finished: bool,
+}
+
+impl MovingPlatform {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        size: Vec2,
+        points: Vec<Vec2>,
+        speed: f32,
+        does_reverse: bool,
+        does_repeat: bool,
+        can_reactivate: bool,
+        default_state: DefaultState,
+        event_id: i32,
+    ) -> Self {
+        let length = points.windows(2).map(|w| w[0].distance(w[1])).sum();
+        MovingPlatform {
+            size,
+            points,
+            length,
+            speed,
+            does_reverse,
+            does_repeat,
+            can_reactivate,
+            default_state,
+            event_id,
+            origin: None,
+            distance: 0.0,
+            direction: 1.0,
+            playing: default_state == DefaultState::Play,
+            finished: false,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.distance = 0.0;
+        self.direction = 1.0;
+        self.playing = self.default_state == DefaultState::Play;
+        self.finished = false;
+    }
+
+    /// Returns the offset from the origin at the given distance along the path.
+    fn sample(&self, distance: f32) -> Vec2 {
+        let mut remaining = distance;
+        for w in self.points.windows(2) {
+            let seg_len = w[0].distance(w[1]);
+            if remaining <= seg_len {
+                if seg_len == 0.0 {
+                    return w[1];
+                }
+                return w[0].lerp(w[1], remaining / seg_len);
+            }
+            remaining -= seg_len;
+        }
+        self.points.last().copied().unwrap_or(Vec2::ZERO)
+    }
+
+    /// Advances the platform along its path, handling reversing, looping and stopping at the ends.
+    fn advance(&mut self, delta: f32) {
+        if !self.playing || self.length == 0.0 {
+            return;
+        }
+        self.distance += self.direction * self.speed * delta;
+
+        if self.distance >= self.length {
+            if self.does_reverse {
+                self.distance = 2.0 * self.length - self.distance;
+                self.direction = -1.0;
+            } else if self.does_repeat {
+                self.distance -= self.length;
+            } else {
+                self.distance = self.length;
+                self.stop();
+            }
+        } else if self.distance <= 0.0 {
+            if self.does_repeat {
+                self.distance = -self.distance;
+                self.direction = 1.0;
+            } else {
+                self.distance = 0.0;
+                self.stop();
+            }
+        }
+    }
+
+    fn stop(&mut self) {
+        self.playing = false;
+        self.finished = true;
+    }
+
+    fn play(&mut self) {
+        if self.finished {
+            if !self.can_reactivate {
+                return;
+            }
+            // a platform that stopped at the end of its path travels back along it
+            self.direction = if self.distance > 0.0 { -1.0 } else { 1.0 };
+            self.finished = false;
+        }
+        self.playing = true;
+    }
+
+    fn pause(&mut self) {
+        self.playing = false;
+    }
+}
+
+/// Builds the polyline followed by a platform. Each entry of `path` is an offset from the spawn
+/// position. A point flagged in `curve_points` acts as the control point of a quadratic curve
+/// between its neighbours instead of being visited directly.
+fn build_path(path: &[Vec2], curve_points: &[bool], close_loop: bool) -> Vec<Vec2> {
+    let mut points = vec![Vec2::ZERO];
+    let mut i = 0;
+    while i < path.len() {
+        let is_curve = curve_points.get(i).copied().unwrap_or(false);
+        match (is_curve, path.get(i + 1)) {
+            (true, Some(&end)) => {
+                let start = *points.last().unwrap();
+                let control = path[i];
+                for step in 1..=CURVE_SEGMENTS {
+                    let t = step as f32 / CURVE_SEGMENTS as f32;
+                    let a = start.lerp(control, t);
+                    let b = control.lerp(end, t);
+                    points.push(a.lerp(b, t));
+                }
+                i += 2;
+            }
+            _ => {
+                points.push(path[i]);
+                i += 1;
+            }
+        }
+    }
+    if close_loop && points.last() != Some(&Vec2::ZERO) {
+        points.push(Vec2::ZERO);
+    }
+    points
+}
+
+/// [`Bundle`] registered with [`LdtkEntityAppExt::register_ldtk_entity`](LdtkEntityAppExt) to
+/// spawn moving platforms directly from Ldtk.
+#[derive(Bundle)]
+pub struct MovingPlatformBundle {
+    platform: MovingPlatform,
+    sprite: Sprite,
+}
+
+impl LdtkEntity for MovingPlatformBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        layer_instance: &LayerInstance,
+        _: Option<&Handle<Image>>,
+        _: Option<&TilesetDefinition>,
+        asset_server: &AssetServer,
+        _: &mut Assets<TextureAtlasLayout>,
+    ) -> Self {
+        let grid = layer_instance.grid_size as f32;
+        let start = entity_instance.grid;
+
+        let path = entity_instance
+            .iter_points_field("path")
+            .expect("path needs to be a point array field on all moving platforms")
+            .map(|point| {
+                Vec2::new(
+                    (point.x - start.x) as f32 * grid,
+                    -(point.y - start.y) as f32 * grid,
+                )
+            })
+            .collect::<Vec<Vec2>>();
+
+        let curve_points = entity_instance
+            .get_bools_field("path_curve_points")
+            .expect("path_curve_points needs to be a bool array field on all moving platforms")
+            .to_vec();
+
+        let speed = *entity_instance
+            .get_float_field("speed")
+            .expect("speed needs to be a float field on all moving platforms");
+        let does_reverse = *entity_instance
+            .get_bool_field("does_reverse")
+            .expect("does_reverse needs to be a bool field on all moving platforms");
+        let does_repeat = *entity_instance
+            .get_bool_field("does_repeat")
+            .expect("does_repeat needs to be a bool field on all moving platforms");
+        let can_reactivate = *entity_instance
+            .get_bool_field("can_reactivate")
+            .expect("can_reactivate needs to be a bool field on all moving platforms");
+        let event_id = *entity_instance
+            .get_int_field("event_id")
+            .expect("event_id needs to be an int field on all moving platforms");
+        let default_state: DefaultState = entity_instance
+            .get_enum_field("DefaultState")
+            .expect("DefaultState needs to be an enum field on all moving platforms")
+            .into();
+
+        let points = build_path(&path, &curve_points, does_repeat && !does_reverse);
+
+        Self {
+            platform: MovingPlatform::new(
+                Vec2::new(entity_instance.width as f32, entity_instance.height as f32),
+                points,
+                speed,
+                does_reverse,
+                does_repeat,
+                can_reactivate,
+                default_state,
+                event_id,
+            ),
+            sprite: Sprite::from_image(asset_server.load("moving_platform.png")),
+        }
+    }
+}
+
+pub fn hydrate_moving_platform(
+    event: On<Add, MovingPlatform>,
+    mut commands: Commands,
+    q_platforms: Query<&MovingPlatform>,
+) {
+    let Ok(platform) = q_platforms.get(event.entity) else {
+        return;
+    };
+    let Vec2 {
+        x: width,
+        y: height,
+    } = platform.size;
+
+    commands
+        .entity(event.entity)
+        .insert(SignalListener(platform.event_id))
+        .insert(RigidBody::Kinematic)
+        .insert(TransformInterpolation)
+        .insert(Collider::rectangle(width, height))
+        .insert(Occluder2d::new(width / 2., height / 2.))
+        .insert(DustSurface::Wood)
+        .insert(Friction::new(0.))
+        .insert(CollisionLayers::new(
+            Layers::Terrain,
+            [
+                Layers::PlayerCollider,
+                Layers::LightRay,
+                Layers::BlueRay,
+                Layers::WhiteRay,
+                Layers::PlayerHurtbox,
+            ],
+        ))
+        .observe(
+            |event: On<SignalReceived>, mut q_platforms: Query<&mut MovingPlatform>| {
+                let Ok(mut platform) = q_platforms.get_mut(event.entity) else {
+                    return;
+                };
+                if event.active {
+                    platform.play();
+                } else {
+                    platform.pause();
+                }
+            },
+        );
+}
+
+/// What a [`MovingPlatformEvent`] should do to the platforms it targets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovingPlatformAction {
+    Play,
+    Pause,
+    Toggle,
+}
+
+/// Event that will play or pause all [`MovingPlatform`]s with a matching
+/// [`event_id`](MovingPlatform::event_id).
+#[derive(Event)]
+pub struct MovingPlatformEvent {
+    pub event_id: i32,
+    pub action: MovingPlatformAction,
+}
+
+pub fn handle_moving_platform_event(
+    event: On<MovingPlatformEvent>,
+    mut q_platforms: Query<&mut MovingPlatform>,
+) {
+    for mut platform in q_platforms.iter_mut() {
+        if platform.event_id != event.event_id {
+            continue;
+        }
+        match event.action {
+            MovingPlatformAction::Play => platform.play(),
+            MovingPlatformAction::Pause => platform.pause(),
+            MovingPlatformAction::Toggle => {
+                if platform.playing {
+                    platform.pause();
+                } else {
+                    platform.play();
+                }
+            }
+        }
+    }
+}
+
+pub fn reset_moving_platforms(
+    _: On<ResetLevels>,
+    mut q_platforms: Query<(&mut MovingPlatform, &mut Position, &mut LinearVelocity)>,
+) {
+    for (mut platform, mut position, mut velocity) in q_platforms.iter_mut() {
+        platform.reset();
+        velocity.0 = Vec2::ZERO;
+        if let Some(origin) = platform.origin {
+            position.0 = origin;
+        }
+    }
+}
+
+/// [`System`] that runs on [`FixedUpdate`], setting the velocity of each [`MovingPlatform`] so
+/// that the physics step moves it to its next point along the path.
+pub fn move_platforms(
+    time: Res<Time>,
+    mut q_platforms: Query<(&mut MovingPlatform, &Position, &mut LinearVelocity)>,
+) {
+    let delta = time.delta_secs();
+    if delta == 0.0 {
+        return;
+    }
+    for (mut platform, position, mut velocity) in q_platforms.iter_mut() {
+        let origin = *platform.origin.get_or_insert(position.0);
+
+        platform.advance(delta);
+        let target = origin + platform.sample(platform.distance);
+        velocity.0 = (target - position.0) / delta;
+    }
+}
+
+/// [`System`] that moves Lyra along with any [`MovingPlatform`] she is standing on. Lyra has no
+/// friction, so the physics step alone would let platforms slide out from under her.
+pub fn carry_lyra_on_platforms(
+    time: Res<Time>,
+    lyra: Single<(&mut Position, &LinearVelocity, &ShapeHits), (With<Lyra>, With<Grounded>)>,
+    q_platforms: Query<&LinearVelocity, (With<MovingPlatform>, Without<Lyra>)>,
+) {
+    let (mut position, velocity, shape_hits) = lyra.into_inner();
+    // only hits on the top of a platform count, brushing against its side isn't standing on it
+    let Some(platform_velocity) = shape_hits
+        .iter()
+        .filter(|hit| -hit.normal2.y > 0.5)
+        .find_map(|hit| q_platforms.get(hit.entity).ok())
+    else {
+        return;
+    };
+    // Lyra is still grounded for the tick she jumps off, she shouldn't be pulled back down then
+    if velocity.0.y > platform_velocity.0.y + 0.5 {
+        return;
+    }
+    position.0 += platform_velocity.0 * time.delta_secs();
+}
@@ -34,6 +34,14 @@ pub struct OneWayPlatform {
     intersecting: EntityHashSet,
 }
 
+impl OneWayPlatform {
+    /// Entities currently passing through the platform.
+    #[cfg(feature = "dev_mode")]
+    pub fn intersecting(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.intersecting.iter().copied()
+    }
+}
+
 impl MergedTile for OneWayMarker {
     type CompareData = ();
 
@@ -0,0 +1,163 @@
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::game::{camera_op::SnapToLyra, lighting::LineLight2d, lyra::Lyra, Layers, LevelSystems};
+
+/// Distance between the front of a portal and Lyra when she comes out of it, so that she doesn't
+/// touch it right away.
+const LYRA_PORTAL_OFFSET: f32 = 8.0;
+
+pub struct PortalPlugin;
+
+impl Plugin for PortalPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<PortalBundle>("Portal");
+        app.add_observer(hydrate_portal);
+        app.add_systems(PreUpdate, link_portals.in_set(LevelSystems::Processing));
+    }
+}
+
+/// [`Component`] for one end of a pair of portals placed in LDtk. Beams going into the front of a
+/// portal come out of the front of its target, turned by the angle between the two.
+#[derive(Component, Debug)]
+pub struct Portal {
+    size: Vec2,
+    /// Direction the front of the portal faces.
+    pub facing: Dir2,
+    /// Iid of the LDtk entity of the target portal, if it has one.
+    target_iid: Option<String>,
+    /// The target portal, once it has been found among the spawned entities.
+    pub target: Option<Entity>,
+    /// Whether Lyra goes through the portal as well.
+    teleport_lyra: bool,
+}
+
+impl From<&EntityInstance> for Portal {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let facing = entity_instance
+            .get_float_field("facing")
+            .expect("facing needs to be a float field on all portals")
+            .to_radians();
+        let target_iid = entity_instance
+            .get_entity_ref_field("target")
+            .ok()
+            .map(|entity_ref| entity_ref.entity_iid.clone());
+        // optional field, so that portals only move beams unless told otherwise
+        let teleport_lyra = entity_instance
+            .get_bool_field("teleport_lyra")
+            .is_ok_and(|teleport_lyra| *teleport_lyra);
+
+        Portal {
+            size: Vec2::new(entity_instance.width as f32, entity_instance.height as f32),
+            facing: Dir2::from_xy_unchecked(facing.cos(), facing.sin()),
+            target_iid,
+            target: None,
+            teleport_lyra,
+        }
+    }
+}
+
+impl Portal {
+    /// Distance from the center of the portal to its front.
+    fn half_depth(&self) -> f32 {
+        (self.facing.x * self.size.x).abs() / 2.0 + (self.facing.y * self.size.y).abs() / 2.0
+    }
+}
+
+#[derive(Bundle, LdtkEntity)]
+pub struct PortalBundle {
+    #[from_entity_instance]
+    portal: Portal,
+}
+
+/// Where something going through the portal `from` at `pos` in `dir` comes out of the portal
+/// `to`, and in which direction. The point is mirrored across `from` and turned with the
+/// direction, so that it ends up in front of `to`.
+pub fn pass_through(
+    (from, from_transform): (&Portal, &GlobalTransform),
+    (to, to_transform): (&Portal, &GlobalTransform),
+    pos: Vec2,
+    dir: Dir2,
+) -> (Vec2, Dir2) {
+    let rotation = (-from.facing).rotation_to(to.facing);
+    let offset = pos - from_transform.translation().truncate();
+    (
+        to_transform.translation().truncate() + rotation * offset.reflect(*from.facing),
+        rotation * dir,
+    )
+}
+
+pub fn hydrate_portal(event: On<Add, Portal>, mut commands: Commands, q_portals: Query<&Portal>) {
+    let Ok(portal) = q_portals.get(event.entity) else {
+        return;
+    };
+    let color = Color::srgb(0.7, 0.3, 1.0);
+    commands
+        .entity(event.entity)
+        .insert(Collider::rectangle(portal.size.x, portal.size.y))
+        .insert(CollisionLayers::new(
+            [Layers::LightSensor, Layers::SensorBox],
+            [
+                Layers::LightRay,
+                Layers::WhiteRay,
+                Layers::BlueRay,
+                Layers::BlackRay,
+                Layers::PlayerHurtbox,
+            ],
+        ))
+        .insert(Sensor)
+        .insert(Sprite::from_color(color.with_alpha(0.6), portal.size))
+        .insert(LineLight2d::point(
+            color.to_linear().to_vec3().extend(0.5),
+            30.0,
+            0.02,
+        ));
+}
+
+/// [`System`] that finds the target of every portal that doesn't know it yet.
+pub fn link_portals(mut q_portals: Query<&mut Portal>, q_iids: Query<(Entity, &EntityIid)>) {
+    for mut portal in q_portals.iter_mut() {
+        if portal.target.is_some() {
+            continue;
+        }
+        let Some(target_iid) = portal.target_iid.as_deref() else {
+            continue;
+        };
+        portal.target = q_iids
+            .iter()
+            .find(|(_, iid)| iid.get() == target_iid)
+            .map(|(entity, _)| entity);
+    }
+}
+
+/// Observer on Lyra's hurtbox that moves her out of the target of the portals she walks into.
+pub fn enter_portal(
+    event: On<CollisionStart>,
+    mut commands: Commands,
+    q_portals: Query<(&Portal, &GlobalTransform)>,
+    lyra: Single<(&mut Transform, &mut Position, &mut LinearVelocity), With<Lyra>>,
+) {
+    let Ok((portal, portal_transform)) = q_portals.get(event.collider2) else {
+        return;
+    };
+    if !portal.teleport_lyra {
+        return;
+    }
+    let Some(target) = portal.target.and_then(|target| q_portals.get(target).ok()) else {
+        return;
+    };
+
+    let (mut transform, mut position, mut velocity) = lyra.into_inner();
+    let rotation = (-portal.facing).rotation_to(target.0.facing);
+    let exit = target.1.translation().truncate()
+        + *target.0.facing * (target.0.half_depth() + LYRA_PORTAL_OFFSET);
+    transform.translation = exit.extend(transform.translation.z);
+    position.0 = exit;
+    velocity.0 = rotation * velocity.0;
+    info!(
+        "Lyra went through portal at {}",
+        portal_transform.translation().truncate()
+    );
+    commands.trigger(SnapToLyra);
+}
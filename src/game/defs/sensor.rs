@@ -1,5 +1,5 @@
 use avian2d::prelude::{Collider, CollisionLayers, Friction};
-use bevy::{prelude::*, time::Stopwatch};
+use bevy::{platform::collections::HashMap, prelude::*, time::Stopwatch};
 use bevy_ecs_ldtk::prelude::*;
 use enum_map::EnumMap;
 
@@ -10,8 +10,10 @@ use crate::{
         light::{segments::simulate_light_sources, HitByLight, LightColor},
         lighting::LineLight2d,
         particle::spark::SparkExplosionEvent,
+        signal::{is_signal_id, LevelSignal},
         Layers, LevelSystems,
     },
+    ldtk::ParentLevelParam,
     shared::ResetLevels,
 };
 
@@ -56,13 +58,73 @@ impl FromWorld for SensorAssets {
     }
 }
 
+/// How a [`LightSensor`] reacts to its light coming and going.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SensorMode {
+    /// Activates once its meter fills up and deactivates once it drains back down.
+    #[default]
+    Meter,
+    /// Deactivates as soon as it stops being lit.
+    Momentary,
+    /// Stays active once activated, until the level is reset.
+    Latching,
+    /// Stays active for its hold time after it stops being lit.
+    Timed,
+}
+
+impl From<&String> for SensorMode {
+    fn from(value: &String) -> Self {
+        match value.as_str() {
+            "Meter" => SensorMode::Meter,
+            "Momentary" => SensorMode::Momentary,
+            "Latching" => SensorMode::Latching,
+            "Timed" => SensorMode::Timed,
+            _ => panic!("String {} does not represent a SensorMode", value),
+        }
+    }
+}
+
+/// How the states of the [`LightSensor`]s in a group are combined into whether their crystals are
+/// toggled.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SensorLogic {
+    /// Any of the sensors is active.
+    #[default]
+    Or,
+    /// All of the sensors are active.
+    And,
+    /// An odd number of the sensors are active.
+    Xor,
+}
+
+impl From<&String> for SensorLogic {
+    fn from(value: &String) -> Self {
+        match value.as_str() {
+            "Or" => SensorLogic::Or,
+            "And" => SensorLogic::And,
+            "Xor" => SensorLogic::Xor,
+            _ => panic!("String {} does not represent a SensorLogic", value),
+        }
+    }
+}
+
+impl SensorLogic {
+    fn eval(&self, mut states: impl Iterator<Item = bool>) -> bool {
+        match self {
+            SensorLogic::Or => states.any(|active| active),
+            SensorLogic::And => states.all(|active| active),
+            SensorLogic::Xor => states.filter(|active| *active).count() % 2 == 1,
+        }
+    }
+}
+
 /// [`Component`] added to entities receptive to light. The
 /// [`activation_timer`](LightSensor::activation_timer) should be initialized in the
 /// `From<&EntityInstance>` implemenation for the [`LightSensorBundle`], if not default.
 ///
 /// The [`Sprite`] on the entity containing a [`LightSensor`] refers to the center part of the
 /// sprite, which will be colored depending on the light that hits it.
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Clone)]
 pub struct LightSensor {
     /// Stores the cumulative time light has been hitting the sensor
     pub cumulative_exposure: Stopwatch,
@@ -74,14 +136,33 @@ pub struct LightSensor {
     pub is_active: bool,
     /// The color of the crystals to toggle
     pub toggle_color: CrystalColor,
+    /// Whether the sensor toggles the crystals of its color, or only sends its signals
+    pub toggle_crystals: bool,
+    /// Ids of the [`LevelSignal`]s sent when the sensor is activated or deactivated
+    pub signals: Vec<i32>,
     /// Meter's rate of change, per fixed timestep tick.
     rate: f32,
     /// Stored color used to animate the center of the sensor when the light no longer hits it
     stored_color: Color,
+    /// Colors that all need to hit the sensor at once for it to be lit. Any color will do if empty.
+    required_colors: Vec<LightColor>,
+    /// Whether the sensor is lit while it isn't hit instead. Inverted sensors start out active and
+    /// toggle their crystals when the level starts, so crystals are authored in the state they have
+    /// while the sensor is lit.
+    inverted: bool,
+    /// Whether the sensor was updated since the level was reset. Its first update sends the
+    /// signals and toggle of its starting state, as if it had just been activated.
+    started: bool,
+    mode: SensorMode,
+    /// How long a [`SensorMode::Timed`] sensor stays active after it stops being lit.
+    hold: Timer,
+    /// Id of the group of sensors toggling their crystals together, if any.
+    pub group: Option<i32>,
+    logic: SensorLogic,
 }
 
 impl LightSensor {
-    fn new(toggle_color: CrystalColor, millis: i32) -> Self {
+    fn new(toggle_color: CrystalColor, signals: Vec<i32>, millis: i32) -> Self {
         let rate = 1.0 / (millis as f32) * (1000.0 / 64.0);
         LightSensor {
             meter: 0.0,
@@ -89,22 +170,54 @@ impl LightSensor {
             hit_by: EnumMap::default(),
             is_active: false,
             toggle_color,
+            toggle_crystals: true,
+            signals,
             rate,
             stored_color: Color::WHITE,
+            required_colors: Vec::new(),
+            inverted: false,
+            started: false,
+            mode: SensorMode::default(),
+            hold: Timer::default(),
+            group: None,
+            logic: SensorLogic::default(),
         }
     }
 
     fn reset(&mut self) {
-        self.meter = 0.0;
+        self.meter = if self.inverted { 1.0 } else { 0.0 };
         self.hit_by = EnumMap::default();
-        self.is_active = false;
+        self.is_active = self.inverted;
+        self.started = false;
         self.cumulative_exposure.reset();
+        self.hold.reset();
+    }
+
+    /// Brings back the meter of a saved sensor. The colors hitting it are left for the restored
+    /// beams to set again.
+    pub fn restore(&mut self, saved: &LightSensor) {
+        self.meter = saved.meter;
+        self.is_active = saved.is_active;
+        self.started = saved.started;
+        self.cumulative_exposure = saved.cumulative_exposure.clone();
+        self.stored_color = saved.stored_color;
+        self.hold = saved.hold.clone();
     }
 
     fn is_hit(&self) -> bool {
         self.hit_by.iter().any(|(_, hit_by_color)| *hit_by_color)
     }
 
+    /// Whether the light hitting the sensor is what it needs to fill up.
+    fn is_lit(&self) -> bool {
+        let lit = if self.required_colors.is_empty() {
+            self.is_hit()
+        } else {
+            self.required_colors.iter().all(|color| self.hit_by[*color])
+        };
+        lit != self.inverted
+    }
+
     fn iter_hit_color(&self) -> impl Iterator<Item = LightColor> + '_ {
         self.hit_by
             .iter()
@@ -123,7 +236,46 @@ impl From<&EntityInstance> for LightSensor {
             .get_int_field("activation_time")
             .expect("activation_time needs to be a float field on all sensors");
 
-        LightSensor::new(toggle_color, millis)
+        let mut signals: Vec<i32> = ["id", "platform_id"]
+            .iter()
+            .map(|field| {
+                *entity_instance
+                    .get_int_field(field)
+                    .unwrap_or_else(|_| panic!("{} needs to be an int field on all sensors", field))
+            })
+            .filter(|id| is_signal_id(*id))
+            .collect();
+        signals.dedup();
+
+        let mut sensor = LightSensor::new(toggle_color, signals, millis);
+
+        // optional fields, so that older ldtk projects without them still load
+        if let Ok(colors) = entity_instance.iter_enums_field("required_colors") {
+            sensor.required_colors = colors.map(LightColor::from).collect();
+        }
+        sensor.toggle_crystals = entity_instance
+            .get_bool_field("toggle_crystals")
+            .map_or(true, |toggle_crystals| *toggle_crystals);
+        sensor.inverted = entity_instance
+            .get_bool_field("inverted")
+            .is_ok_and(|inverted| *inverted);
+        if let Ok(mode) = entity_instance.get_enum_field("mode") {
+            sensor.mode = mode.into();
+        }
+        if let Ok(hold_time) = entity_instance.get_int_field("hold_time") {
+            sensor.hold = Timer::from_seconds((*hold_time).max(0) as f32 / 1000.0, TimerMode::Once);
+        }
+        sensor.group = entity_instance
+            .get_int_field("group")
+            .ok()
+            .copied()
+            .filter(|id| is_signal_id(*id));
+        if let Ok(logic) = entity_instance.get_enum_field("logic") {
+            sensor.logic = logic.into();
+        }
+        sensor.reset();
+
+        sensor
     }
 }
 
@@ -200,14 +352,71 @@ pub fn reset_light_sensors(_: On<ResetLevels>, mut q_sensors: Query<&mut LightSe
     }
 }
 
+/// The [`LightSensor`]s of a group seen by [`update_light_sensors`] in one tick.
+struct SensorGroup {
+    logic: SensorLogic,
+    toggle_color: CrystalColor,
+    toggle_crystals: bool,
+    /// The sensor the toggle sound and sparks come from.
+    entity: Entity,
+    level: LevelIid,
+    pos: Vec2,
+    signals: Vec<i32>,
+    /// Whether each sensor was active before and after this tick.
+    states: Vec<(bool, bool)>,
+    /// Whether one of the sensors hadn't started yet, so the toggle comes from the level starting.
+    starting: bool,
+}
+
 pub fn update_light_sensors(
     mut commands: Commands,
     mut q_sensors: Query<(Entity, &mut LightSensor, &mut Sprite, &GlobalTransform)>,
+    parent_level: ParentLevelParam,
     asset_server: Res<AssetServer>,
     time: Res<Time>,
     mut ev_spark_explosion: MessageWriter<SparkExplosionEvent>,
 ) {
+    let mut send_toggle = |entity: Entity,
+                           level: &LevelIid,
+                           pos: Vec2,
+                           toggle: (CrystalColor, bool),
+                           signals: &[i32],
+                           active: bool,
+                           starting: bool| {
+        let (toggle_color, toggle_crystals) = toggle;
+        if toggle_crystals {
+            commands.trigger(CrystalToggleEvent {
+                level: level.clone(),
+                color: toggle_color,
+            });
+        }
+        for id in signals.iter() {
+            commands.trigger(LevelSignal {
+                level: level.clone(),
+                id: *id,
+                active,
+            });
+        }
+        // the starting state of the level makes no sound
+        if starting {
+            return;
+        }
+        commands.entity(entity).with_child((
+            AudioPlayer::new(asset_server.load("sfx/button.wav")),
+            PlaybackSettings::DESPAWN,
+        ));
+        ev_spark_explosion.write(SparkExplosionEvent {
+            pos,
+            color: ButtonColor::from(toggle_color),
+        });
+    };
+
+    // group ids are only unique per level
+    let mut groups: HashMap<(LevelIid, i32), SensorGroup> = HashMap::new();
     for (entity, mut sensor, mut sprite, transform) in q_sensors.iter_mut() {
+        let Some(level) = parent_level.level_iid(entity) else {
+            continue;
+        };
         let was_hit = sensor.is_hit();
 
         if was_hit {
@@ -222,37 +431,262 @@ pub fn update_light_sensors(
             sensor.stored_color = Color::srgb(col.x, col.y, col.z);
         }
 
-        let juice = if was_hit { sensor.rate } else { -sensor.rate };
-        sensor.meter += juice;
-
-        let mut send_toggle = || {
-            commands.trigger(CrystalToggleEvent {
-                color: sensor.toggle_color,
-            });
-            commands.entity(entity).with_child((
-                AudioPlayer::new(asset_server.load("sfx/button.wav")),
-                PlaybackSettings::DESPAWN,
-            ));
-            ev_spark_explosion.write(SparkExplosionEvent {
-                pos: transform.translation().xy(),
-                color: ButtonColor::from(sensor.toggle_color),
-            });
+        let lit = sensor.is_lit();
+        let juice = match sensor.mode {
+            SensorMode::Meter => {
+                if lit {
+                    sensor.rate
+                } else {
+                    -sensor.rate
+                }
+            }
+            // empties the meter at once, so the sensor deactivates this tick
+            SensorMode::Momentary if !lit => f32::NEG_INFINITY,
+            SensorMode::Momentary => sensor.rate,
+            SensorMode::Latching if lit || sensor.is_active => sensor.rate,
+            SensorMode::Latching => -sensor.rate,
+            SensorMode::Timed if lit => {
+                sensor.hold.reset();
+                sensor.rate
+            }
+            SensorMode::Timed if sensor.is_active => {
+                sensor.hold.tick(time.delta());
+                if sensor.hold.is_finished() {
+                    f32::NEG_INFINITY
+                } else {
+                    0.0
+                }
+            }
+            SensorMode::Timed => -sensor.rate,
         };
+        sensor.meter += juice;
 
+        // a sensor that starts out active acts as if it was inactive before its first update
+        let starting = !sensor.started;
+        sensor.started = true;
+        let was_active = sensor.is_active && !starting;
         if sensor.meter > 1.0 {
-            if !sensor.is_active {
-                send_toggle();
-                sensor.is_active = true;
-            }
+            sensor.is_active = true;
             sensor.meter = 1.0;
         } else if sensor.meter < 0.0 {
-            if sensor.is_active {
-                send_toggle();
-                sensor.is_active = false;
-            }
+            sensor.is_active = false;
             sensor.meter = 0.0;
         }
+        let pos = transform.translation().xy();
+
+        match sensor.group {
+            Some(id) => {
+                let group = groups
+                    .entry((level.clone(), id))
+                    .or_insert_with(|| SensorGroup {
+                        logic: sensor.logic,
+                        toggle_color: sensor.toggle_color,
+                        toggle_crystals: sensor.toggle_crystals,
+                        entity,
+                        level: level.clone(),
+                        pos,
+                        signals: Vec::new(),
+                        states: Vec::new(),
+                        starting: false,
+                    });
+                if was_active != sensor.is_active {
+                    group.entity = entity;
+                    group.pos = pos;
+                }
+                group.signals.extend(sensor.signals.iter().copied());
+                group.states.push((was_active, sensor.is_active));
+                group.starting |= starting;
+            }
+            None if was_active != sensor.is_active => {
+                send_toggle(
+                    entity,
+                    level,
+                    pos,
+                    (sensor.toggle_color, sensor.toggle_crystals),
+                    &sensor.signals,
+                    sensor.is_active,
+                    starting,
+                );
+            }
+            None => {}
+        }
 
         sprite.color = Color::WHITE.mix(&sensor.stored_color, sensor.meter);
     }
+
+    // grouped sensors toggle their crystals once for the whole group
+    for group in groups.values_mut() {
+        let was_active = group.logic.eval(group.states.iter().map(|(was, _)| *was));
+        let is_active = group.logic.eval(group.states.iter().map(|(_, is)| *is));
+        if was_active != is_active {
+            group.signals.sort();
+            group.signals.dedup();
+            send_toggle(
+                group.entity,
+                &group.level,
+                group.pos,
+                (group.toggle_color, group.toggle_crystals),
+                &group.signals,
+                is_active,
+                group.starting,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Resource, Default)]
+    struct Sent {
+        toggles: Vec<(LevelIid, CrystalColor)>,
+        signals: Vec<(i32, bool)>,
+    }
+
+    fn sensor_app(sensors: impl IntoIterator<Item = LightSensor>) -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()));
+        app.add_message::<SparkExplosionEvent>();
+        app.init_resource::<Sent>();
+        app.add_observer(|event: On<CrystalToggleEvent>, mut sent: ResMut<Sent>| {
+            sent.toggles.push((event.level.clone(), event.color));
+        });
+        app.add_observer(|event: On<LevelSignal>, mut sent: ResMut<Sent>| {
+            sent.signals.push((event.id, event.active));
+        });
+        app.add_observer(reset_light_sensors);
+        app.add_systems(Update, update_light_sensors);
+        let level = app.world_mut().spawn(LevelIid::new("level")).id();
+        for sensor in sensors {
+            spawn_sensor(app.world_mut(), level, sensor);
+        }
+        app
+    }
+
+    fn spawn_sensor(world: &mut World, parent: Entity, sensor: LightSensor) {
+        world.spawn((
+            sensor,
+            Sprite::default(),
+            GlobalTransform::default(),
+            ChildOf(parent),
+        ));
+    }
+
+    fn toggle(level: &str, color: CrystalColor) -> (LevelIid, CrystalColor) {
+        (LevelIid::new(level), color)
+    }
+
+    fn inverted(toggle_color: CrystalColor, signals: Vec<i32>) -> LightSensor {
+        let mut sensor = LightSensor::new(toggle_color, signals, 100);
+        sensor.inverted = true;
+        sensor.reset();
+        sensor
+    }
+
+    #[test]
+    fn inverted_sensors_send_their_starting_state() {
+        let mut app = sensor_app([
+            inverted(CrystalColor::Red, vec![3]),
+            LightSensor::new(CrystalColor::Blue, vec![4], 100),
+        ]);
+        app.update();
+        app.update();
+        let sent = app.world().resource::<Sent>();
+        assert_eq!(sent.toggles, vec![toggle("level", CrystalColor::Red)]);
+        assert_eq!(sent.signals, vec![(3, true)]);
+
+        // and again after the crystals and listeners were reset with the level
+        app.world_mut().trigger(ResetLevels);
+        app.update();
+        let sent = app.world().resource::<Sent>();
+        assert_eq!(
+            sent.toggles,
+            vec![
+                toggle("level", CrystalColor::Red),
+                toggle("level", CrystalColor::Red)
+            ]
+        );
+        assert_eq!(sent.signals, vec![(3, true), (3, true)]);
+    }
+
+    #[test]
+    fn inverted_groups_send_their_starting_state_once() {
+        let grouped = || {
+            let mut sensor = inverted(CrystalColor::Red, vec![3]);
+            sensor.group = Some(1);
+            sensor.logic = SensorLogic::And;
+            sensor
+        };
+        let mut app = sensor_app([grouped(), grouped()]);
+        app.update();
+        app.update();
+        let sent = app.world().resource::<Sent>();
+        assert_eq!(sent.toggles, vec![toggle("level", CrystalColor::Red)]);
+        assert_eq!(sent.signals, vec![(3, true)]);
+    }
+
+    #[test]
+    fn momentary_sensors_deactivate_as_soon_as_they_are_unlit() {
+        let mut sensor = LightSensor::new(CrystalColor::Red, vec![3], 1);
+        sensor.mode = SensorMode::Momentary;
+        let mut app = sensor_app([sensor]);
+        let update_hit = |app: &mut App, hit: bool| {
+            let mut q_sensors = app.world_mut().query::<&mut LightSensor>();
+            for mut sensor in q_sensors.iter_mut(app.world_mut()) {
+                sensor.hit_by[LightColor::Green] = hit;
+            }
+            app.update();
+            let mut q_sensors = app.world_mut().query::<&LightSensor>();
+            q_sensors.single(app.world()).unwrap().is_active
+        };
+        assert!(update_hit(&mut app, true));
+        assert!(!update_hit(&mut app, false));
+        let sent = app.world().resource::<Sent>();
+        assert_eq!(sent.signals, vec![(3, true), (3, false)]);
+    }
+
+    #[test]
+    fn groups_with_the_same_id_in_other_levels_are_separate() {
+        let grouped = |toggle_color| {
+            let mut sensor = inverted(toggle_color, vec![3]);
+            sensor.group = Some(1);
+            sensor
+        };
+        let mut app = sensor_app([grouped(CrystalColor::Red)]);
+        let neighbour = app.world_mut().spawn(LevelIid::new("neighbour")).id();
+        spawn_sensor(app.world_mut(), neighbour, grouped(CrystalColor::Blue));
+        app.update();
+        let mut toggles = app.world().resource::<Sent>().toggles.clone();
+        toggles.sort_by_key(|(level, _)| level.get().clone());
+        assert_eq!(
+            toggles,
+            vec![
+                toggle("level", CrystalColor::Red),
+                toggle("neighbour", CrystalColor::Blue)
+            ]
+        );
+    }
+
+    #[test]
+    fn sensors_outside_the_current_level_toggle_their_own_level() {
+        let mut app = sensor_app([inverted(CrystalColor::Red, vec![3])]);
+        // a loaded neighbour, whose sensors are nested in an entity layer like in bevy_ecs_ldtk
+        let neighbour = app.world_mut().spawn(LevelIid::new("neighbour")).id();
+        let layer = app.world_mut().spawn(ChildOf(neighbour)).id();
+        spawn_sensor(
+            app.world_mut(),
+            layer,
+            inverted(CrystalColor::Blue, vec![3]),
+        );
+        app.update();
+        let sent = app.world().resource::<Sent>();
+        assert_eq!(
+            sent.toggles,
+            vec![
+                toggle("level", CrystalColor::Red),
+                toggle("neighbour", CrystalColor::Blue)
+            ]
+        );
+    }
 }
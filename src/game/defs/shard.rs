@@ -19,7 +19,8 @@ use crate::{
         Layers,
     },
     ldtk::{LdtkLevelParam, LevelExt},
-    shared::{AnimationState, PlayState, ResetLevels},
+    save::SaveData,
+    shared::{AnimationState, GameState, PlayState, ResetLevels},
     sound::{BgmMarker, Fade, FadeSettings, BGM_VOLUME},
 };
 
@@ -35,6 +36,7 @@ impl Plugin for CrystalShardPlugin {
         app.add_observer(on_add_crystal_shard);
         app.add_observer(reset_shard_visibility);
         app.add_observer(reset_shard_effects_cache);
+        app.add_systems(OnEnter(GameState::InGame), restore_saved_shards);
         app.add_observer(start_shard_animation);
     }
 }
@@ -93,7 +95,7 @@ pub fn on_add_crystal_shard(
     };
 
     const CRYSTAL_SHARD_FRAMES: usize = 7;
-    const CRYSTAL_SHARD_ROWS: usize = 4;
+    const CRYSTAL_SHARD_ROWS: usize = 5;
 
     let texture_atlas_layout = texture_atlas_layouts.add(TextureAtlasLayout::from_grid(
         UVec2::new(12, 16),
@@ -108,7 +110,7 @@ pub fn on_add_crystal_shard(
         LightColor::Green => 1,
         LightColor::Purple => 2,
         LightColor::White => 3,
-        // LightColor::Black => 4,
+        LightColor::Black => 4,
     };
 
     let start_index = shard_row(shard) * CRYSTAL_SHARD_FRAMES;
@@ -153,12 +155,39 @@ pub fn reset_shard_visibility(
         *visibility = Visibility::Visible;
     }
 }
-pub fn reset_shard_effects_cache(_: On<ResetLevels>, mut shard_mods: ResMut<CrystalShardMods>) {
-    for (_, is_temporary) in shard_mods.0.iter_mut() {
-        *is_temporary = false;
+/// Sets the [`CrystalShardMods`] to the shards collected in earlier completed runs of the current
+/// level, so that replaying a level doesn't mean finding its shards again.
+fn set_saved_shards(
+    shard_mods: &mut CrystalShardMods,
+    save: &SaveData,
+    ldtk_level_param: &LdtkLevelParam,
+) {
+    shard_mods.0 = EnumMap::default();
+    let Some(level) = ldtk_level_param.cur_level() else {
+        return;
+    };
+    for color in save.shards(level.raw()) {
+        shard_mods.0[*color] = true;
     }
 }
 
+pub fn restore_saved_shards(
+    mut shard_mods: ResMut<CrystalShardMods>,
+    save: Res<SaveData>,
+    ldtk_level_param: LdtkLevelParam,
+) {
+    set_saved_shards(&mut shard_mods, &save, &ldtk_level_param);
+}
+
+pub fn reset_shard_effects_cache(
+    _: On<ResetLevels>,
+    mut shard_mods: ResMut<CrystalShardMods>,
+    save: Res<SaveData>,
+    ldtk_level_param: LdtkLevelParam,
+) {
+    set_saved_shards(&mut shard_mods, &save, &ldtk_level_param);
+}
+
 pub fn on_player_intersect_shard(
     event: On<CollisionStart>,
     mut commands: Commands,
@@ -271,6 +300,7 @@ pub fn on_shard_zoom_in_finished(
         LightColor::Blue => "Blue light, formerly known as the light of harmony. Could this one shoot through the active blue crystals above me?",
         LightColor::White => "A different feeling than before... could this color have a special reflective properties?",
         LightColor::Purple => "This one's even more powerful... the purple light beam should bounce twice instead of once.",
+        LightColor::Black => "This one feels... hungry. I bet a black beam would swallow any other light that crosses it.",
     };
 
     commands.trigger(Dialogue {
@@ -5,6 +5,7 @@ use bevy::prelude::*;
 use crate::{
     asset::LoadResource,
     callback::Callback,
+    input::{ActionInput, InputAction},
     ui::{UiFont, UiFontSize},
 };
 
@@ -137,8 +138,7 @@ pub fn handle_start_dialogue(
 #[allow(clippy::too_many_arguments)]
 pub fn do_dialogue(
     mut commands: Commands,
-    keys: Res<ButtonInput<KeyCode>>,
-    mouse: Res<ButtonInput<MouseButton>>,
+    input: ActionInput,
     time: Res<Time>,
     dialogue_res: ResMut<DialogueRes>,
     dialogue_box: Single<Entity, With<DialogueBox>>,
@@ -155,9 +155,7 @@ pub fn do_dialogue(
     let dialogue = dialogue_res.dialogue.as_ref().unwrap();
     let wanted_text = dialogue.entries[dialogue_res.index].text.as_str();
 
-    if keys.any_just_pressed([KeyCode::Space, KeyCode::Enter])
-        || mouse.just_pressed(MouseButton::Left)
-    {
+    if input.just_pressed(InputAction::Confirm) {
         match text.len().cmp(&wanted_text.len()) {
             Ordering::Less => {
                 //if animating the text rn, display it fully
@@ -0,0 +1,679 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use avian2d::prelude::{Collider, CollisionLayers};
+use bevy::{asset::io::file::FileAssetReader, prelude::*};
+use bevy_ecs_ldtk::{
+    ldtk::{EntityInstance, Level},
+    prelude::*,
+};
+use bevy_ecs_tilemap::tiles::TileVisible;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::{
+    config::Config,
+    game::{
+        cursor::CursorWorldCoords,
+        defs::{
+            crystal::{Crystal, CrystalColor, RemergeCrystals},
+            mirror::Mirror,
+            sensor::{ButtonColor, LightSensor},
+            spikes::Spike,
+        },
+    },
+    ldtk::{LdtkLevelParam, LdtkProjectReloaded, LevelExt},
+    shared::{AnimationState, GameState, PlayState, ResetLevels},
+    ui::UiFont,
+};
+
+const TERRAIN_LAYER_IDENT: &str = "Terrain";
+const ENTITY_LAYER_IDENT: &str = "Entities";
+const SENSOR_ENTITY_IDENT: &str = "Sensor";
+
+/// [`Plugin`] for the overlay that tweaks puzzles without LDtk, only built with the `dev_mode`
+/// feature. F2 toggles it and Ctrl+S writes the edits back into the `.ldtk` file.
+pub struct LevelEditorPlugin;
+
+impl Plugin for LevelEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelEditor>();
+        app.add_systems(
+            Update,
+            toggle_level_editor.run_if(in_state(GameState::InGame)),
+        );
+        app.add_systems(
+            Update,
+            (
+                select_editor_tool,
+                edit_level,
+                save_level_edits,
+                draw_level_editor,
+            )
+                .chain()
+                .run_if(in_state(GameState::InGame).and(level_editor_enabled)),
+        );
+        app.add_systems(OnExit(GameState::InGame), close_level_editor);
+        app.add_observer(forget_project_on_reload);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EditorTool {
+    Crystal(CrystalColor, bool),
+    Mirror,
+    Spike,
+    Sensor(CrystalColor),
+}
+
+const CRYSTAL_COLORS: [CrystalColor; 4] = [
+    CrystalColor::Pink,
+    CrystalColor::Red,
+    CrystalColor::White,
+    CrystalColor::Blue,
+];
+
+const EDITOR_TOOLS: [EditorTool; 14] = [
+    EditorTool::Crystal(CrystalColor::Pink, true),
+    EditorTool::Crystal(CrystalColor::Pink, false),
+    EditorTool::Crystal(CrystalColor::Red, true),
+    EditorTool::Crystal(CrystalColor::Red, false),
+    EditorTool::Crystal(CrystalColor::White, true),
+    EditorTool::Crystal(CrystalColor::White, false),
+    EditorTool::Crystal(CrystalColor::Blue, true),
+    EditorTool::Crystal(CrystalColor::Blue, false),
+    EditorTool::Mirror,
+    EditorTool::Spike,
+    EditorTool::Sensor(CrystalColor::Pink),
+    EditorTool::Sensor(CrystalColor::Red),
+    EditorTool::Sensor(CrystalColor::White),
+    EditorTool::Sensor(CrystalColor::Blue),
+];
+
+impl EditorTool {
+    /// Value of the cell on the `Terrain` int grid, [`None`] for tools placing entities.
+    fn int_grid_value(&self) -> Option<i32> {
+        match self {
+            EditorTool::Crystal(color, active) => {
+                let index = CRYSTAL_COLORS
+                    .iter()
+                    .position(|c| c == color)
+                    .expect("every crystal color should be listed")
+                    as i32;
+                Some(3 + index * 2 + if *active { 0 } else { 1 })
+            }
+            EditorTool::Mirror => Some(16),
+            EditorTool::Spike => Some(2),
+            EditorTool::Sensor(_) => None,
+        }
+    }
+
+    fn from_int_grid_value(value: i32) -> Option<Self> {
+        EDITOR_TOOLS
+            .into_iter()
+            .find(|tool| tool.int_grid_value() == Some(value))
+    }
+
+    fn name(&self) -> String {
+        match self {
+            EditorTool::Crystal(color, true) => format!("{:?} crystal", color),
+            EditorTool::Crystal(color, false) => format!("Inactive {:?} crystal", color),
+            EditorTool::Mirror => "Mirror".into(),
+            EditorTool::Spike => "Spike".into(),
+            EditorTool::Sensor(color) => format!("{:?} sensor", color),
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            EditorTool::Crystal(color, true) | EditorTool::Sensor(color) => {
+                ButtonColor::from(*color)
+            }
+            EditorTool::Crystal(color, false) => ButtonColor::from(*color).with_alpha(0.4),
+            EditorTool::Mirror => Color::srgb(0.8, 0.8, 0.8),
+            EditorTool::Spike => Color::srgb(0.46, 0.62, 0.79),
+        }
+    }
+}
+
+/// A cell of the current level under the cursor, with `y` going down like in LDtk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct EditorCell {
+    x: i32,
+    y: i32,
+}
+
+/// The cell of a level at a world position, along with its [`GridCoords`] and world center.
+fn cursor_cell(level: &Level, pos: Vec2) -> Option<(EditorCell, GridCoords, Vec2)> {
+    let level_box = level.level_box();
+    if !level_box.contains(pos) {
+        return None;
+    }
+    let terrain = level
+        .layer_instances
+        .iter()
+        .flatten()
+        .find(|layer| layer.identifier == TERRAIN_LAYER_IDENT)?;
+    let grid_size = terrain.grid_size as f32;
+    let coords = ((pos - level_box.min) / grid_size).floor();
+    let grid_coords = GridCoords::new(coords.x as i32, coords.y as i32);
+    let cell = EditorCell {
+        x: grid_coords.x,
+        y: terrain.c_hei - 1 - grid_coords.y,
+    };
+    Some((
+        cell,
+        grid_coords,
+        level_box.min + (coords + 0.5) * grid_size,
+    ))
+}
+
+/// Placement of a tool in a level, or removal of whatever was there if the tool is [`None`].
+#[derive(Debug, Clone, Copy)]
+struct Edit {
+    world_pos: Vec2,
+    tool: Option<EditorTool>,
+}
+
+#[derive(Resource, Default)]
+struct LevelEditor {
+    enabled: bool,
+    tool: usize,
+    /// The project file being edited, read from disk on the first edit.
+    project: Option<Value>,
+    /// Every edit made this session by level iid and cell, drawn by the overlay.
+    edits: HashMap<(String, EditorCell), Edit>,
+    unsaved: usize,
+}
+
+/// Marks the cells and entities spawned by the editor, which are despawned instead of hidden when
+/// removed.
+#[derive(Component)]
+struct EditorSpawned;
+
+#[derive(Component)]
+struct LevelEditorUi;
+
+fn level_editor_enabled(editor: Res<LevelEditor>) -> bool {
+    editor.enabled
+}
+
+fn project_path(config: &Config) -> PathBuf {
+    FileAssetReader::get_base_path()
+        .join("assets")
+        .join(&config.level_config.level_path)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn toggle_level_editor(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut editor: ResMut<LevelEditor>,
+    play_state: Res<State<PlayState>>,
+    mut next_play_state: ResMut<NextState<PlayState>>,
+    mut next_anim_state: ResMut<NextState<AnimationState>>,
+    ui_font: Res<UiFont>,
+    q_ui: Query<Entity, With<LevelEditorUi>>,
+) {
+    if !keys.just_pressed(KeyCode::F2) {
+        return;
+    }
+    if editor.enabled {
+        editor.enabled = false;
+        next_play_state.set(PlayState::Playing);
+        for entity in q_ui.iter() {
+            commands.entity(entity).despawn();
+        }
+        return;
+    }
+    if *play_state.get() != PlayState::Playing {
+        return;
+    }
+
+    editor.enabled = true;
+    // NOTE: freezes the level the same way as switching levels, which also keeps clicks from
+    // shooting beams
+    next_play_state.set(PlayState::Animating);
+    next_anim_state.set(AnimationState::Frozen);
+    commands
+        .spawn(LevelEditorUi)
+        .insert(Text::new(""))
+        .insert(Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(16.),
+            left: Val::Px(16.),
+            ..default()
+        })
+        .insert(ui_font.text_font().with_font_size(24.));
+}
+
+fn close_level_editor(
+    mut commands: Commands,
+    mut editor: ResMut<LevelEditor>,
+    q_ui: Query<Entity, With<LevelEditorUi>>,
+) {
+    editor.enabled = false;
+    editor.edits.clear();
+    for entity in q_ui.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Drops the copy of the project file when it changes on disk, unless it has unsaved edits.
+fn forget_project_on_reload(_: On<LdtkProjectReloaded>, mut editor: ResMut<LevelEditor>) {
+    if editor.unsaved > 0 {
+        warn!(
+            "The LDtk project changed on disk, saving the level editor's {} edit(s) will overwrite it",
+            editor.unsaved
+        );
+        return;
+    }
+    editor.project = None;
+}
+
+fn select_editor_tool(keys: Res<ButtonInput<KeyCode>>, mut editor: ResMut<LevelEditor>) {
+    if !keys.just_pressed(KeyCode::Tab) {
+        return;
+    }
+    let step = if keys.pressed(KeyCode::ShiftLeft) {
+        EDITOR_TOOLS.len() - 1
+    } else {
+        1
+    };
+    editor.tool = (editor.tool + step) % EDITOR_TOOLS.len();
+}
+
+/// Finds the layer of the project file with the given identifier in the level with the given iid.
+fn json_layer<'a>(
+    project: &'a mut Value,
+    level_iid: &str,
+    identifier: &str,
+) -> Option<&'a mut Value> {
+    project["levels"]
+        .as_array_mut()?
+        .iter_mut()
+        .find(|level| level["iid"] == level_iid)?["layerInstances"]
+        .as_array_mut()?
+        .iter_mut()
+        .find(|layer| layer["__identifier"] == identifier)
+}
+
+/// The value LDtk gives a field that was never edited, from its field definition.
+fn default_field_value(field_def: &Value) -> Value {
+    if let Some(value) = field_def["defaultOverride"]["params"].get(0) {
+        return value.clone();
+    }
+    if field_def["isArray"] == true {
+        return json!([]);
+    }
+    if field_def["canBeNull"] == true {
+        return Value::Null;
+    }
+    match field_def["__type"].as_str() {
+        Some("Int") => json!(0),
+        Some("Float") => json!(0.0),
+        Some("Bool") => json!(false),
+        Some("String") => json!(""),
+        _ => Value::Null,
+    }
+}
+
+/// A new sensor instance built from the `Sensor` entity definition of the project, with every
+/// field but its color left at its default like a sensor freshly placed in LDtk.
+fn sensor_instance(
+    project: &Value,
+    cell: EditorCell,
+    grid_size: i32,
+    level_origin: IVec2,
+    color: CrystalColor,
+) -> Option<Value> {
+    let def = project["defs"]["entities"]
+        .as_array()?
+        .iter()
+        .find(|def| def["identifier"] == SENSOR_ENTITY_IDENT)?;
+
+    let color = format!("{:?}", color);
+    let fields = def["fieldDefs"]
+        .as_array()?
+        .iter()
+        .map(|field_def| {
+            let (value, editor_values) = match field_def["identifier"].as_str() {
+                Some("toggle_color") => (
+                    json!(color),
+                    json!([{ "id": "V_String", "params": [color] }]),
+                ),
+                _ => (default_field_value(field_def), json!([])),
+            };
+            json!({
+                "__identifier": field_def["identifier"],
+                "__type": field_def["__type"],
+                "__value": value,
+                "__tile": null,
+                "defUid": field_def["uid"],
+                "realEditorValues": editor_values,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let px = IVec2::new(cell.x, cell.y) * grid_size;
+    Some(json!({
+        "__identifier": SENSOR_ENTITY_IDENT,
+        "__grid": [cell.x, cell.y],
+        "__pivot": [def["pivotX"], def["pivotY"]],
+        "__tags": def["tags"],
+        "__tile": def["tileRect"],
+        "__smartColor": def["color"],
+        "iid": new_iid(),
+        "width": def["width"],
+        "height": def["height"],
+        "defUid": def["uid"],
+        "px": [px.x, px.y],
+        "fieldInstances": fields,
+        "__worldX": level_origin.x + px.x,
+        "__worldY": level_origin.y + px.y,
+    }))
+}
+
+/// A random version 4 UUID, like the iids LDtk generates.
+fn new_iid() -> String {
+    let bytes: [u8; 16] = rand::random();
+    let hex = bytes
+        .iter()
+        .enumerate()
+        .map(|(i, byte)| match i {
+            6 => format!("{:02x}", byte & 0x0f | 0x40),
+            8 => format!("{:02x}", byte & 0x3f | 0x80),
+            _ => format!("{:02x}", byte),
+        })
+        .collect::<String>();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// Applies a single edit to the project file, returning the sensor instance if one was placed.
+fn edit_project(
+    project: &mut Value,
+    level_iid: &str,
+    level_origin: IVec2,
+    cell: EditorCell,
+    tool: Option<EditorTool>,
+) -> Result<Option<Value>, String> {
+    let terrain =
+        json_layer(project, level_iid, TERRAIN_LAYER_IDENT).ok_or("level has no Terrain layer")?;
+    let width = terrain["__cWid"]
+        .as_i64()
+        .ok_or("Terrain layer has no width")? as i32;
+    let grid_size = terrain["__gridSize"]
+        .as_i64()
+        .ok_or("Terrain layer has no grid size")? as i32;
+    let csv = terrain["intGridCsv"]
+        .as_array_mut()
+        .ok_or("Terrain layer has no int grid")?;
+    let cell_value = csv
+        .get_mut((cell.y * width + cell.x) as usize)
+        .ok_or("cell is outside of the Terrain layer")?;
+    let current = cell_value.as_i64().unwrap_or(0) as i32;
+    if current != 0 && EditorTool::from_int_grid_value(current).is_none() {
+        return Err(format!(
+            "cell holds int grid value {}, which can't be edited",
+            current
+        ));
+    }
+    *cell_value = json!(tool.and_then(|tool| tool.int_grid_value()).unwrap_or(0));
+
+    let sensor = match tool {
+        Some(EditorTool::Sensor(color)) => Some(
+            sensor_instance(project, cell, grid_size, level_origin, color)
+                .ok_or("the project has no Sensor entity definition")?,
+        ),
+        _ => None,
+    };
+    let entities = json_layer(project, level_iid, ENTITY_LAYER_IDENT)
+        .and_then(|layer| layer["entityInstances"].as_array_mut())
+        .ok_or("level has no Entities layer")?;
+    entities.retain(|entity| {
+        entity["__identifier"] != SENSOR_ENTITY_IDENT || entity["__grid"] != json!([cell.x, cell.y])
+    });
+    if let Some(sensor) = sensor.as_ref() {
+        entities.push(sensor.clone());
+    }
+    Ok(sensor)
+}
+
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::type_complexity)]
+fn edit_level(
+    mut commands: Commands,
+    mouse: Res<ButtonInput<MouseButton>>,
+    cursor: Single<&CursorWorldCoords>,
+    mut editor: ResMut<LevelEditor>,
+    config: Res<Config>,
+    ldtk_level_param: LdtkLevelParam,
+    q_levels: Query<(Entity, &LevelIid)>,
+    q_layers: Query<(Entity, &LayerMetadata, &ChildOf)>,
+    q_cells: Query<(Entity, &GridCoords, &ChildOf, Has<EditorSpawned>), With<IntGridCell>>,
+    q_sensors: Query<(Entity, &GlobalTransform), With<LightSensor>>,
+) {
+    let tool = if mouse.just_pressed(MouseButton::Left) {
+        Some(EDITOR_TOOLS[editor.tool])
+    } else if mouse.just_pressed(MouseButton::Right) {
+        None
+    } else {
+        return;
+    };
+    let Some(level) = ldtk_level_param.cur_level() else {
+        return;
+    };
+    let level = level.raw();
+    let Some((cell, grid_coords, world_center)) = cursor_cell(level, cursor.pos) else {
+        return;
+    };
+
+    if editor.project.is_none() {
+        let path = project_path(&config);
+        match std::fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|contents| serde_json::from_str(&contents).map_err(|err| err.to_string()))
+        {
+            Ok(project) => editor.project = Some(project),
+            Err(err) => {
+                error!("Failed to read {}: {}", path.display(), err);
+                return;
+            }
+        }
+    }
+    let project = editor.project.as_mut().expect("project was just read");
+    let sensor = match edit_project(
+        project,
+        &level.iid,
+        IVec2::new(level.world_x, level.world_y),
+        cell,
+        tool,
+    ) {
+        Ok(sensor) => sensor,
+        Err(err) => {
+            warn!("Can't edit [{}, {}]: {}", cell.x, cell.y, err);
+            return;
+        }
+    };
+
+    let Some((level_entity, _)) = q_levels.iter().find(|(_, iid)| iid.as_str() == level.iid) else {
+        return;
+    };
+    let layer = |identifier: &str| {
+        q_layers
+            .iter()
+            .find(|(_, metadata, ChildOf(parent))| {
+                *parent == level_entity && metadata.identifier == identifier
+            })
+            .map(|(entity, _, _)| entity)
+    };
+    let (Some(terrain_layer), Some(entity_layer)) =
+        (layer(TERRAIN_LAYER_IDENT), layer(ENTITY_LAYER_IDENT))
+    else {
+        return;
+    };
+    // NOTE: layers are placed at the bottom left corner of their level
+    let center = world_center - level.level_box().min;
+
+    // NOTE: tiles of the tilemap can't be despawned without updating its storage, so they are
+    // hidden and stripped of what makes them a crystal, mirror or spike instead
+    for (entity, coords, ChildOf(parent), spawned) in q_cells.iter() {
+        if *parent != terrain_layer || *coords != grid_coords {
+            continue;
+        }
+        if spawned {
+            commands.entity(entity).despawn();
+        } else {
+            commands
+                .entity(entity)
+                .remove::<(
+                    IntGridCell,
+                    Crystal,
+                    Mirror,
+                    Spike,
+                    Collider,
+                    CollisionLayers,
+                )>()
+                .insert(TileVisible(false));
+        }
+    }
+    for (entity, transform) in q_sensors.iter() {
+        if transform.translation().xy().distance(world_center) < 1. {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    if let Some(value) = tool.and_then(|tool| tool.int_grid_value()) {
+        let cell_entity = commands
+            .spawn(EditorSpawned)
+            .insert(IntGridCell { value })
+            .insert(grid_coords)
+            .insert(Transform::from_translation(center.extend(0.)))
+            .insert(ChildOf(terrain_layer))
+            .id();
+        match tool {
+            Some(EditorTool::Crystal(..)) => {
+                commands
+                    .entity(cell_entity)
+                    .insert(Crystal::from(IntGridCell { value }));
+            }
+            Some(EditorTool::Mirror) => {
+                commands.entity(cell_entity).insert(Mirror);
+            }
+            _ => {
+                commands.entity(cell_entity).insert(Spike {});
+            }
+        }
+    }
+    if let Some(sensor) = sensor {
+        match serde_json::from_value::<EntityInstance>(sensor) {
+            Ok(instance) => {
+                commands
+                    .spawn(EditorSpawned)
+                    .insert(LightSensor::from(&instance))
+                    .insert(Transform::from_translation(center.extend(0.)))
+                    .insert(ChildOf(entity_layer));
+            }
+            Err(err) => error!("Failed to spawn the new sensor: {}", err),
+        }
+    }
+
+    commands.trigger(ResetLevels);
+    commands.trigger(RemergeCrystals {
+        level: level_entity,
+    });
+    editor.edits.insert(
+        (level.iid.clone(), cell),
+        Edit {
+            world_pos: world_center,
+            tool,
+        },
+    );
+    editor.unsaved += 1;
+}
+
+fn save_level_edits(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut editor: ResMut<LevelEditor>,
+    config: Res<Config>,
+) {
+    if !(keys.pressed(KeyCode::ControlLeft) && keys.just_pressed(KeyCode::KeyS)) {
+        return;
+    }
+    let Some(project) = editor.project.as_ref() else {
+        return;
+    };
+
+    // NOTE: LDtk indents with tabs too, the rest of its formatting comes back once it saves again
+    let mut contents = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(b"\t");
+    let mut serializer = serde_json::Serializer::with_formatter(&mut contents, formatter);
+    if let Err(err) = project.serialize(&mut serializer) {
+        error!("Failed to serialize the LDtk project: {}", err);
+        return;
+    }
+
+    let path = project_path(&config);
+    match std::fs::write(&path, contents) {
+        Ok(()) => {
+            info!(
+                "Saved {} level edit(s) to {}",
+                editor.unsaved,
+                path.display()
+            );
+            editor.unsaved = 0;
+        }
+        Err(err) => error!("Failed to write {}: {}", path.display(), err),
+    }
+}
+
+fn draw_level_editor(
+    mut gizmos: Gizmos,
+    editor: Res<LevelEditor>,
+    cursor: Single<&CursorWorldCoords>,
+    ldtk_level_param: LdtkLevelParam,
+    mut q_editor_ui: Query<&mut Text, With<LevelEditorUi>>,
+) {
+    // NOTE: every level uses the default grid size of 8
+    let cell_size = Vec2::splat(8.);
+    // edited cells are spawned without tiles, since auto-layer rules only run inside LDtk
+    for edit in editor.edits.values() {
+        match edit.tool {
+            Some(tool) => {
+                gizmos.rect_2d(edit.world_pos, cell_size * 0.75, tool.color());
+            }
+            None => {
+                let half = cell_size / 2. * 0.75;
+                let color = Color::srgb(1.0, 0.3, 0.3);
+                gizmos.line_2d(edit.world_pos - half, edit.world_pos + half, color);
+                gizmos.line_2d(
+                    edit.world_pos + Vec2::new(-half.x, half.y),
+                    edit.world_pos + Vec2::new(half.x, -half.y),
+                    color,
+                );
+            }
+        }
+    }
+
+    let tool = EDITOR_TOOLS[editor.tool];
+    if let Some((_, _, center)) = ldtk_level_param
+        .cur_level()
+        .and_then(|level| cursor_cell(level.raw(), cursor.pos))
+    {
+        gizmos.rect_2d(center, cell_size, tool.color());
+    }
+
+    if let Ok(mut editor_ui) = q_editor_ui.single_mut() {
+        editor_ui.0 = format!(
+            "Level editor: {}\n[Tab] next tool  [LMB] place  [RMB] remove  [Ctrl+S] save ({} unsaved)",
+            tool.name(),
+            editor.unsaved
+        );
+    }
+}
@@ -0,0 +1,408 @@
+use std::collections::HashMap;
+
+use avian2d::prelude::*;
+use bevy::{
+    camera::RenderTarget, core_pipeline::tonemapping::Tonemapping, prelude::*, render::view::Hdr,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera::{build_render_target, GHOST_LAYER, HIGHRES_LAYER},
+    game::{
+        level_completion::{LevelCompleted, LevelStarted},
+        light::{LightBeamSource, LightColor},
+        lyra::{
+            animation::{flip_player_direction, PlayerAnimationType, ANIMATION_FRAMES},
+            beam::PlayerBeam,
+            Lyra,
+        },
+        Layers, LevelSystems,
+    },
+    ldtk::{LdtkLevelParam, LevelExt},
+    save::storage,
+    shared::{GameState, ResetLevels},
+};
+
+/// Bumped whenever the layout of [`GhostRun`] changes. Ghosts of other versions are ignored.
+const GHOST_VERSION: u32 = 1;
+
+/// How long the beams shot by the ghost stay visible, in seconds.
+const GHOST_BEAM_DURATION: f32 = 1.0;
+
+/// Length of ghost beams that don't hit any terrain.
+const GHOST_BEAM_MAX_LENGTH: f32 = 400.0;
+
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GhostSettings>();
+        app.init_resource::<GhostRecorder>();
+        app.init_resource::<BestGhosts>();
+        app.init_resource::<GhostAssets>();
+        app.add_observer(start_ghost_recording);
+        app.add_observer(save_best_ghost);
+        app.add_observer(discard_ghost_recording);
+        app.add_observer(spawn_ghost);
+        app.add_systems(
+            FixedUpdate,
+            (record_ghost_frame, play_ghost)
+                .after(flip_player_direction)
+                .in_set(LevelSystems::Simulation),
+        );
+        app.add_systems(Update, draw_ghost_beams.run_if(in_state(GameState::InGame)));
+        app.add_systems(OnExit(GameState::InGame), despawn_ghost);
+    }
+}
+
+/// [`Resource`] holding whether the ghost of the best run is shown. Toggled from the pause menu.
+#[derive(Resource, Default)]
+pub struct GhostSettings {
+    pub enabled: bool,
+}
+
+/// Where Lyra was and what she looked like during a single fixed timestep.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GhostFrame {
+    pub position: Vec2,
+    pub animation: PlayerAnimationType,
+    /// Index into the Lyra sprite sheet.
+    pub sprite_index: usize,
+    pub flip_x: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GhostBeam {
+    /// Index of the [`GhostFrame`] during which the beam was shot.
+    pub frame: usize,
+    pub color: LightColor,
+    pub start: Vec2,
+    pub dir: Vec2,
+}
+
+/// The best run of a level, from its start marker to its end marker.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GhostRun {
+    version: u32,
+    pub level_iid: String,
+    pub time_ms: u64,
+    pub frames: Vec<GhostFrame>,
+    #[serde(default)]
+    pub beams: Vec<GhostBeam>,
+}
+
+impl GhostRun {
+    fn storage_name(level_id: &str) -> String {
+        format!("ghosts/{}", level_id)
+    }
+
+    fn load(level_id: &str) -> Option<Self> {
+        let contents = storage::read(&Self::storage_name(level_id))?;
+        match toml::from_str::<GhostRun>(&contents) {
+            Ok(run) if run.version == GHOST_VERSION => Some(run),
+            Ok(run) => {
+                warn!(
+                    "Ignoring ghost for level {}: version {} is not supported (expected {})",
+                    level_id, run.version, GHOST_VERSION
+                );
+                None
+            }
+            Err(err) => {
+                warn!("Failed to parse ghost for level {}: {}", level_id, err);
+                None
+            }
+        }
+    }
+
+    fn write(&self, level_id: &str) {
+        match toml::to_string(self) {
+            Ok(contents) => storage::write(&Self::storage_name(level_id), &contents),
+            Err(err) => error!("Failed to serialize ghost for level {}: {}", level_id, err),
+        }
+    }
+}
+
+/// [`Resource`] holding the run being recorded since Lyra last crossed a start marker.
+#[derive(Resource, Default)]
+pub struct GhostRecorder(Option<GhostRun>);
+
+/// [`Resource`] caching the best run of every level, by `LevelId`. [`None`] if the level has no
+/// saved ghost.
+#[derive(Resource, Default)]
+pub struct BestGhosts(HashMap<String, Option<GhostRun>>);
+
+impl BestGhosts {
+    fn get(&mut self, level_id: &str) -> Option<&GhostRun> {
+        self.0
+            .entry(level_id.to_string())
+            .or_insert_with(|| GhostRun::load(level_id))
+            .as_ref()
+    }
+}
+
+/// [`Resource`] holding the assets of the [`Ghost`], created once so that respawning it on every
+/// [`LevelStarted`] doesn't add new ones.
+#[derive(Resource)]
+pub struct GhostAssets {
+    sheet: Handle<Image>,
+    layout: Handle<TextureAtlasLayout>,
+    /// Image the ghost's camera renders to, and the projection of that camera.
+    target: Handle<Image>,
+    projection: Projection,
+}
+
+impl FromWorld for GhostAssets {
+    fn from_world(world: &mut World) -> Self {
+        let sheet = world.resource::<AssetServer>().load("lyra_sheet.png");
+        let layout = TextureAtlasLayout::from_grid(
+            UVec2::new(15, 20),
+            ANIMATION_FRAMES as u32,
+            1,
+            None,
+            None,
+        );
+        let layout = world
+            .resource_mut::<Assets<TextureAtlasLayout>>()
+            .add(layout);
+        let (target, projection) = build_render_target(36, 36);
+        let target = world.resource_mut::<Assets<Image>>().add(target);
+
+        Self {
+            sheet,
+            layout,
+            target,
+            projection,
+        }
+    }
+}
+
+/// [`Component`] on the translucent Lyra replaying a [`GhostRun`].
+#[derive(Component)]
+pub struct Ghost {
+    run: GhostRun,
+    frame: usize,
+}
+
+/// [`Component`] on a beam shot by the [`Ghost`], drawn with gizmos until its timer runs out.
+#[derive(Component)]
+pub struct GhostBeamLine {
+    color: LightColor,
+    start: Vec2,
+    end: Vec2,
+    timer: Timer,
+}
+
+pub fn start_ghost_recording(event: On<LevelStarted>, mut recorder: ResMut<GhostRecorder>) {
+    recorder.0 = Some(GhostRun {
+        version: GHOST_VERSION,
+        level_iid: event.level_iid.get().clone(),
+        time_ms: 0,
+        frames: Vec::new(),
+        beams: Vec::new(),
+    });
+}
+
+pub fn discard_ghost_recording(_: On<ResetLevels>, mut recorder: ResMut<GhostRecorder>) {
+    recorder.0 = None;
+}
+
+pub fn record_ghost_frame(
+    mut recorder: ResMut<GhostRecorder>,
+    lyra: Single<(&Position, &PlayerAnimationType, &Sprite), With<Lyra>>,
+    q_new_beams: Query<&LightBeamSource, (Added<LightBeamSource>, With<PlayerBeam>)>,
+) {
+    let Some(run) = recorder.0.as_mut() else {
+        return;
+    };
+    let (position, animation, sprite) = lyra.into_inner();
+    run.frames.push(GhostFrame {
+        position: position.0,
+        animation: *animation,
+        sprite_index: sprite.texture_atlas.as_ref().map_or(0, |atlas| atlas.index),
+        flip_x: sprite.flip_x,
+    });
+    for source in q_new_beams.iter() {
+        run.beams.push(GhostBeam {
+            frame: run.frames.len() - 1,
+            color: source.color,
+            start: source.start_pos,
+            dir: source.start_dir.as_vec2(),
+        });
+    }
+}
+
+/// Saves the recorded run if it beat the best one of the level.
+pub fn save_best_ghost(
+    event: On<LevelCompleted>,
+    mut recorder: ResMut<GhostRecorder>,
+    mut best_ghosts: ResMut<BestGhosts>,
+    ldtk_level_param: LdtkLevelParam,
+) {
+    if recorder
+        .0
+        .as_ref()
+        .is_none_or(|run| &run.level_iid != event.level_iid.get())
+    {
+        return;
+    }
+    let mut run = recorder.0.take().unwrap();
+    let Some(level) = ldtk_level_param.level_by_iid(&event.level_iid) else {
+        return;
+    };
+    let level_id = level.raw().level_id().clone();
+
+    run.time_ms = event.time.as_millis() as u64;
+    if best_ghosts
+        .get(&level_id)
+        .is_some_and(|best| best.time_ms <= run.time_ms)
+    {
+        return;
+    }
+    info!("New best run for level {}, saving its ghost", level_id);
+    run.write(&level_id);
+    best_ghosts.0.insert(level_id, Some(run));
+}
+
+/// Restarts the ghost of the level whose start marker was just crossed.
+pub fn spawn_ghost(
+    event: On<LevelStarted>,
+    mut commands: Commands,
+    settings: Res<GhostSettings>,
+    mut best_ghosts: ResMut<BestGhosts>,
+    ldtk_level_param: LdtkLevelParam,
+    q_ghost: Query<Entity, With<Ghost>>,
+    ghost_assets: Res<GhostAssets>,
+) {
+    for ghost in q_ghost.iter() {
+        commands.entity(ghost).despawn();
+    }
+    if !settings.enabled {
+        return;
+    }
+    let Some(level) = ldtk_level_param.level_by_iid(&event.level_iid) else {
+        return;
+    };
+    let Some(run) = best_ghosts.get(level.raw().level_id()).cloned() else {
+        return;
+    };
+    let Some(first_frame) = run.frames.first() else {
+        return;
+    };
+
+    let ghost = commands
+        .spawn(Transform::from_translation(first_frame.position.extend(0.)))
+        .insert(Visibility::default())
+        .insert(Sprite {
+            image: ghost_assets.sheet.clone(),
+            texture_atlas: Some(TextureAtlas {
+                layout: ghost_assets.layout.clone(),
+                index: first_frame.sprite_index,
+            }),
+            flip_x: first_frame.flip_x,
+            ..default()
+        })
+        .insert(GHOST_LAYER)
+        .insert(Ghost { run, frame: 0 })
+        .id();
+
+    // NOTE: rendered through its own camera like Lyra, so that it stays pixel perfect and can be
+    // made translucent as a whole
+    commands
+        .spawn(Camera2d)
+        .insert(Camera {
+            order: 0,
+            target: RenderTarget::Image(ghost_assets.target.clone().into()),
+            clear_color: ClearColorConfig::Custom(Color::NONE),
+            ..default()
+        })
+        .insert(Hdr)
+        .insert(Tonemapping::TonyMcMapface)
+        .insert(ghost_assets.projection.clone())
+        .insert(Transform::from_xyz(-0.1, -0.1, 0.0))
+        .insert(GHOST_LAYER)
+        .insert(ChildOf(ghost))
+        .with_child((
+            Sprite {
+                image: ghost_assets.target.clone(),
+                color: Color::WHITE.with_alpha(0.4),
+                ..default()
+            },
+            HIGHRES_LAYER,
+            Transform::from_xyz(0., 0., 4.),
+        ));
+}
+
+pub fn play_ghost(
+    mut commands: Commands,
+    mut q_ghost: Query<(Entity, &mut Ghost, &mut Transform, &mut Sprite)>,
+    spatial_query: SpatialQuery,
+) {
+    for (entity, mut ghost, mut transform, mut sprite) in q_ghost.iter_mut() {
+        let Some(frame) = ghost.run.frames.get(ghost.frame) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        transform.translation = frame.position.extend(transform.translation.z);
+        sprite.flip_x = frame.flip_x;
+        if let Some(atlas) = sprite.texture_atlas.as_mut() {
+            atlas.index = frame.sprite_index;
+        }
+
+        for beam in ghost
+            .run
+            .beams
+            .iter()
+            .filter(|beam| beam.frame == ghost.frame)
+        {
+            let Ok(dir) = Dir2::new(beam.dir) else {
+                continue;
+            };
+            let length = spatial_query
+                .cast_ray(
+                    beam.start,
+                    dir,
+                    GHOST_BEAM_MAX_LENGTH,
+                    true,
+                    &SpatialQueryFilter::from_mask(Layers::Terrain),
+                )
+                .map_or(GHOST_BEAM_MAX_LENGTH, |hit| hit.distance);
+            commands.spawn(GhostBeamLine {
+                color: beam.color,
+                start: beam.start,
+                end: beam.start + dir * length,
+                timer: Timer::from_seconds(GHOST_BEAM_DURATION, TimerMode::Once),
+            });
+        }
+        ghost.frame += 1;
+    }
+}
+
+pub fn draw_ghost_beams(
+    mut commands: Commands,
+    mut gizmos: Gizmos,
+    time: Res<Time>,
+    mut q_beams: Query<(Entity, &mut GhostBeamLine)>,
+) {
+    for (entity, mut beam) in q_beams.iter_mut() {
+        beam.timer.tick(time.delta());
+        if beam.timer.is_finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let alpha = 0.4 * beam.timer.fraction_remaining();
+        gizmos.line_2d(
+            beam.start,
+            beam.end,
+            beam.color.light_beam_color().with_alpha(alpha),
+        );
+    }
+}
+
+pub fn despawn_ghost(
+    mut commands: Commands,
+    q_ghost: Query<Entity, Or<(With<Ghost>, With<GhostBeamLine>)>>,
+) {
+    for entity in q_ghost.iter() {
+        commands.entity(entity).despawn();
+    }
+}
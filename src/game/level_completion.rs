@@ -1,8 +1,14 @@
+use std::time::Duration;
+
 use avian2d::prelude::*;
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
 
-use crate::{game::Layers, ldtk::LdtkLevelParam, ui::level_select::LevelProgress};
+use crate::{
+    game::Layers,
+    ldtk::LdtkLevelParam,
+    ui::{level_select::LevelProgress, speedrun::SpeedrunTimer},
+};
 
 pub struct LevelCompletionPlugin;
 
@@ -10,7 +16,7 @@ impl Plugin for LevelCompletionPlugin {
     fn build(&self, app: &mut App) {
         app.register_ldtk_entity::<CompletionMarkerBundle>("StartMarker");
         app.register_ldtk_entity::<CompletionMarkerBundle>("EndMarker");
-        app.insert_resource(InProgressLevel(LevelIid::default()));
+        app.init_resource::<InProgressLevel>();
     }
 }
 
@@ -28,8 +34,28 @@ pub struct CompletionMarkerBundle {
     collision_groups: CollisionLayers,
 }
 
-#[derive(Resource)]
-pub struct InProgressLevel(LevelIid);
+/// The level whose [`CompletionMarkerType::StartMarker`] was crossed last, and the
+/// [`SpeedrunTimer`] time at which it happened.
+#[derive(Resource, Default)]
+pub struct InProgressLevel {
+    level_iid: LevelIid,
+    started: Duration,
+}
+
+/// [`Event`] triggered when Lyra crosses the start marker of a level.
+#[derive(Event, Debug)]
+pub struct LevelStarted {
+    pub level_iid: LevelIid,
+}
+
+/// [`Event`] triggered when Lyra reaches the end of a level she started properly. Triggered after
+/// [`LevelProgress`] has been updated.
+#[derive(Event, Debug)]
+pub struct LevelCompleted {
+    pub level_iid: LevelIid,
+    /// Time spent between the start and end markers of the level.
+    pub time: Duration,
+}
 
 impl LdtkEntity for CompletionMarkerBundle {
     fn bundle_entity(
@@ -63,17 +89,23 @@ pub fn handle_start_end_markers(
     ldtk_level_param: LdtkLevelParam,
     mut res_in_progress_level: ResMut<InProgressLevel>,
     mut res_levels: ResMut<LevelProgress>,
+    speedrun_timer: Res<SpeedrunTimer>,
+    mut commands: Commands,
 ) {
     let Ok(marker_type) = q_completion_markers.get(event.collider2) else {
         return;
     };
     match marker_type {
         CompletionMarkerType::StartMarker => {
-            res_in_progress_level.0 = ldtk_level_param.cur_iid().expect("cur level exist");
+            res_in_progress_level.level_iid = ldtk_level_param.cur_iid().expect("cur level exist");
+            res_in_progress_level.started = speedrun_timer.elapsed();
+            commands.trigger(LevelStarted {
+                level_iid: res_in_progress_level.level_iid.clone(),
+            });
         }
         CompletionMarkerType::EndMarker => {
             let current = ldtk_level_param.cur_iid().expect("cur level exist");
-            if res_in_progress_level.0 != current {
+            if res_in_progress_level.level_iid != current {
                 return;
             }
             let mut unlock_next = false;
@@ -87,6 +119,12 @@ pub fn handle_start_end_markers(
                     unlock_next = true;
                 }
             }
+            commands.trigger(LevelCompleted {
+                level_iid: current,
+                time: speedrun_timer
+                    .elapsed()
+                    .saturating_sub(res_in_progress_level.started),
+            });
         }
     }
 }
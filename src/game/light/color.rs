@@ -0,0 +1,114 @@
+// NOTE: only depends on external crates, so that `lightborne-lint` can include this file too.
+
+use bevy::{
+    color::{Alpha, Color},
+    math::Vec3,
+};
+use enum_map::Enum;
+use serde::{Deserialize, Serialize};
+
+/// [`Enum`] for each of the light colors.
+#[derive(Enum, Clone, Copy, Default, PartialEq, Debug, Eq, Hash, Serialize, Deserialize)]
+pub enum LightColor {
+    #[default]
+    Green,
+    Purple,
+    White,
+    Blue,
+    Black,
+}
+
+impl From<&String> for LightColor {
+    fn from(value: &String) -> Self {
+        LightColor::from_name(value)
+            .unwrap_or_else(|| panic!("String {} does not represent Light Color", value))
+    }
+}
+
+impl LightColor {
+    /// The color with the name used by the `LightColor` enum of the LDtk project.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Purple" => Some(LightColor::Purple),
+            "Green" => Some(LightColor::Green),
+            "White" => Some(LightColor::White),
+            "Blue" => Some(LightColor::Blue),
+            "Black" => Some(LightColor::Black),
+            _ => None,
+        }
+    }
+
+    /// Whether beams of this color are stopped by the segments of beams of the `other` color.
+    /// White segments reflect other beams, and black segments absorb them.
+    pub fn hits_beam(&self, other: LightColor) -> bool {
+        match (self, other) {
+            (LightColor::Black, _) => false,
+            (_, LightColor::Black) => true,
+            (LightColor::White, _) => false,
+            (_, LightColor::White) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether beams of this color are stopped by blue crystals, which only blue beams go through.
+    pub fn hits_blue_crystals(&self) -> bool {
+        *self != LightColor::Blue
+    }
+
+    /// The number of bounces off of terrain each [`LightColor`] can make.
+    pub fn num_bounces(&self) -> usize {
+        match self {
+            LightColor::Purple => 2,
+            LightColor::Black => 0,
+            _ => 1,
+        }
+    }
+
+    /// The refraction index of glass for each [`LightColor`]. Purple bends the most and black the
+    /// least, so a glass block splits beams of different colors apart.
+    pub fn refraction_index(&self) -> f32 {
+        match self {
+            LightColor::Purple => 1.65,
+            LightColor::Blue => 1.6,
+            LightColor::Green => 1.5,
+            LightColor::White => 1.45,
+            LightColor::Black => 1.4,
+        }
+    }
+
+    /// The color of the light each [`LightColor`] casts on its surroundings. Lights are blended
+    /// additively, so the negative color of black beams darkens the area around them instead.
+    pub fn lighting_color(&self) -> Vec3 {
+        match self {
+            LightColor::Purple => Vec3::new(0.7, 0.2, 0.8),
+            LightColor::Green => Vec3::new(0.0, 0.9, 0.5),
+            LightColor::White => Vec3::new(0.8, 0.8, 0.5),
+            LightColor::Blue => Vec3::new(0.1, 0.2, 0.8),
+            LightColor::Black => Vec3::new(-0.6, -0.6, -0.6),
+        }
+    }
+
+    pub fn light_beam_color(&self) -> Color {
+        match self {
+            LightColor::Purple => Color::srgb(1.5, 0.5, 3.0),
+            LightColor::Green => Color::srgb(1.0, 4.0, 3.0),
+            LightColor::White => Color::srgb(2.0, 2.0, 2.0),
+            LightColor::Blue => Color::srgb(1.0, 2.0, 4.0),
+            LightColor::Black => Color::srgb(0.2, 0.2, 0.2),
+        }
+    }
+
+    pub fn indicator_color(&self) -> Color {
+        match self {
+            LightColor::Purple => Color::srgb(0.7, 0.3, 1.0),
+            LightColor::Green => Color::srgb(0.25, 0.9, 0.75),
+            LightColor::White => Color::srgb(1.0, 1.0, 1.0),
+            LightColor::Blue => Color::srgb(0.25, 0.5, 1.0),
+            LightColor::Black => Color::srgb(0.2, 0.2, 0.2),
+        }
+    }
+
+    pub fn indicator_dimmed_color(&self) -> Color {
+        self.indicator_color().with_alpha(0.15)
+    }
+}
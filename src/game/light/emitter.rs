@@ -0,0 +1,204 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::{
+    game::{
+        light::{
+            segments::{unhit_beam, PrevLightBeamPlayback},
+            LightBeamSource, LightColor,
+        },
+        lighting::LineLight2d,
+        signal::{is_signal_id, SignalListener, SignalReceived},
+    },
+    shared::{GameState, ResetLevels},
+};
+
+pub struct LightEmitterPlugin;
+
+impl Plugin for LightEmitterPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<LightEmitterBundle>("LightSource");
+        app.add_observer(hydrate_light_emitter);
+        app.add_observer(reset_light_emitters);
+        app.add_systems(
+            PostUpdate,
+            spawn_emitter_beams
+                .after(TransformSystems::Propagate)
+                .run_if(in_state(GameState::InGame)),
+        );
+    }
+}
+
+/// [`Component`] for an always-on beam emitter placed in LDtk. The [`LightBeamSource`]s it owns
+/// are regular light sources, so they get despawned by
+/// [`cleanup_light_sources`](super::segments::cleanup_light_sources) like any other; the emitter
+/// simply spawns them again on the next frame.
+#[derive(Component, Debug)]
+pub struct LightEmitter {
+    /// Offset of the start of the beams from the center of the emitter.
+    offset: Vec2,
+    /// Directions of the beams this emitter shoots.
+    dirs: Vec<Dir2>,
+    color: LightColor,
+    /// Whether the emitter currently shoots. Emitters with a signal id only shoot while their
+    /// [`LevelSignal`](crate::game::signal::LevelSignal) is active.
+    pub active: bool,
+    signal_id: Option<i32>,
+    /// The [`LightBeamSource`] entities currently owned by this emitter.
+    beams: Vec<Entity>,
+}
+
+/// [`Bundle`] registered with [`LdtkEntityAppExt::register_ldtk_entity`](LdtkEntityAppExt) to
+/// spawn light emitters directly from Ldtk.
+#[derive(Bundle)]
+pub struct LightEmitterBundle {
+    emitter: LightEmitter,
+}
+
+impl LdtkEntity for LightEmitterBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        layer_instance: &LayerInstance,
+        _: Option<&Handle<Image>>,
+        _: Option<&TilesetDefinition>,
+        _: &AssetServer,
+        _: &mut Assets<TextureAtlasLayout>,
+    ) -> Self {
+        let grid = layer_instance.grid_size as f32;
+
+        let x_offset = *entity_instance
+            .get_float_field("XOffset")
+            .expect("XOffset needs to be a float field on all light sources");
+        let y_offset = *entity_instance
+            .get_float_field("YOffset")
+            .expect("YOffset needs to be a float field on all light sources");
+        let direction = *entity_instance
+            .get_point_field("Direction")
+            .expect("Direction needs to be a point field on all light sources");
+        let both_directions = *entity_instance
+            .get_bool_field("BothDirections")
+            .expect("BothDirections needs to be a bool field on all light sources");
+
+        // NOTE: flip y values because ldtk shenanigans
+        let offset = Vec2::new(x_offset, -y_offset);
+        let target = Vec2::new(
+            (direction.x - entity_instance.grid.x) as f32 * grid,
+            -(direction.y - entity_instance.grid.y) as f32 * grid,
+        );
+        // validation reports these, but the level should still load without the broken emitter
+        let dirs = match Dir2::new(target - offset) {
+            Ok(dir) if both_directions => vec![dir, -dir],
+            Ok(dir) => vec![dir],
+            Err(_) => {
+                warn!(
+                    "Light source {} points at itself, so it won't shoot any beams",
+                    entity_instance.iid
+                );
+                Vec::new()
+            }
+        };
+
+        // optional fields, so that older ldtk projects without them still load
+        let color = entity_instance
+            .get_enum_field("light_color")
+            .map(LightColor::from)
+            .unwrap_or(LightColor::White);
+        let signal_id = entity_instance
+            .get_int_field("signal_id")
+            .ok()
+            .copied()
+            .filter(|id| is_signal_id(*id));
+
+        Self {
+            emitter: LightEmitter {
+                offset,
+                dirs,
+                color,
+                active: signal_id.is_none(),
+                signal_id,
+                beams: Vec::new(),
+            },
+        }
+    }
+}
+
+pub fn hydrate_light_emitter(
+    event: On<Add, LightEmitter>,
+    mut commands: Commands,
+    q_emitters: Query<&LightEmitter>,
+) {
+    let Ok(emitter) = q_emitters.get(event.entity) else {
+        return;
+    };
+    let Some(signal_id) = emitter.signal_id else {
+        return;
+    };
+
+    commands
+        .entity(event.entity)
+        .insert(SignalListener(signal_id))
+        .observe(
+            |event: On<SignalReceived>,
+             mut commands: Commands,
+             mut q_emitters: Query<&mut LightEmitter>,
+             q_playback: Query<&PrevLightBeamPlayback>| {
+                let Ok(mut emitter) = q_emitters.get_mut(event.entity) else {
+                    return;
+                };
+                emitter.active = event.active;
+                if emitter.active {
+                    return;
+                }
+                for beam in emitter.beams.drain(..) {
+                    if let Ok(playback) = q_playback.get(beam) {
+                        unhit_beam(&mut commands, beam, playback);
+                    }
+                    commands.entity(beam).despawn();
+                }
+            },
+        );
+}
+
+/// Forgets the beams of all emitters, since
+/// [`cleanup_light_sources`](super::segments::cleanup_light_sources) has despawned them.
+pub fn reset_light_emitters(_: On<ResetLevels>, mut q_emitters: Query<&mut LightEmitter>) {
+    for mut emitter in q_emitters.iter_mut() {
+        emitter.active = emitter.signal_id.is_none();
+        emitter.beams.clear();
+    }
+}
+
+/// [`System`] that spawns the [`LightBeamSource`]s of active emitters that don't have any. Runs
+/// after transform propagation so that newly spawned emitters already know where they are.
+pub fn spawn_emitter_beams(
+    mut commands: Commands,
+    mut q_emitters: Query<(&mut LightEmitter, &GlobalTransform)>,
+) {
+    for (mut emitter, transform) in q_emitters.iter_mut() {
+        if !emitter.active || !emitter.beams.is_empty() {
+            continue;
+        }
+        let emitter = &mut *emitter;
+        let start_pos = transform.translation().truncate() + emitter.offset;
+        let color = emitter.color;
+
+        for dir in emitter.dirs.iter() {
+            let beam = commands
+                .spawn(LightBeamSource {
+                    start_pos,
+                    start_dir: *dir,
+                    time_traveled: 0.0,
+                    color,
+                })
+                .insert(PrevLightBeamPlayback::default())
+                .insert(Transform::from_translation(start_pos.extend(3.)))
+                .with_child(LineLight2d::point(
+                    color.lighting_color().extend(1.0),
+                    30.0,
+                    0.02,
+                ))
+                .id();
+            emitter.beams.push(beam);
+        }
+    }
+}
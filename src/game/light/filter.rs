@@ -0,0 +1,88 @@
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::game::{light::LightColor, Layers};
+
+pub struct ColorFilterPlugin;
+
+impl Plugin for ColorFilterPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<ColorFilterBundle>("ColorFilter");
+        app.add_observer(hydrate_color_filter);
+    }
+}
+
+/// [`Component`] for an area of colored glass placed in LDtk. Recoloring filters turn every beam
+/// going through them into beams of their color, other filters only let beams of their color
+/// through.
+#[derive(Component, Debug)]
+pub struct ColorFilter {
+    size: Vec2,
+    pub color: LightColor,
+    pub recolor: bool,
+}
+
+impl ColorFilter {
+    /// The color of a beam of `color` after going through this filter, or [`None`] if the filter
+    /// blocks it.
+    pub fn filter(&self, color: LightColor) -> Option<LightColor> {
+        if self.recolor || color == self.color {
+            Some(self.color)
+        } else {
+            None
+        }
+    }
+}
+
+impl From<&EntityInstance> for ColorFilter {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let color = entity_instance
+            .get_enum_field("light_color")
+            .map(LightColor::from)
+            .expect("light_color needs to be an enum field on all color filters");
+        let recolor = *entity_instance
+            .get_bool_field("recolor")
+            .expect("recolor needs to be a bool field on all color filters");
+
+        ColorFilter {
+            size: Vec2::new(entity_instance.width as f32, entity_instance.height as f32),
+            color,
+            recolor,
+        }
+    }
+}
+
+#[derive(Bundle, LdtkEntity)]
+pub struct ColorFilterBundle {
+    #[from_entity_instance]
+    filter: ColorFilter,
+}
+
+pub fn hydrate_color_filter(
+    event: On<Add, ColorFilter>,
+    mut commands: Commands,
+    q_filters: Query<&ColorFilter>,
+) {
+    let Ok(filter) = q_filters.get(event.entity) else {
+        return;
+    };
+    // blocking filters are drawn more opaque than recoloring ones, so the two can be told apart
+    let alpha = if filter.recolor { 0.3 } else { 0.6 };
+    commands
+        .entity(event.entity)
+        .insert(Collider::rectangle(filter.size.x, filter.size.y))
+        .insert(CollisionLayers::new(
+            Layers::LightSensor,
+            [
+                Layers::LightRay,
+                Layers::WhiteRay,
+                Layers::BlueRay,
+                Layers::BlackRay,
+            ],
+        ))
+        .insert(Sprite::from_color(
+            filter.color.indicator_color().with_alpha(alpha),
+            filter.size,
+        ));
+}
@@ -3,12 +3,13 @@ use bevy::{
     sprite_render::{AlphaMode2d, Material2dPlugin},
 };
 
-use enum_map::Enum;
-
 use crate::{
     asset::LoadResource,
     game::{
         light::{
+            emitter::LightEmitterPlugin,
+            filter::ColorFilterPlugin,
+            prism::PrismPlugin,
             render::{LightMaterial, LightRenderData},
             segments::{
                 cleanup_light_sources, simulate_light_sources, tick_light_sources, LightBounceSfx,
@@ -19,9 +20,15 @@ use crate::{
     },
 };
 
+mod color;
+pub mod emitter;
+pub mod filter;
+pub mod prism;
 mod render;
 pub mod segments;
 
+pub use color::LightColor;
+
 /// The speed of the light beam in units per [`FixedUpdate`].
 const LIGHT_SPEED: f32 = 8.0;
 
@@ -38,157 +45,29 @@ impl Plugin for LightBeamPlugin {
         app.load_resource::<LightBounceSfx>();
         app.init_resource::<LightRenderData>();
         app.init_resource::<LightSegmentCache>();
-        // .register_ldtk_entity::<LightSourceBundle>("LightSource")
+        // NOTE: on FixedUpdate, so that beams travel the same distance each tick at any frame rate
         app.add_systems(
-            Update,
+            FixedUpdate,
             (tick_light_sources, simulate_light_sources)
                 .chain()
                 .in_set(LevelSystems::Simulation),
         );
         app.add_observer(cleanup_light_sources);
-        // .add_systems(
-        //     PostUpdate,
-        //     spawn_level_light_beams.in_set(LevelSystems::Simulation),
-        // )
-        // .add_systems(
-        //     PostUpdate,
-        //     add_light_beam_added.in_set(LevelSystems::Processing),
-        // );
+        app.add_plugins(LightEmitterPlugin);
+        app.add_plugins(PrismPlugin);
+        app.add_plugins(ColorFilterPlugin);
     }
 }
 
-// fn add_light_beam_added(
-//     ldtk_sources: Query<
-//         (Entity, &LightBeamLDTKSource, &GlobalTransform),
-//         Added<LightBeamLDTKSource>,
-//     >,
-//     mut commands: Commands,
-// ) {
-//     for source in ldtk_sources.iter() {
-//         commands.entity(source.0).insert(LightBeamSourceAdded);
-//     }
-// }
-//
-// fn spawn_level_light_beams(
-//     mut commands: Commands,
-//     ldtk_sources: Query<
-//         (Entity, &LightBeamLDTKSource, &GlobalTransform),
-//         Added<LightBeamSourceAdded>,
-//     >,
-// ) {
-//     for (_, source, transform) in ldtk_sources.iter() {
-//         let ray_dir_int = source.direction - source.position;
-//         let ray_dir = Vec2::new(
-//             ray_dir_int.x as f32 + (source.x_offset / 8.0),
-//             -(ray_dir_int.y as f32 + (source.y_offset / 8.0)),
-//         )
-//         .normalize();
-//         let ray_pos = Vec2::new(
-//             transform.translation().truncate().x + source.x_offset,
-//             transform.translation().truncate().y + source.y_offset,
-//         );
-//         let shoot_color = LightColor::Black;
-//
-//         /* Used for the source image; currently not used
-//         let mut source_transform = Transform::from_translation(ray_pos.extend(light_source_z.translation.z));
-//         source_transform.rotate_z(ray_dir.to_angle());
-//         let mut source_sprite = Sprite::from_image(asset_server.load("light/compass.png"));
-//         source_sprite.color = Color::srgb(2.0, 2.0, 2.0);
-//         let mut outer_source_sprite = Sprite::from_image(asset_server.load("light/compass-gold.png"));
-//         outer_source_sprite.color = shoot_color.light_beam_color().mix(&Color::BLACK, 0.4);
-//         */
-//
-//         let light_beam_source = LightBeamSource {
-//             start_pos: ray_pos,
-//             start_dir: ray_dir,
-//             time_traveled: 1000.0,
-//             color: shoot_color,
-//         };
-//         commands
-//             .spawn(light_beam_source)
-//             .insert(PrevLightBeamPlayback::default());
-//         // .insert(LineLight2d::point(
-//         //     shoot_color.lighting_color().extend(1.0),
-//         //     30.0,
-//         //     0.0,
-//         // ));
-//         if source.both_directions {
-//             let light_beam_source = LightBeamSource {
-//                 start_pos: ray_pos,
-//                 start_dir: -ray_dir,
-//                 time_traveled: 1000.0,
-//                 color: shoot_color,
-//             };
-//             commands
-//                 .spawn(light_beam_source)
-//                 .insert(PrevLightBeamPlayback::default());
-//             //         .insert(LineLight2d::point(
-//             //             shoot_color.lighting_color().extend(1.0),
-//             //             30.0,
-//             //             0.0,
-//             //         ));
-//         }
-//     }
-// }
-//
-// #[derive(Default, Component)]
-// pub struct BlackRayComponent;
-
-// #[derive(Default, Component)]
-// pub struct LightBeamSourceAdded;
-
-// // Bundle for LDTK Light Source
-// #[derive(Bundle, LdtkEntity)]
-// pub struct LightSourceBundle {
-//     #[from_entity_instance]
-//     pub beam_source: LightBeamLDTKSource,
-// }
-
-// // Component for LDTK Light Source
-// #[derive(Default, Component)]
-// pub struct LightBeamLDTKSource {
-//     pub direction: IVec2,
-//     pub position: IVec2,
-//     pub x_offset: f32,
-//     pub y_offset: f32,
-//     pub both_directions: bool,
-// }
-
-// impl From<&bevy_ecs_ldtk::EntityInstance> for LightBeamLDTKSource {
-//     fn from(entity_instance: &bevy_ecs_ldtk::EntityInstance) -> Self {
-//         let height = entity_instance.height;
-//         let position = IVec2::new(
-//             entity_instance.grid.x,
-//             entity_instance.grid.y + (height / (BLOCK_WIDTH as i32)) - 1,
-//         );
-//         let x_offset = *entity_instance.get_float_field("XOffset").unwrap();
-//         let y_offset = *entity_instance.get_float_field("YOffset").unwrap();
-//         let direction = *entity_instance.get_point_field("Direction").unwrap();
-//         let both_directions = *entity_instance.get_bool_field("BothDirections").unwrap();
-//         LightBeamLDTKSource {
-//             direction,
-//             position,
-//             x_offset,
-//             y_offset,
-//             both_directions,
-//         }
-//     }
-// }
-
-/// [`Enum`] for each of the light colors.
-#[derive(Enum, Clone, Copy, Default, PartialEq, Debug, Eq, Hash)]
-pub enum LightColor {
-    #[default]
-    Green,
-    Purple,
-    White,
-    Blue,
-    // Black,
-}
+/// Marker [`Component`] for the segments of black beams. Other beams stop when they hit one.
+#[derive(Default, Component)]
+pub struct BlackRayComponent;
 
 #[derive(EntityEvent)]
 pub struct HitByLight {
     pub entity: Entity,
+    /// The [`LightBeamSource`] of the beam.
+    pub source: Entity,
     pub color: LightColor,
     pub hit: bool,
 }
@@ -205,68 +84,10 @@ impl From<LightColor> for LightMaterial {
     }
 }
 
-impl From<&String> for LightColor {
-    fn from(value: &String) -> Self {
-        match value.as_str() {
-            "Purple" => LightColor::Purple,
-            "Green" => LightColor::Green,
-            "White" => LightColor::White,
-            "Blue" => LightColor::Blue,
-            // "Black" => LightColor::Black,
-            _ => panic!("String {} does not represent Light Color", value),
-        }
-    }
-}
-
-impl LightColor {
-    /// The number of bounces off of terrain each [`LightColor`] can make.
-    pub fn num_bounces(&self) -> usize {
-        match self {
-            LightColor::Purple => 2,
-            // LightColor::Black => 0,
-            _ => 1,
-        }
-    }
-
-    pub fn lighting_color(&self) -> Vec3 {
-        match self {
-            LightColor::Purple => Vec3::new(0.7, 0.2, 0.8),
-            LightColor::Green => Vec3::new(0.0, 0.9, 0.5),
-            LightColor::White => Vec3::new(0.8, 0.8, 0.5),
-            LightColor::Blue => Vec3::new(0.1, 0.2, 0.8),
-            // LightColor::Black => Vec3::new(0.2, 0.2, 0.2),
-        }
-    }
-
-    pub fn light_beam_color(&self) -> Color {
-        match self {
-            LightColor::Purple => Color::srgb(1.5, 0.5, 3.0),
-            LightColor::Green => Color::srgb(1.0, 4.0, 3.0),
-            LightColor::White => Color::srgb(2.0, 2.0, 2.0),
-            LightColor::Blue => Color::srgb(1.0, 2.0, 4.0),
-            // LightColor::Black => Color::srgb(0.2, 0.2, 0.2),
-        }
-    }
-
-    pub fn indicator_color(&self) -> Color {
-        match self {
-            LightColor::Purple => Color::srgb(0.7, 0.3, 1.0),
-            LightColor::Green => Color::srgb(0.25, 0.9, 0.75),
-            LightColor::White => Color::srgb(1.0, 1.0, 1.0),
-            LightColor::Blue => Color::srgb(0.25, 0.5, 1.0),
-            // LightColor::Black => Color::srgb(0.2, 0.2, 0.2),
-        }
-    }
-
-    pub fn indicator_dimmed_color(&self) -> Color {
-        self.indicator_color().with_alpha(0.15)
-    }
-}
-
 /// A [`Component`] marking the start of a light ray. These are spawned in
 /// [`shoot_light`](crate::player::light::shoot_light), and simulated in
 /// [`simulate_light_sources`]
-#[derive(Component)]
+#[derive(Component, Clone)]
 #[require(Transform, Visibility, Sprite, PrevLightBeamPlayback)]
 pub struct LightBeamSource {
     pub start_pos: Vec2,
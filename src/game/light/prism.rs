@@ -0,0 +1,234 @@
+use avian2d::prelude::*;
+use bevy::{platform::collections::HashSet, prelude::*};
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::{
+    game::{
+        light::{
+            segments::{unhit_beam, PrevLightBeamPlayback},
+            HitByLight, LightBeamSource, LightColor,
+        },
+        lighting::LineLight2d,
+        Layers,
+    },
+    shared::{GameState, ResetLevels},
+};
+
+/// Width and height of the prism collider.
+const PRISM_SIZE: f32 = 8.0;
+
+/// Distance from the center of the prism to the start of its beams, so that they start outside of
+/// the prism collider in every direction.
+const PRISM_BEAM_OFFSET: f32 = PRISM_SIZE * 0.75;
+
+pub struct PrismPlugin;
+
+impl Plugin for PrismPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<PrismBundle>("Prism");
+        app.add_observer(hydrate_prism);
+        app.add_observer(reset_prisms);
+        app.add_systems(
+            PostUpdate,
+            update_prism_beams
+                .after(TransformSystems::Propagate)
+                .run_if(in_state(GameState::InGame)),
+        );
+    }
+}
+
+/// [`Component`] that absorbs the beams that hit it and splits them into beams of the configured
+/// colors and directions for as long as it is lit. Its beams are regular [`LightBeamSource`]s, so
+/// they can light up sensors and other prisms, but not the prisms they come from.
+#[derive(Component, Debug)]
+pub struct Prism {
+    outputs: Vec<(LightColor, Dir2)>,
+    /// The [`LightBeamSource`]s of the beams currently hitting the prism, once per hit.
+    hits: Vec<Entity>,
+    /// The [`LightBeamSource`] entities currently owned by this prism.
+    beams: Vec<Entity>,
+}
+
+impl From<&EntityInstance> for Prism {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let colors = entity_instance
+            .iter_enums_field("colors")
+            .expect("colors needs to be an enum array field on all prisms")
+            .map(LightColor::from);
+        let angles = entity_instance
+            .iter_floats_field("angles")
+            .expect("angles needs to be a float array field on all prisms")
+            // NOTE: angles are in degrees, counterclockwise from the right
+            .map(|angle| {
+                Dir2::from_xy_unchecked(angle.to_radians().cos(), angle.to_radians().sin())
+            });
+
+        Prism {
+            outputs: colors.zip(angles).collect(),
+            hits: Vec::new(),
+            beams: Vec::new(),
+        }
+    }
+}
+
+#[derive(Bundle, LdtkEntity)]
+pub struct PrismBundle {
+    #[from_entity_instance]
+    prism: Prism,
+}
+
+pub fn hydrate_prism(event: On<Add, Prism>, mut commands: Commands) {
+    commands
+        .entity(event.entity)
+        .insert(Collider::rectangle(PRISM_SIZE, PRISM_SIZE))
+        .insert(CollisionLayers::new(
+            Layers::LightSensor,
+            [
+                Layers::LightRay,
+                Layers::WhiteRay,
+                Layers::BlueRay,
+                Layers::BlackRay,
+            ],
+        ))
+        .insert(Sprite::from_color(
+            Color::srgb(0.75, 0.8, 0.86),
+            Vec2::splat(PRISM_SIZE),
+        ))
+        .observe(|event: On<HitByLight>, mut q_prisms: Query<&mut Prism>| {
+            let Ok(mut prism) = q_prisms.get_mut(event.entity) else {
+                return;
+            };
+            if event.hit {
+                prism.hits.push(event.source);
+            } else if let Some(index) = prism.hits.iter().position(|hit| *hit == event.source) {
+                prism.hits.swap_remove(index);
+            }
+        });
+}
+
+/// Forgets the beams of all prisms, since
+/// [`cleanup_light_sources`](super::segments::cleanup_light_sources) has despawned them.
+pub fn reset_prisms(_: On<ResetLevels>, mut q_prisms: Query<&mut Prism>) {
+    for mut prism in q_prisms.iter_mut() {
+        prism.hits.clear();
+        prism.beams.clear();
+    }
+}
+
+/// The prisms lit by a beam that doesn't come from their own beams, directly or through other
+/// prisms. Prisms facing each other would otherwise keep each other lit forever.
+fn lit_prisms<'a>(prisms: impl IntoIterator<Item = (Entity, &'a Prism)>) -> HashSet<Entity> {
+    let prisms = prisms.into_iter().collect::<Vec<_>>();
+    let owner = |source: Entity| {
+        prisms
+            .iter()
+            .find(|(_, prism)| prism.beams.contains(&source))
+            .map(|(entity, _)| *entity)
+    };
+
+    // starting from the prisms hit by other sources, light the prisms hit by lit prisms
+    let mut lit = HashSet::new();
+    loop {
+        let newly_lit = prisms
+            .iter()
+            .filter(|(entity, prism)| {
+                !lit.contains(entity)
+                    && prism
+                        .hits
+                        .iter()
+                        .any(|source| owner(*source).is_none_or(|owner| lit.contains(&owner)))
+            })
+            .map(|(entity, _)| *entity)
+            .collect::<Vec<_>>();
+        if newly_lit.is_empty() {
+            return lit;
+        }
+        lit.extend(newly_lit);
+    }
+}
+
+/// [`System`] that spawns the [`LightBeamSource`]s of prisms that just got lit, and despawns those
+/// of prisms that aren't lit anymore.
+pub fn update_prism_beams(
+    mut commands: Commands,
+    mut q_prisms: Query<(Entity, &mut Prism, &GlobalTransform)>,
+    q_playback: Query<&PrevLightBeamPlayback>,
+) {
+    let lit = lit_prisms(q_prisms.iter().map(|(entity, prism, _)| (entity, prism)));
+    for (entity, mut prism, transform) in q_prisms.iter_mut() {
+        let prism = &mut *prism;
+        if !lit.contains(&entity) {
+            for beam in prism.beams.drain(..) {
+                if let Ok(playback) = q_playback.get(beam) {
+                    unhit_beam(&mut commands, beam, playback);
+                }
+                commands.entity(beam).despawn();
+            }
+            continue;
+        }
+        if !prism.beams.is_empty() {
+            continue;
+        }
+
+        let center = transform.translation().truncate();
+        for (color, dir) in prism.outputs.iter() {
+            let start_pos = center + *dir * PRISM_BEAM_OFFSET;
+            let beam = commands
+                .spawn(LightBeamSource {
+                    start_pos,
+                    start_dir: *dir,
+                    time_traveled: 0.0,
+                    color: *color,
+                })
+                .insert(PrevLightBeamPlayback::default())
+                .insert(Transform::from_translation(start_pos.extend(3.)))
+                .with_child(LineLight2d::point(
+                    color.lighting_color().extend(1.0),
+                    30.0,
+                    0.02,
+                ))
+                .id();
+            prism.beams.push(beam);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prism(hits: Vec<Entity>, beams: Vec<Entity>) -> Prism {
+        Prism {
+            outputs: Vec::new(),
+            hits,
+            beams,
+        }
+    }
+
+    #[test]
+    fn facing_prisms_dont_keep_each_other_lit() {
+        let mut world = World::new();
+        let [a, b, a_beam, b_beam, player_beam] = [(); 5].map(|_| world.spawn_empty().id());
+
+        // the player's beam lights a, whose beam lights b, whose beam lights a back
+        let lit_by_player = lit_prisms([
+            (a, &prism(vec![player_beam, b_beam], vec![a_beam])),
+            (b, &prism(vec![a_beam], vec![b_beam])),
+        ]);
+        assert_eq!(lit_by_player, HashSet::from_iter([a, b]));
+
+        // once the player's beam is gone, only their own beams are left
+        let unlit = lit_prisms([
+            (a, &prism(vec![b_beam], vec![a_beam])),
+            (b, &prism(vec![a_beam], vec![b_beam])),
+        ]);
+        assert!(unlit.is_empty());
+    }
+
+    #[test]
+    fn prisms_ignore_their_own_beams() {
+        let mut world = World::new();
+        let [a, a_beam] = [(); 2].map(|_| world.spawn_empty().id());
+        assert!(lit_prisms([(a, &prism(vec![a_beam], vec![a_beam]))]).is_empty());
+    }
+}
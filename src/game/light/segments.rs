@@ -7,10 +7,17 @@ use bevy::{
 use crate::{
     camera::HIGHRES_LAYER,
     game::{
-        defs::mirror::Mirror,
+        constants::LIGHT_MAX_SEGMENTS,
+        defs::{
+            glass::{focus, glass_exit, next_glass_block, refract, Glass},
+            mirror::Mirror,
+            portal::{pass_through, Portal},
+        },
         light::{
+            filter::ColorFilter,
+            prism::Prism,
             render::{LightMaterial, LightRenderData},
-            HitByLight, LightBeamSource, LightColor, LIGHT_SPEED,
+            BlackRayComponent, HitByLight, LightBeamSource, LightColor, LIGHT_SPEED,
         },
         lighting::LineLight2d,
         particle::spark::SparkExplosionEvent,
@@ -20,8 +27,10 @@ use crate::{
 };
 
 /// Marker [`Component`] used to query for light segments.
-#[derive(Default, Component, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Component, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct LightSegment {
+    /// The [`LightBeamSource`] entity this segment belongs to.
+    pub source: Entity,
     pub color: LightColor,
     pub index: usize,
 }
@@ -30,7 +39,7 @@ pub struct LightSegment {
 pub struct LightSegmentCache(HashMap<LightSegment, (Transform, Entity, Entity)>);
 
 /// [`Bundle`] used in the initialization of the [`LightSegmentCache`] to spawn segment entities.
-#[derive(Bundle, Debug, Clone, Default)]
+#[derive(Bundle, Debug, Clone)]
 pub struct LightSegmentBundle {
     pub segment: LightSegment,
     pub mesh: Mesh2d,
@@ -71,6 +80,12 @@ pub struct LightBeamIntersection {
     pub entity: Entity,
     pub point: Vec2,
     pub time: f32,
+    /// The color of the beam when it hit, which can differ from the color of its source after
+    /// going through a [`ColorFilter`].
+    pub color: LightColor,
+    /// Whether the beam continues from the next intersection without crossing the space in
+    /// between, after going through a [`Portal`].
+    pub jump: bool,
 }
 
 /// Stores information about the trajectory of a LightBeam
@@ -79,6 +94,8 @@ pub struct LightBeamPlayback {
     pub intersections: Vec<LightBeamIntersection>,
     pub end_point: Option<Vec2>,
     pub elapsed_time: f32,
+    /// The color of the beam after its last intersection.
+    pub color: LightColor,
 }
 
 impl LightBeamPlayback {
@@ -94,6 +111,22 @@ impl LightBeamPlayback {
             )
             .chain(self.end_point.iter().copied())
     }
+
+    /// Whether the segment ending at the `index`th point after the start of the beam is a jump
+    /// through a [`Portal`], which isn't drawn.
+    pub fn is_jump(&self, index: usize) -> bool {
+        index
+            .checked_sub(1)
+            .and_then(|index| self.intersections.get(index))
+            .is_some_and(|intersection| intersection.jump)
+    }
+
+    /// The color of the segment ending at the `index`th point after the start of the beam.
+    pub fn segment_color(&self, index: usize) -> LightColor {
+        self.intersections
+            .get(index)
+            .map_or(self.color, |intersection| intersection.color)
+    }
 }
 
 #[derive(Default, Debug, Component)]
@@ -101,104 +134,205 @@ pub struct PrevLightBeamPlayback {
     pub intersections: Vec<Option<LightBeamIntersection>>,
 }
 
-const LIGHT_MAX_SEGMENTS: usize = 15;
+/// Unhits everything a beam was touching, for beams that are despawned and won't be simulated
+/// again.
+pub fn unhit_beam(commands: &mut Commands, source: Entity, playback: &PrevLightBeamPlayback) {
+    for intersection in playback.intersections.iter().flatten() {
+        commands.trigger(HitByLight {
+            entity: intersection.entity,
+            source,
+            color: intersection.color,
+            hit: false,
+        });
+    }
+}
+
+/// The [`CollisionLayers`] of the rays cast for beams of each [`LightColor`].
+fn beam_collision_layers(color: LightColor) -> CollisionLayers {
+    let membership = match color {
+        LightColor::White => Layers::WhiteRay,
+        LightColor::Black => Layers::BlackRay,
+        LightColor::Blue => Layers::BlueRay,
+        _ => Layers::LightRay,
+    };
+    let mut filters = LayerMask::from([Layers::Terrain, Layers::LightSensor, Layers::Spike]);
+    if color.hits_blue_crystals() {
+        filters |= LayerMask::from(Layers::BlueCrystal);
+    }
+    if color.hits_beam(LightColor::White) {
+        filters |= LayerMask::from(Layers::WhiteRay);
+    }
+    if color.hits_beam(LightColor::Black) {
+        filters |= LayerMask::from(Layers::BlackRay);
+    }
+    CollisionLayers::new(membership, filters)
+}
 
+#[allow(clippy::too_many_arguments)]
 pub fn play_light_beam(
     spatial_query: &SpatialQuery,
     source: &LightBeamSource,
-    // black_ray_qry: &Query<(Entity, &BlackRayComponent)>,
+    q_black_rays: &Query<&BlackRayComponent>,
     q_mirrors: &Query<&Mirror>,
+    q_prisms: &Query<&Prism>,
+    q_glass: &Query<(&Glass, &GlobalTransform)>,
+    q_filters: &Query<&ColorFilter>,
+    q_portals: &Query<(&Portal, &GlobalTransform)>,
 ) -> LightBeamPlayback {
     let mut ray_pos = source.start_pos;
     let mut ray_dir = source.start_dir;
-    let collision_groups = match source.color {
-        LightColor::White => CollisionLayers::new(
-            Layers::WhiteRay,
-            [
-                Layers::Terrain,
-                Layers::LightSensor,
-                Layers::Spike,
-                Layers::BlueCrystal,
-            ],
-        ),
-        // LightColor::Black => {
-        //     CollisionLayers::new(Layers::BlackRay, Layers::Terrain, Layers::LightSensor)
-        // }
-        LightColor::Blue => CollisionLayers::new(
-            Layers::BlueRay,
-            [
-                Layers::Terrain,
-                Layers::LightSensor,
-                Layers::WhiteRay,
-                Layers::Spike,
-            ],
-        ),
-        _ => CollisionLayers::new(
-            Layers::LightRay,
-            [
-                Layers::Terrain,
-                Layers::LightSensor,
-                Layers::WhiteRay,
-                Layers::Spike,
-                Layers::BlueCrystal,
-            ],
-        ),
-    };
-
-    let mut ray_qry = SpatialQueryFilter::default().with_mask(collision_groups.filters);
+    let mut color = source.color;
+    let mut ray_qry = SpatialQueryFilter::default().with_mask(beam_collision_layers(color).filters);
     let mut remaining_time = source.time_traveled;
 
     let mut playback = LightBeamPlayback {
         intersections: vec![],
         end_point: None,
         elapsed_time: 0.0,
+        color,
     };
 
     // for _ in 0..source.color.num_bounces() + 1 {
-    let num_segments = source.color.num_bounces() + 1;
+    let mut num_segments = color.num_bounces() + 1;
 
     let mut i = 0;
     let mut extra_bounces_from_mirror = 0;
+    // the glass block the beam is currently going through, and its center
+    let mut inside_glass: Option<(Entity, Vec2)> = None;
     while i < num_segments + extra_bounces_from_mirror && i < LIGHT_MAX_SEGMENTS {
-        let Some(hit) = spatial_query.cast_ray(ray_pos, ray_dir, remaining_time, true, &ray_qry)
-        else {
+        let hit = match inside_glass {
+            Some((entity, center)) => {
+                let (distance, normal) = glass_exit(center, ray_pos, ray_dir);
+                (distance <= remaining_time).then_some((entity, distance, normal))
+            }
+            None => spatial_query
+                .cast_ray(ray_pos, ray_dir, remaining_time, true, &ray_qry)
+                .map(|hit| (hit.entity, hit.distance, hit.normal)),
+        };
+        let Some((hit_entity, hit_distance, hit_normal)) = hit else {
             let final_point = ray_pos + ray_dir * remaining_time;
             playback.elapsed_time += remaining_time;
             playback.end_point = Some(final_point);
             break;
         };
-        if q_mirrors.contains(hit.entity) {
+        // the side between two cells of glass isn't a surface, so the beam goes on unbent and
+        // without using up a segment
+        if inside_glass.is_some() {
+            let exit_point = ray_pos + *ray_dir * hit_distance;
+            if let Some(next) = next_glass_block(spatial_query, q_glass, exit_point, hit_normal) {
+                playback.elapsed_time += hit_distance;
+                remaining_time -= hit_distance;
+                ray_pos = exit_point;
+                inside_glass = Some(next);
+                continue;
+            }
+        }
+        if q_mirrors.contains(hit_entity) {
             extra_bounces_from_mirror += 1;
         }
 
         // if inside something???
         let mut ignore_entity = true;
-        if hit.distance < 0.01 {
+        if hit_distance < 0.01 {
             ignore_entity = false;
         }
 
-        playback.elapsed_time += hit.distance;
-        remaining_time -= hit.distance;
-        let hit_point = ray_pos + *ray_dir * hit.distance;
+        playback.elapsed_time += hit_distance;
+        remaining_time -= hit_distance;
+        let hit_point = ray_pos + *ray_dir * hit_distance;
 
         playback.intersections.push(LightBeamIntersection {
-            entity: hit.entity,
+            entity: hit_entity,
             point: hit_point,
             time: playback.elapsed_time,
+            color,
+            jump: false,
         });
 
+        let incoming_dir = ray_dir;
+        let reflected =
+            Dir2::new((Vec2::from(ray_dir)).reflect(hit_normal)).expect("cast dir cannot be 0");
+        ray_dir = match q_glass.get(hit_entity) {
+            // going through glass doesn't use up any of the bounces of the beam
+            Ok((glass, transform)) => {
+                extra_bounces_from_mirror += 1;
+                let center = transform.translation().truncate();
+                let index = color.refraction_index();
+                match (glass, inside_glass) {
+                    (Glass::Lens, _) => focus(ray_dir, hit_normal, hit_point - center, index),
+                    (Glass::Block, None) => {
+                        inside_glass = Some((hit_entity, center));
+                        refract(ray_dir, hit_normal, 1.0 / index).unwrap_or(ray_dir)
+                    }
+                    (Glass::Block, Some(_)) => match refract(ray_dir, hit_normal, index) {
+                        Some(dir) => {
+                            inside_glass = None;
+                            dir
+                        }
+                        // total internal reflection, the beam stays inside the glass
+                        None => reflected,
+                    },
+                }
+            }
+            Err(_) => reflected,
+        };
         ray_pos = hit_point;
-        ray_dir =
-            Dir2::new((Vec2::from(ray_dir)).reflect(hit.normal)).expect("cast dir cannot be 0");
         if ignore_entity {
-            ray_qry = ray_qry.with_excluded_entities([hit.entity]);
+            ray_qry = ray_qry.with_excluded_entities([hit_entity]);
         }
 
-        // if black_ray_qry.get(hit.entity).is_ok() {
-        //     break;
-        // }
+        // black beams absorb any light that crosses them
+        if q_black_rays.contains(hit_entity) {
+            break;
+        }
+        // prisms absorb the beam and shoot their own
+        if q_prisms.contains(hit_entity) {
+            break;
+        }
+        if let Ok(filter) = q_filters.get(hit_entity) {
+            let Some(filtered) = filter.filter(color) else {
+                break;
+            };
+            ray_dir = incoming_dir;
+            // recolored beams start over with the bounces of their new color
+            if filter.recolor {
+                color = filtered;
+                ray_qry.mask = beam_collision_layers(color).filters;
+                num_segments = i + 1 + color.num_bounces() + 1;
+                extra_bounces_from_mirror = 0;
+            } else {
+                extra_bounces_from_mirror += 1;
+            }
+        }
+        if let Ok(portal) = q_portals.get(hit_entity) {
+            // portals only take in beams going into their front, and block the rest
+            let target = portal
+                .0
+                .target
+                .filter(|_| incoming_dir.dot(*portal.0.facing) < 0.0)
+                .and_then(|target| Some((target, q_portals.get(target).ok()?)));
+            let Some((target_entity, target)) = target else {
+                break;
+            };
+            let (exit_point, exit_dir) = pass_through(portal, target, hit_point, incoming_dir);
+            if let Some(intersection) = playback.intersections.last_mut() {
+                intersection.jump = true;
+            }
+            playback.intersections.push(LightBeamIntersection {
+                entity: target_entity,
+                point: exit_point,
+                time: playback.elapsed_time,
+                color,
+                jump: false,
+            });
+            ray_pos = exit_point;
+            ray_dir = exit_dir;
+            ray_qry = ray_qry.with_excluded_entities([hit_entity, target_entity]);
+            extra_bounces_from_mirror += 1;
+        }
         i += 1;
     }
+    playback.color = color;
 
     playback
 }
@@ -213,10 +347,14 @@ pub fn play_light_beam(
 #[allow(clippy::too_many_arguments)]
 pub fn simulate_light_sources(
     mut commands: Commands,
-    mut q_light_sources: Query<(&mut LightBeamSource, &mut PrevLightBeamPlayback)>,
-    // q_black_ray: Query<(Entity, &BlackRayComponent)>,
+    mut q_light_sources: Query<(Entity, &mut LightBeamSource, &mut PrevLightBeamPlayback)>,
+    q_black_rays: Query<&BlackRayComponent>,
     spatial_query: SpatialQuery,
     q_mirrors: Query<&Mirror>,
+    q_prisms: Query<&Prism>,
+    q_glass: Query<(&Glass, &GlobalTransform)>,
+    q_filters: Query<&ColorFilter>,
+    q_portals: Query<(&Portal, &GlobalTransform)>,
     // used to tell if a collision was against a white beam (a different sound is played)
     q_segments: Query<&LightSegment>,
     light_bounce_sfx: Res<LightBounceSfx>,
@@ -229,8 +367,17 @@ pub fn simulate_light_sources(
         .map(|(k, _)| k.clone())
         .collect::<HashSet<LightSegment>>();
 
-    for (mut source, mut prev_playback) in q_light_sources.iter_mut() {
-        let playback = play_light_beam(&spatial_query, &source, &q_mirrors);
+    for (source_entity, mut source, mut prev_playback) in q_light_sources.iter_mut() {
+        let playback = play_light_beam(
+            &spatial_query,
+            &source,
+            &q_black_rays,
+            &q_mirrors,
+            &q_prisms,
+            &q_glass,
+            &q_filters,
+            &q_portals,
+        );
         let mut pts: Vec<Vec2> = playback.iter_points(&source).collect();
 
         let mut i = 0;
@@ -265,7 +412,8 @@ pub fn simulate_light_sources(
                     pts[i + 1] = prev_x.unwrap().point;
                     commands.trigger(HitByLight {
                         entity: prev_x.unwrap().entity,
-                        color: source.color,
+                        source: source_entity,
+                        color: prev_x.unwrap().color,
                         hit: false,
                     });
                     prev_playback.intersections[i] = None;
@@ -278,7 +426,8 @@ pub fn simulate_light_sources(
                         };
                         commands.trigger(HitByLight {
                             entity: intersection.entity,
-                            color: source.color,
+                            source: source_entity,
+                            color: intersection.color,
                             hit: false,
                         });
                     }
@@ -289,7 +438,8 @@ pub fn simulate_light_sources(
                     pts[i + 1] = new_x.point;
                     commands.trigger(HitByLight {
                         entity: new_x.entity,
-                        color: source.color,
+                        source: source_entity,
+                        color: new_x.color,
                         hit: true,
                     });
                     if i >= prev_playback.intersections.len() {
@@ -321,7 +471,7 @@ pub fn simulate_light_sources(
                     .clone();
                     ev_spark_explosion.write(SparkExplosionEvent {
                         pos: new_x.point,
-                        color: source.color.light_beam_color(),
+                        color: new_x.color.light_beam_color(),
                     });
                     commands
                         .entity(new_x.entity)
@@ -339,7 +489,7 @@ pub fn simulate_light_sources(
         }
 
         for i in 0..pts.len() - 1 {
-            if pts[i].distance(pts[i + 1]) < 0.1 {
+            if pts[i].distance(pts[i + 1]) < 0.1 || playback.is_jump(i) {
                 continue;
             }
             // NOTE: hardcode here should be okay
@@ -351,8 +501,10 @@ pub fn simulate_light_sources(
                 .with_scale(scale)
                 .with_rotation(Quat::from_rotation_z(rotation));
 
+            let color = playback.segment_color(i);
             let segment = LightSegment {
-                color: source.color,
+                source: source_entity,
+                color,
                 index: i,
             };
 
@@ -361,12 +513,9 @@ pub fn simulate_light_sources(
                     let seg = commands
                         .spawn(transform)
                         .insert(LightSegmentBundle {
-                            segment: LightSegment {
-                                color: source.color,
-                                index: i,
-                            },
+                            segment: segment.clone(),
                             mesh: light_render_data.mesh.clone(),
-                            material: light_render_data.material_map[source.color].clone(),
+                            material: light_render_data.material_map[color].clone(),
                             visibility: Visibility::Visible,
                             transform,
                         })
@@ -375,7 +524,7 @@ pub fn simulate_light_sources(
 
                     let light = commands
                         .spawn(LineLight2d {
-                            color: source.color.lighting_color().extend(1.0),
+                            color: color.lighting_color().extend(1.0),
                             half_length: scale.x / 2.0,
                             radius: 20.0,
                             volumetric_intensity: 0.04,
@@ -383,7 +532,7 @@ pub fn simulate_light_sources(
                         .insert(ChildOf(seg))
                         .id();
 
-                    if source.color == LightColor::White {
+                    if color == LightColor::White {
                         commands.entity(seg).insert((
                             Collider::rectangle(1., 1.),
                             Sensor,
@@ -393,6 +542,17 @@ pub fn simulate_light_sources(
                             ),
                         ));
                     }
+                    if color == LightColor::Black {
+                        commands.entity(seg).insert((
+                            BlackRayComponent,
+                            Collider::rectangle(1., 1.),
+                            Sensor,
+                            CollisionLayers::new(
+                                Layers::BlackRay,
+                                [Layers::LightRay, Layers::BlueRay, Layers::WhiteRay],
+                            ),
+                        ));
+                    }
                     all_segments.remove(&segment);
                     (seg, light)
                 }
@@ -402,7 +562,7 @@ pub fn simulate_light_sources(
                         continue;
                     }
                     commands.entity(*le).try_insert(LineLight2d {
-                        color: source.color.lighting_color().extend(1.0),
+                        color: color.lighting_color().extend(1.0),
                         half_length: scale.x / 2.0,
                         radius: 20.0,
                         volumetric_intensity: 0.04,
@@ -421,9 +581,9 @@ pub fn simulate_light_sources(
     }
 }
 
-pub fn tick_light_sources(mut q_light_sources: Query<&mut LightBeamSource>, time: Res<Time>) {
+pub fn tick_light_sources(mut q_light_sources: Query<&mut LightBeamSource>) {
     for mut source in q_light_sources.iter_mut() {
-        source.time_traveled += LIGHT_SPEED * time.delta_secs() * 64.;
+        source.time_traveled += LIGHT_SPEED;
     }
 }
 
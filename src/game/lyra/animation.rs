@@ -1,5 +1,6 @@
 use avian2d::prelude::LinearVelocity;
 use bevy::{math::vec2, prelude::*};
+use serde::{Deserialize, Serialize};
 
 use crate::game::{
     animation::AnimationConfig,
@@ -31,7 +32,7 @@ impl Plugin for LyraAnimationPlugin {
     }
 }
 
-#[derive(Debug, Component, PartialEq, Eq, Clone, Copy, Default)]
+#[derive(Debug, Component, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
 pub enum PlayerAnimationType {
     #[default]
     Idle,
@@ -1,30 +1,34 @@
 use std::f32::consts::PI;
 
 use avian2d::prelude::SpatialQuery;
-use bevy::{input::mouse::MouseWheel, prelude::*};
+use bevy::{input::mouse::MouseWheel, prelude::*, window::CursorMoved};
 use enum_map::{enum_map, EnumMap};
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     asset::LoadResource,
     camera::HIGHRES_LAYER,
     game::{
+        constants::NUM_INCREMENTS,
         cursor::CursorWorldCoords,
-        defs::{mirror::Mirror, shard::CrystalShardMods},
+        defs::{glass::Glass, mirror::Mirror, portal::Portal, shard::CrystalShardMods},
         light::{
-            segments::{play_light_beam, PrevLightBeamPlayback},
-            LightBeamSource, LightColor,
+            filter::ColorFilter,
+            prism::Prism,
+            segments::{play_light_beam, tick_light_sources, PrevLightBeamPlayback},
+            BlackRayComponent, LightBeamSource, LightColor,
         },
         lighting::LineLight2d,
-        lyra::Lyra,
+        lyra::{controller::movement, Lyra},
+        recording::input_is_live,
         LevelSystems,
     },
+    input::{ActionInput, InputAction},
     ldtk::{LdtkLevelParam, LevelExt},
     shared::ResetLevels,
 };
 
-const NUM_INCREMENTS: i32 = 16; // The number of angle increments for light beam alignment
-
 pub struct BeamControllerPlugin;
 
 impl Plugin for BeamControllerPlugin {
@@ -34,14 +38,20 @@ impl Plugin for BeamControllerPlugin {
         app.add_message::<BeamAction>();
         app.add_systems(
             Update,
-            (handle_color_switch, handle_shoot_inputs, preview_light_path)
+            (
+                handle_color_switch.run_if(input_is_live),
+                handle_shoot_inputs.run_if(input_is_live),
+                preview_light_path,
+            )
                 .chain()
                 .in_set(LevelSystems::Input),
         );
+        // NOTE: on FixedUpdate, so that recordings play shots back on the exact tick they happened
         app.add_systems(
-            Update,
+            FixedUpdate,
             process_beam_actions
-                .after(preview_light_path)
+                .after(movement)
+                .before(tick_light_sources)
                 .in_set(LevelSystems::Simulation),
         );
         app.add_observer(reset_light_inventory);
@@ -70,13 +80,17 @@ pub fn reset_light_inventory(
     }
 }
 
-#[derive(Message)]
+#[derive(Message, Clone, Debug, Serialize, Deserialize)]
 pub enum BeamAction {
     SwitchColor(Option<LightColor>),
     Preview,
     Snap(bool),
+    /// Aim with a gamepad stick instead of the cursor. Reset to [`None`] when the mouse moves.
+    StickAim(Option<Vec2>),
     Cancel,
     Shoot,
+    /// Takes back the last shot, see [`rewind_last_shot`](super::rewind::rewind_last_shot).
+    Rewind,
 }
 
 #[derive(Component, Default, Debug)]
@@ -87,6 +101,8 @@ pub struct PlayerLightInventory {
     pub current_color: Option<LightColor>,
     /// Is true if the color is available
     pub sources: EnumMap<LightColor, bool>,
+    /// Last direction of the aiming stick, used instead of the cursor when set.
+    pub stick_aim: Option<Vec2>,
 }
 
 impl PlayerLightInventory {
@@ -96,12 +112,13 @@ impl PlayerLightInventory {
             snapping: false,
             should_shoot: false,
             current_color: None,
+            stick_aim: None,
             sources: enum_map! {
                 LightColor::Green =>true,
                 LightColor::Blue => true,
                 LightColor::Purple => true,
                 LightColor::White =>true,
-                // LightColor::Black => true,
+                LightColor::Black => true,
             },
         }
     }
@@ -109,23 +126,33 @@ impl PlayerLightInventory {
     pub fn can_shoot(&self) -> bool {
         self.should_shoot && self.current_color.is_some_and(|color| self.sources[color])
     }
+
+    /// The direction a beam shot from `from` would go in, or [`Vec2::ZERO`] if there is none.
+    pub fn aim_dir(&self, from: Vec2, cursor: Vec2) -> Vec2 {
+        let ray_dir = self.stick_aim.unwrap_or(cursor - from).normalize_or_zero();
+        if self.snapping {
+            snap_ray(ray_dir)
+        } else {
+            ray_dir
+        }
+    }
 }
 
-/// [`System`] to handle the keyboard presses corresponding to color switches.
+/// [`System`] to handle the inputs corresponding to color switches.
 pub fn handle_color_switch(
-    keys: Res<ButtonInput<KeyCode>>,
+    input: ActionInput,
     mut ev_scroll: MessageReader<MouseWheel>,
     mut beam_actions: MessageWriter<BeamAction>,
     inventory: Single<&PlayerLightInventory, With<Lyra>>,
     shard_modifs: Res<CrystalShardMods>,
     ldtk_level_param: LdtkLevelParam,
 ) {
-    static COLOR_BINDS: [(KeyCode, LightColor); 4] = [
-        (KeyCode::Digit1, LightColor::Green),
-        (KeyCode::Digit2, LightColor::Purple),
-        (KeyCode::Digit3, LightColor::White),
-        (KeyCode::Digit4, LightColor::Blue),
-        // (KeyCode::Digit5, LightColor::Black),
+    static COLOR_BINDS: [(InputAction, LightColor); 5] = [
+        (InputAction::Green, LightColor::Green),
+        (InputAction::Purple, LightColor::Purple),
+        (InputAction::White, LightColor::White),
+        (InputAction::Blue, LightColor::Blue),
+        (InputAction::Black, LightColor::Black),
     ];
 
     let mut cur_index = match inventory.current_color {
@@ -134,7 +161,7 @@ pub fn handle_color_switch(
         Some(LightColor::Purple) => 1,
         Some(LightColor::White) => 2,
         Some(LightColor::Blue) => 3,
-        // Some(LightColor::Black) => 4,
+        Some(LightColor::Black) => 4,
     };
 
     let mut allowed_colors = ldtk_level_param
@@ -149,54 +176,76 @@ pub fn handle_color_switch(
         }
     }
 
-    for scroll in ev_scroll.read() {
-        let sign = -(scroll.y.signum() as i32);
+    let cycle_action = if input.just_pressed(InputAction::NextColor) {
+        Some(1)
+    } else if input.just_pressed(InputAction::PrevColor) {
+        Some(-1)
+    } else {
+        None
+    };
+    let cycles = ev_scroll
+        .read()
+        .map(|scroll| -(scroll.y.signum() as i32))
+        .chain(cycle_action);
+
+    let num_colors = COLOR_BINDS.len() as i32;
+    for sign in cycles {
         let mut new_index = cur_index + sign;
 
-        // suspicious algorithm to cycle through available colors with the scroll wheel
+        // suspicious algorithm to cycle through available colors with the scroll wheel (or bumpers)
         // basically skips disallowed colors until you find the next one
         let mut count = 0;
-        while !allowed_colors[COLOR_BINDS[new_index.rem_euclid(4) as usize].1]
+        while !allowed_colors[COLOR_BINDS[new_index.rem_euclid(num_colors) as usize].1]
             && count < COLOR_BINDS.len()
         {
             new_index += sign;
             count += 1;
         }
         cur_index = new_index;
-        if allowed_colors[COLOR_BINDS[new_index.rem_euclid(4) as usize].1] {
+        if allowed_colors[COLOR_BINDS[new_index.rem_euclid(num_colors) as usize].1] {
             beam_actions.write(BeamAction::SwitchColor(Some(
-                COLOR_BINDS[cur_index.rem_euclid(4) as usize].1,
+                COLOR_BINDS[cur_index.rem_euclid(num_colors) as usize].1,
             )));
         }
     }
 
-    for (key, color) in COLOR_BINDS {
-        if keys.just_pressed(key) && allowed_colors[color] {
+    for (action, color) in COLOR_BINDS {
+        if input.just_pressed(action) && allowed_colors[color] {
             beam_actions.write(BeamAction::SwitchColor(Some(color)));
         }
     }
 }
 
 pub fn handle_shoot_inputs(
-    mouse: Res<ButtonInput<MouseButton>>,
-    keys: Res<ButtonInput<KeyCode>>,
+    input: ActionInput,
+    mut ev_cursor_moved: MessageReader<CursorMoved>,
     mut beam_actions: MessageWriter<BeamAction>,
 ) {
-    if mouse.just_pressed(MouseButton::Left) {
+    let cursor_moved = ev_cursor_moved.read().count() > 0;
+    if let Some(stick) = input.right_stick() {
+        beam_actions.write(BeamAction::StickAim(Some(stick)));
+    } else if cursor_moved {
+        beam_actions.write(BeamAction::StickAim(None));
+    }
+
+    if input.just_pressed(InputAction::Aim) {
         beam_actions.write(BeamAction::Preview);
     }
-    if mouse.just_pressed(MouseButton::Right) {
+    if input.just_pressed(InputAction::CancelAim) {
         beam_actions.write(BeamAction::Cancel);
     }
-    if mouse.just_released(MouseButton::Left) {
+    if input.just_released(InputAction::Aim) {
         beam_actions.write(BeamAction::Shoot);
     }
-    if keys.just_pressed(KeyCode::ShiftLeft) {
+    if input.just_pressed(InputAction::Snap) {
         beam_actions.write(BeamAction::Snap(true));
     }
-    if keys.just_released(KeyCode::ShiftLeft) {
+    if input.just_released(InputAction::Snap) {
         beam_actions.write(BeamAction::Snap(false));
     }
+    if input.just_pressed(InputAction::Rewind) {
+        beam_actions.write(BeamAction::Rewind);
+    }
 }
 
 #[derive(Resource, Reflect, Asset, Clone)]
@@ -219,6 +268,43 @@ impl FromWorld for BeamSourceAssets {
     }
 }
 
+/// Event triggered right before Lyra shoots a beam, while the beam isn't spawned yet.
+#[derive(Event)]
+pub struct BeamShot {
+    pub color: LightColor,
+}
+
+/// Marker [`Component`] for the [`LightBeamSource`]s shot by Lyra, as opposed to the ones owned by
+/// emitters.
+#[derive(Component)]
+pub struct PlayerBeam;
+
+/// Spawns a [`LightBeamSource`] shot by Lyra, along with its compass sprite and light.
+pub fn spawn_player_beam(
+    commands: &mut Commands,
+    beam_assets: &BeamSourceAssets,
+    source: LightBeamSource,
+) {
+    // NOTE: hardcode here should be okay
+    let mut source_transform = Transform::from_translation(source.start_pos.extend(3.));
+    source_transform.rotate_z(source.start_dir.to_angle());
+    let mut source_sprite = Sprite::from_image(beam_assets.compass.clone());
+    source_sprite.color = Color::srgb(2.0, 2.0, 2.0);
+    let mut outer_source_sprite = Sprite::from_image(beam_assets.compass_gold.clone());
+    outer_source_sprite.color = source.color.light_beam_color().mix(&Color::BLACK, 0.4);
+    let light_color = source.color.lighting_color();
+
+    commands
+        .spawn(source)
+        .insert(PlayerBeam)
+        .insert(PrevLightBeamPlayback::default())
+        .insert(HIGHRES_LAYER)
+        .insert(source_sprite)
+        .insert(source_transform)
+        .with_child((outer_source_sprite, HIGHRES_LAYER))
+        .with_child(LineLight2d::point(light_color.extend(1.0), 30.0, 0.02));
+}
+
 pub fn process_beam_actions(
     mut commands: Commands,
     mut beam_actions: MessageReader<BeamAction>,
@@ -235,42 +321,24 @@ pub fn process_beam_actions(
                 }
 
                 let ray_pos = player_transform.translation.truncate();
-                let mut ray_dir = (cursor.pos - ray_pos).normalize_or_zero();
-                if player_inventory.snapping {
-                    ray_dir = snap_ray(ray_dir);
-                }
+                let ray_dir = player_inventory.aim_dir(ray_pos, cursor.pos);
                 if ray_dir == Vec2::ZERO {
                     continue;
                 }
                 let ray_dir = Dir2::new_unchecked(ray_dir);
 
                 let shoot_color = player_inventory.current_color.unwrap();
-
-                // NOTE: hardcode here should be okay
-                let mut source_transform = Transform::from_translation(ray_pos.extend(3.));
-                source_transform.rotate_z(ray_dir.to_angle());
-                let mut source_sprite = Sprite::from_image(beam_assets.compass.clone());
-                source_sprite.color = Color::srgb(2.0, 2.0, 2.0);
-                let mut outer_source_sprite = Sprite::from_image(beam_assets.compass_gold.clone());
-                outer_source_sprite.color = shoot_color.light_beam_color().mix(&Color::BLACK, 0.4);
-
-                commands
-                    .spawn(LightBeamSource {
+                commands.trigger(BeamShot { color: shoot_color });
+                spawn_player_beam(
+                    &mut commands,
+                    &beam_assets,
+                    LightBeamSource {
                         start_pos: ray_pos,
                         start_dir: ray_dir,
                         time_traveled: 0.0,
                         color: shoot_color,
-                    })
-                    .insert(PrevLightBeamPlayback::default())
-                    .insert(HIGHRES_LAYER)
-                    .insert(source_sprite)
-                    .insert(source_transform)
-                    .with_child((outer_source_sprite, HIGHRES_LAYER))
-                    .with_child(LineLight2d::point(
-                        shoot_color.lighting_color().extend(1.0),
-                        30.0,
-                        0.02,
-                    ));
+                    },
+                );
 
                 player_inventory.sources[shoot_color] = false;
                 player_inventory.should_shoot = false;
@@ -279,6 +347,9 @@ pub fn process_beam_actions(
             BeamAction::Snap(val) => {
                 player_inventory.snapping = *val;
             }
+            BeamAction::StickAim(stick) => {
+                player_inventory.stick_aim = *stick;
+            }
             BeamAction::Preview => {
                 player_inventory.previewing = true;
                 player_inventory.should_shoot = true;
@@ -290,6 +361,7 @@ pub fn process_beam_actions(
             BeamAction::SwitchColor(color) => {
                 player_inventory.current_color = *color;
             }
+            BeamAction::Rewind => {}
         }
     }
 }
@@ -297,13 +369,18 @@ pub fn process_beam_actions(
 #[derive(Default, Reflect, GizmoConfigGroup)]
 pub struct LightPreviewGizmos;
 
+#[allow(clippy::too_many_arguments)]
 pub fn preview_light_path(
     spatial_query: SpatialQuery,
     lyra: Single<(&Transform, &PlayerLightInventory), With<Lyra>>,
     cursor: Single<&CursorWorldCoords>,
     mut gizmos: Gizmos,
     q_mirror: Query<&Mirror>,
-    // q_black_ray: Query<(Entity, &BlackRayComponent)>,
+    q_black_rays: Query<&BlackRayComponent>,
+    q_prisms: Query<&Prism>,
+    q_glass: Query<(&Glass, &GlobalTransform)>,
+    q_filters: Query<&ColorFilter>,
+    q_portals: Query<(&Portal, &GlobalTransform)>,
 ) {
     let (transform, inventory) = lyra.into_inner();
     if !inventory.can_shoot() || !inventory.previewing {
@@ -313,10 +390,7 @@ pub fn preview_light_path(
     let shoot_color = inventory.current_color.unwrap();
 
     let ray_pos = transform.translation.truncate();
-    let mut ray_dir = (cursor.pos - ray_pos).normalize_or_zero();
-    if inventory.snapping {
-        ray_dir = snap_ray(ray_dir);
-    }
+    let ray_dir = inventory.aim_dir(ray_pos, cursor.pos);
     if ray_dir == Vec2::ZERO {
         return;
     }
@@ -331,12 +405,24 @@ pub fn preview_light_path(
     let playback = play_light_beam(
         &spatial_query,
         &dummy_source,
-        // &q_black_ray,
+        &q_black_rays,
         &q_mirror,
+        &q_prisms,
+        &q_glass,
+        &q_filters,
+        &q_portals,
     );
 
-    for (a, b) in playback.iter_points(&dummy_source).tuple_windows() {
-        gizmos.line_2d(a, b, shoot_color.light_beam_color().darker(0.3));
+    for (i, (a, b)) in playback
+        .iter_points(&dummy_source)
+        .tuple_windows()
+        .enumerate()
+    {
+        if playback.is_jump(i) {
+            continue;
+        }
+        let color = playback.segment_color(i);
+        gizmos.line_2d(a, b, color.light_beam_color().darker(0.3));
     }
 }
 
@@ -1,35 +1,32 @@
 use avian2d::{math::*, prelude::*};
 use bevy::{ecs::query::Has, prelude::*};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    game::{lyra::Lyra, LevelSystems},
+    game::{
+        constants::{
+            COYOTE_TIME_TICKS, JUMP_BOOST_TICKS, PLAYER_GRAVITY, PLAYER_JUMP_VEL, PLAYER_MAX_H_VEL,
+            PLAYER_MAX_Y_VEL, PLAYER_MOVE_VEL, SHOULD_JUMP_TICKS,
+        },
+        lyra::Lyra,
+        recording::input_is_live,
+        LevelSystems,
+    },
+    input::{ActionInput, InputAction},
     shared::PlayState,
 };
 
-/// The number of [`FixedUpdate`] steps the player can jump for after pressing the spacebar.
-const SHOULD_JUMP_TICKS: isize = 8;
-/// The number of [`FixedUpdate`] steps the player can jump for after falling off an edge.
-const COYOTE_TIME_TICKS: isize = 5;
-/// The number of [`FixedUpdate`] steps the player should receive upward velocity for.
-const JUMP_BOOST_TICKS: isize = 2;
-
-/// Max player horizontal velocity.
-const PLAYER_MAX_H_VEL: f32 = 1.5;
-/// Max player vertical velocity.
-const PLAYER_MAX_Y_VEL: f32 = 5.;
-/// The positive y velocity added to the player every jump boost tick.
-const PLAYER_JUMP_VEL: f32 = 2.2;
-/// The x velocity added to the player when A/D is held.
-const PLAYER_MOVE_VEL: f32 = 0.4;
-/// The y velocity subtracted from the player due to gravity.
-const PLAYER_GRAVITY: f32 = 0.15;
-
 pub struct CharacterControllerPlugin;
 
 impl Plugin for CharacterControllerPlugin {
     fn build(&self, app: &mut App) {
         app.add_message::<MovementAction>();
-        app.add_systems(Update, keyboard_input.in_set(LevelSystems::Input));
+        app.add_systems(
+            Update,
+            keyboard_input
+                .run_if(input_is_live)
+                .in_set(LevelSystems::Input),
+        );
         app.add_systems(
             FixedUpdate,
             update_grounded
@@ -60,7 +57,7 @@ pub fn res_linear_vel(lyra: Single<(&mut LinearVelocity, &mut CachedLinearVeloci
 }
 
 /// A [`Message`] written for a movement input action.
-#[derive(Message)]
+#[derive(Message, Clone, Debug, Serialize, Deserialize)]
 pub enum MovementAction {
     Move(Scalar),
     Jump,
@@ -87,30 +84,34 @@ pub struct MovementInfo {
     pub crouched: bool,
 }
 
-pub fn keyboard_input(
-    mut movement_writer: MessageWriter<MovementAction>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-) {
-    let left = keyboard_input.any_pressed([KeyCode::KeyA, KeyCode::ArrowLeft]);
-    let right = keyboard_input.any_pressed([KeyCode::KeyD, KeyCode::ArrowRight]);
+pub fn keyboard_input(mut movement_writer: MessageWriter<MovementAction>, input: ActionInput) {
+    let left = input.pressed(InputAction::MoveLeft);
+    let right = input.pressed(InputAction::MoveRight);
 
     let horizontal = right as i8 - left as i8;
-    let direction = horizontal as Scalar;
+    let mut direction = horizontal as Scalar;
+
+    // the stick is only read when no movement buttons are held
+    if direction == 0.0 {
+        if let Some(stick) = input.left_stick() {
+            direction = stick.x.clamp(-1.0, 1.0) as Scalar;
+        }
+    }
 
     if direction != 0.0 {
         movement_writer.write(MovementAction::Move(direction));
     }
 
-    if keyboard_input.just_pressed(KeyCode::Space) {
+    if input.just_pressed(InputAction::Jump) {
         movement_writer.write(MovementAction::Jump);
     }
-    if keyboard_input.just_released(KeyCode::Space) {
+    if input.just_released(InputAction::Jump) {
         movement_writer.write(MovementAction::JumpCut);
     }
-    if keyboard_input.just_pressed(KeyCode::ShiftLeft) {
+    if input.just_pressed(InputAction::Crouch) {
         movement_writer.write(MovementAction::Crouch);
     }
-    if keyboard_input.just_released(KeyCode::ShiftLeft) {
+    if input.just_released(InputAction::Crouch) {
         movement_writer.write(MovementAction::Stand);
     }
 }
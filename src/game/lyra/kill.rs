@@ -1,7 +1,7 @@
 use std::time::Duration;
 
 use avian2d::prelude::{CollisionStart, Position};
-use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+use bevy::prelude::*;
 
 use crate::{
     asset::LoadResource,
@@ -9,9 +9,10 @@ use crate::{
     camera::{CameraTransition, CameraTransitionEvent},
     game::{
         camera_op::SnapToLyra,
-        defs::DangerBox,
+        defs::{checkpoint::RestoreCheckpoint, DangerBox},
         lyra::{lyra_spawn_transform, Lyra},
     },
+    input::{action_just_pressed, InputAction},
     ldtk::LdtkLevelParam,
     shared::{AnimationState, PlayState, ResetLevels},
 };
@@ -27,7 +28,7 @@ impl Plugin for LyraKillPlugin {
         app.add_systems(
             Update,
             quick_reset
-                .run_if(input_just_pressed(KeyCode::KeyR))
+                .run_if(action_just_pressed(InputAction::Restart))
                 .run_if(in_state(PlayState::Playing)),
         );
     }
@@ -50,7 +51,7 @@ impl FromWorld for KillSfx {
 }
 
 pub fn quick_reset(mut commands: Commands) {
-    commands.trigger(KillPlayer);
+    commands.trigger(KillPlayer { restart: true });
 }
 
 pub fn play_death_sound_on_kill(
@@ -66,7 +67,10 @@ pub fn play_death_sound_on_kill(
 }
 
 #[derive(Event)]
-pub struct KillPlayer;
+pub struct KillPlayer {
+    /// Whether the player restarted the level themselves, rather than dying.
+    pub restart: bool,
+}
 
 pub fn kill_player_on_danger(
     event: On<CollisionStart>,
@@ -76,7 +80,7 @@ pub fn kill_player_on_danger(
     if q_danger_box.get(event.collider2).is_err() {
         return;
     }
-    commands.trigger(KillPlayer);
+    commands.trigger(KillPlayer { restart: false });
 }
 
 pub fn start_kill_animation(
@@ -114,6 +118,7 @@ pub fn start_kill_animation(
                     effect: CameraTransition::SlideFromBlack,
                 });
                 commands.trigger(ResetLevels);
+                commands.trigger(RestoreCheckpoint);
 
                 let lyra_transform = lyra_spawn_transform(&ldtk_level_param);
                 *transform = Transform::from_translation(lyra_transform.extend(0.));
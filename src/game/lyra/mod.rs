@@ -8,8 +8,13 @@ use crate::{
     game::{
         animation::AnimationConfig,
         camera_op::SnapToLyra,
+        constants::{
+            LYRA_COLLIDER_OFFSET, LYRA_COLLIDER_SIZE, LYRA_HURTBOX_SIZE, LYRA_RESPAWN_EPSILON,
+        },
         defs::{
+            checkpoint::touch_checkpoint,
             one_way_platform::PassThroughOneWayPlatform,
+            portal::enter_portal,
             shard::on_player_intersect_shard,
             tooltip_sign::{display_tooltip_signs, hide_tooltip_signs},
         },
@@ -24,24 +29,24 @@ use crate::{
             indicator::LightIndicatorPlugin,
             kill::{kill_player_on_danger, LyraKillPlugin},
             restart_hint::HintRestartPlugin,
+            rewind::RewindPlugin,
             strand::LyraStrandPlugin,
         },
         Layers,
     },
-    ldtk::{LdtkLevelParam, LevelExt},
+    ldtk::{LdtkLevelParam, LdtkProjectReloaded, LevelExt},
     shared::GameState,
 };
 
-mod animation;
+pub mod animation;
 pub mod beam;
 pub mod controller;
 mod indicator;
-mod kill;
+pub mod kill;
 mod restart_hint;
+pub mod rewind;
 mod strand;
 
-pub const LYRA_RESPAWN_EPSILON: f32 = 3.0;
-
 pub struct LyraPlugin;
 
 impl Plugin for LyraPlugin {
@@ -53,11 +58,13 @@ impl Plugin for LyraPlugin {
         app.add_plugins(LyraAnimationPlugin);
         app.add_plugins(LyraKillPlugin);
         app.add_plugins(BeamControllerPlugin);
+        app.add_plugins(RewindPlugin);
         app.add_plugins(HintRestartPlugin);
         app.add_plugins(LightIndicatorPlugin);
         app.add_systems(OnEnter(GameState::InGame), spawn_lyra);
         app.add_systems(OnEnter(GameState::InGame), spawn_lyra_cam.after(spawn_lyra));
         app.add_systems(OnExit(GameState::InGame), despawn_lyra);
+        app.add_observer(place_lyra_on_reload);
     }
 }
 
@@ -125,9 +132,9 @@ pub fn spawn_lyra(
         .insert(RigidBody::Dynamic)
         .insert(TransformInterpolation)
         .insert(Collider::compound(vec![(
-            Vec2::new(0.0, -2.0),
+            LYRA_COLLIDER_OFFSET,
             Rotation::default(),
-            Collider::rectangle(12.0, 16.0), // 12 x 16
+            Collider::rectangle(LYRA_COLLIDER_SIZE.x, LYRA_COLLIDER_SIZE.y),
         )]))
         .insert(MovementInfo::default())
         .insert(
@@ -161,9 +168,9 @@ pub fn spawn_lyra(
 
     commands
         .spawn(Collider::compound(vec![(
-            Vec2::new(0.0, -2.0),
+            LYRA_COLLIDER_OFFSET,
             Rotation::default(),
-            Collider::rectangle(6.0, 8.0),
+            Collider::rectangle(LYRA_HURTBOX_SIZE.x, LYRA_HURTBOX_SIZE.y),
         )]))
         .insert(CollisionEventsEnabled)
         .insert(Sensor)
@@ -184,11 +191,35 @@ pub fn spawn_lyra(
         .observe(display_tooltip_signs)
         .observe(handle_start_end_markers)
         .observe(on_player_intersect_shard)
+        .observe(touch_checkpoint)
+        .observe(enter_portal)
         .observe(kill_player_on_danger);
 
     commands.trigger(SnapToLyra);
 }
 
+/// Keeps Lyra where she is when the LDtk project is reloaded, unless the current level no longer
+/// contains her, in which case she is moved to its start flag.
+pub fn place_lyra_on_reload(
+    _: On<LdtkProjectReloaded>,
+    mut commands: Commands,
+    mut lyra: Query<&mut Transform, With<Lyra>>,
+    ldtk_level_param: LdtkLevelParam,
+) {
+    let Ok(mut transform) = lyra.single_mut() else {
+        return;
+    };
+    let Some(level) = ldtk_level_param.cur_level() else {
+        return;
+    };
+    if level.raw().level_box().contains(transform.translation.xy()) {
+        return;
+    }
+
+    transform.translation = lyra_spawn_transform(&ldtk_level_param).extend(transform.translation.z);
+    commands.trigger(SnapToLyra);
+}
+
 #[derive(Component)]
 pub struct PlayerCamera;
 
@@ -0,0 +1,230 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_ecs_ldtk::prelude::*;
+use enum_map::EnumMap;
+
+use crate::{
+    game::{
+        defs::{
+            crystal::{CrystalCache, CrystalColor, CrystalGroup, CrystalToggleEvent},
+            sensor::LightSensor,
+            shard::CrystalShardMods,
+        },
+        light::{
+            segments::{tick_light_sources, unhit_beam, LightSegmentCache, PrevLightBeamPlayback},
+            LightBeamSource, LightColor,
+        },
+        lyra::{
+            beam::{
+                process_beam_actions, spawn_player_beam, BeamAction, BeamShot, BeamSourceAssets,
+                PlayerBeam, PlayerLightInventory,
+            },
+            Lyra,
+        },
+        signal::LevelSignal,
+        LevelSystems,
+    },
+    ldtk::{LdtkLevelParam, ParentLevelParam},
+    shared::ResetLevels,
+};
+
+pub struct RewindPlugin;
+
+impl Plugin for RewindPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RewindStack>();
+        app.add_observer(push_rewind_point);
+        app.add_observer(clear_rewind_stack);
+        app.add_systems(
+            FixedUpdate,
+            rewind_last_shot
+                .after(process_beam_actions)
+                .before(tick_light_sources)
+                .in_set(LevelSystems::Simulation),
+        );
+    }
+}
+
+/// The state of the puzzle in the current level: which crystals are toggled, how full the sensors
+/// are, the beams Lyra has shot and the colors she has left.
+#[derive(Clone)]
+pub struct PuzzleSnapshot {
+    /// Colors of the crystals toggled an odd number of times.
+    toggled_colors: Vec<CrystalColor>,
+    sensors: Vec<(Entity, LightSensor)>,
+    beams: Vec<LightBeamSource>,
+    sources: EnumMap<LightColor, bool>,
+    current_color: Option<LightColor>,
+    shard_mods: EnumMap<LightColor, bool>,
+}
+
+/// [`SystemParam`] to take a [`PuzzleSnapshot`] of the current level and bring it back later.
+#[derive(SystemParam)]
+pub struct PuzzleParam<'w, 's> {
+    commands: Commands<'w, 's>,
+    crystal_cache: Res<'w, CrystalCache>,
+    q_crystal_groups: Query<'w, 's, &'static CrystalGroup>,
+    q_sensors: Query<'w, 's, (Entity, &'static mut LightSensor)>,
+    parent_level: ParentLevelParam<'w, 's>,
+    q_beams: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static LightBeamSource,
+            &'static PrevLightBeamPlayback,
+        ),
+        With<PlayerBeam>,
+    >,
+    q_inventory: Query<'w, 's, &'static mut PlayerLightInventory, With<Lyra>>,
+    shard_mods: ResMut<'w, CrystalShardMods>,
+    beam_assets: Res<'w, BeamSourceAssets>,
+    segment_cache: ResMut<'w, LightSegmentCache>,
+}
+
+impl PuzzleParam<'_, '_> {
+    fn toggled_colors(&self, level_iid: &LevelIid) -> Vec<CrystalColor> {
+        self.crystal_cache
+            .groups(level_iid)
+            .filter(|(_, groups)| {
+                groups
+                    .iter()
+                    .filter_map(|group| self.q_crystal_groups.get(*group).ok())
+                    .any(|group| group.0.is_toggled())
+            })
+            .map(|(color, _)| *color)
+            .collect()
+    }
+
+    pub fn snapshot(&self, level_iid: &LevelIid) -> Option<PuzzleSnapshot> {
+        let inventory = self.q_inventory.single().ok()?;
+        Some(PuzzleSnapshot {
+            toggled_colors: self.toggled_colors(level_iid),
+            sensors: self
+                .q_sensors
+                .iter()
+                .map(|(entity, sensor)| (entity, sensor.clone()))
+                .collect(),
+            beams: self
+                .q_beams
+                .iter()
+                .map(|(_, source, _)| source.clone())
+                .collect(),
+            sources: inventory.sources,
+            current_color: inventory.current_color,
+            shard_mods: self.shard_mods.0,
+        })
+    }
+
+    pub fn restore(&mut self, level_iid: &LevelIid, snapshot: &PuzzleSnapshot) {
+        // toggling is all or nothing per color, so only the colors that differ need a toggle
+        let toggled_colors = self.toggled_colors(level_iid);
+        for color in toggled_colors
+            .iter()
+            .filter(|color| !snapshot.toggled_colors.contains(color))
+            .chain(
+                snapshot
+                    .toggled_colors
+                    .iter()
+                    .filter(|color| !toggled_colors.contains(color)),
+            )
+        {
+            self.commands.trigger(CrystalToggleEvent {
+                level: level_iid.clone(),
+                color: *color,
+            });
+        }
+
+        let mut removed = Vec::new();
+        for (entity, _, playback) in self.q_beams.iter() {
+            unhit_beam(&mut self.commands, entity, playback);
+            self.commands.entity(entity).try_despawn();
+            removed.push(entity);
+        }
+        let commands = &mut self.commands;
+        self.segment_cache
+            .retain(|segment, (_, segment_entity, _)| {
+                if !removed.contains(&segment.source) {
+                    return true;
+                }
+                commands.entity(*segment_entity).try_despawn();
+                false
+            });
+
+        for (entity, saved) in snapshot.sensors.iter() {
+            let Ok((_, mut sensor)) = self.q_sensors.get_mut(*entity) else {
+                continue;
+            };
+            // NOTE: the crystals toggled by the sensor are already restored above, only the
+            // signals need to be sent again
+            let level = self.parent_level.level_iid(*entity);
+            if let Some(level) = level.filter(|_| sensor.is_active != saved.is_active) {
+                for id in sensor.signals.iter() {
+                    self.commands.trigger(LevelSignal {
+                        level: level.clone(),
+                        id: *id,
+                        active: saved.is_active,
+                    });
+                }
+            }
+            sensor.restore(saved);
+        }
+        for beam in snapshot.beams.iter() {
+            spawn_player_beam(&mut self.commands, &self.beam_assets, beam.clone());
+        }
+
+        if let Ok(mut inventory) = self.q_inventory.single_mut() {
+            inventory.sources = snapshot.sources;
+            inventory.current_color = snapshot.current_color;
+        }
+        self.shard_mods.0 = snapshot.shard_mods;
+    }
+}
+
+/// [`Resource`] with a [`PuzzleSnapshot`] from before each shot taken since the level was last
+/// reset.
+#[derive(Resource, Default)]
+pub struct RewindStack(Vec<PuzzleSnapshot>);
+
+pub fn push_rewind_point(
+    event: On<BeamShot>,
+    puzzle: PuzzleParam,
+    ldtk_level_param: LdtkLevelParam,
+    mut rewind_stack: ResMut<RewindStack>,
+) {
+    let Some(level_iid) = ldtk_level_param.cur_iid() else {
+        return;
+    };
+    let Some(mut snapshot) = puzzle.snapshot(&level_iid) else {
+        return;
+    };
+    // NOTE: the shot has already used up its color by the time this runs
+    snapshot.sources[event.color] = true;
+    rewind_stack.0.push(snapshot);
+}
+
+pub fn clear_rewind_stack(_: On<ResetLevels>, mut rewind_stack: ResMut<RewindStack>) {
+    rewind_stack.0.clear();
+}
+
+/// [`System`] that brings the level back to how it was before the last shot when a
+/// [`BeamAction::Rewind`] comes in. Lyra stays where she is.
+pub fn rewind_last_shot(
+    mut beam_actions: MessageReader<BeamAction>,
+    mut rewind_stack: ResMut<RewindStack>,
+    mut puzzle: PuzzleParam,
+    ldtk_level_param: LdtkLevelParam,
+) {
+    for action in beam_actions.read() {
+        if !matches!(action, BeamAction::Rewind) {
+            continue;
+        }
+        let Some(snapshot) = rewind_stack.0.pop() else {
+            continue;
+        };
+        let Some(level_iid) = ldtk_level_param.cur_iid() else {
+            return;
+        };
+        info!("Rewinding the last shot");
+        puzzle.restore(&level_iid, &snapshot);
+    }
+}
@@ -11,12 +11,15 @@ use crate::{
         cursor::CursorCoordsPlugin,
         defs::{one_way_platform::OneWayPlatformHooks, LevelPlugin},
         dialogue::DialoguePlugin,
+        ghost::GhostPlugin,
         level_completion::LevelCompletionPlugin,
         light::LightBeamPlugin,
         lighting::DeferredLightingPlugin,
         lyra::LyraPlugin,
         particle::ParticlePlugin,
+        recording::InputRecordingPlugin,
         setup::LevelSetupPlugin,
+        signal::LevelSignalPlugin,
         switch::SwitchLevelPlugin,
     },
     shared::{AnimationState, GameState, PlayState},
@@ -25,12 +28,22 @@ use crate::{
 mod animation;
 mod bgm;
 mod camera_op;
+pub mod constants;
+#[cfg(feature = "dev_mode")]
+mod console;
 mod cursor;
+#[cfg(feature = "dev_mode")]
+mod debug;
 pub mod defs;
 mod dialogue;
+#[cfg(feature = "dev_mode")]
+mod editor;
+pub mod ghost;
 pub mod light;
 pub mod lyra;
+pub mod recording;
 pub mod setup;
+pub mod signal;
 mod switch;
 // mod input;
 // mod level;
@@ -49,7 +62,6 @@ impl Plugin for GamePlugin {
                 .with_length_unit(8.)
                 .with_collision_hooks::<OneWayPlatformHooks>(),
         );
-        // app.add_plugins(PhysicsDebugPlugin);
         // app.add_plugins(PhysicsDiagnosticsPlugin);
         app.add_plugins(CursorCoordsPlugin);
         app.add_plugins(LevelBgmPlugin);
@@ -64,6 +76,15 @@ impl Plugin for GamePlugin {
         app.add_plugins(LightBeamPlugin);
         app.add_plugins(DeferredLightingPlugin);
         app.add_plugins(DialoguePlugin);
+        app.add_plugins(LevelSignalPlugin);
+        app.add_plugins(InputRecordingPlugin);
+        app.add_plugins(GhostPlugin);
+        #[cfg(feature = "dev_mode")]
+        app.add_plugins(editor::LevelEditorPlugin);
+        #[cfg(feature = "dev_mode")]
+        app.add_plugins(debug::DebugOverlayPlugin);
+        #[cfg(feature = "dev_mode")]
+        app.add_plugins(console::DevConsolePlugin);
         app.insert_resource(Gravity::ZERO);
         app.configure_sets(
             PreUpdate,
@@ -115,7 +136,7 @@ pub enum Layers {
     BlueCrystal,
     LightSensor,
     SensorBox,
-    // BlackRay,
+    BlackRay,
 }
 
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
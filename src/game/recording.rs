@@ -0,0 +1,358 @@
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    game::{
+        cursor::CursorWorldCoords,
+        lyra::{
+            beam::{process_beam_actions, BeamAction, PlayerLightInventory},
+            controller::{movement, MovementAction},
+            rewind::rewind_last_shot,
+            Lyra,
+        },
+        LevelSystems,
+    },
+    ldtk::{LdtkLevelParam, LevelExt},
+    shared::ResetLevels,
+};
+
+/// Bumped whenever the layout or the timing of [`InputRecording`] changes.
+const RECORDING_VERSION: u32 = 1;
+
+const RECORDING_DIR: &str = "recordings";
+
+pub struct InputRecordingPlugin;
+
+impl Plugin for InputRecordingPlugin {
+    fn build(&self, app: &mut App) {
+        let config = app.world().resource::<Config>();
+        if config.debug_config.record_inputs {
+            app.insert_resource(Recorder(None));
+        }
+        if let Some(path) = &config.debug_config.replay_path {
+            match InputRecording::load(path) {
+                Ok(recording) => {
+                    app.insert_resource(Playback::new(recording));
+                }
+                Err(err) => error!("Failed to load replay {}: {}", path, err),
+            }
+        }
+
+        app.init_resource::<FixedTick>();
+        app.add_observer(end_attempt);
+        app.add_systems(
+            FixedUpdate,
+            (begin_attempt, play_back_movement, record_movement)
+                .chain()
+                .before(movement)
+                .in_set(LevelSystems::Simulation),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (play_back_beam_actions, record_beam_actions)
+                .chain()
+                .after(movement)
+                .before(process_beam_actions)
+                .in_set(LevelSystems::Simulation),
+        );
+        app.add_systems(
+            FixedUpdate,
+            advance_fixed_tick
+                .after(rewind_last_shot)
+                .in_set(LevelSystems::Simulation),
+        );
+    }
+}
+
+/// [`Resource`] counting the [`FixedUpdate`] steps since the start of the current level attempt.
+#[derive(Resource, Default, Debug)]
+pub struct FixedTick(pub u32);
+
+/// The inputs of a single level attempt, from the moment Lyra entered or respawned in the level.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct InputRecording {
+    version: u32,
+    pub level_iid: String,
+    pub level_id: String,
+    /// State of Lyra at tick 0, since she might have walked into the level instead of spawning.
+    pub start_position: Vec2,
+    pub start_velocity: Vec2,
+    /// Only the ticks during which something was pressed, in order.
+    pub ticks: Vec<RecordedTick>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RecordedTick {
+    pub tick: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub movement: Vec<MovementAction>,
+    /// [`BeamAction`]s processed during this tick. Every [`BeamAction::Shoot`] is preceded by a
+    /// [`BeamAction::StickAim`] holding the direction the cursor was in.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub beam: Vec<BeamAction>,
+}
+
+impl InputRecording {
+    pub fn new(
+        level_iid: String,
+        level_id: String,
+        start_position: Vec2,
+        start_velocity: Vec2,
+        ticks: Vec<RecordedTick>,
+    ) -> Self {
+        InputRecording {
+            version: RECORDING_VERSION,
+            level_iid,
+            level_id,
+            start_position,
+            start_velocity,
+            ticks,
+        }
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let recording: InputRecording = toml::from_str(&contents).map_err(|err| err.to_string())?;
+        if recording.version != RECORDING_VERSION {
+            return Err(format!(
+                "recording version {} is not supported (expected {})",
+                recording.version, RECORDING_VERSION
+            ));
+        }
+        Ok(recording)
+    }
+
+    /// Writes the recording to the first free `recordings/<LevelId>_<attempt>.toml`, so that every
+    /// attempt is kept.
+    pub fn write(&self) {
+        let path = (1..)
+            .map(|attempt| format!("{}/{}_{}.toml", RECORDING_DIR, self.level_id, attempt))
+            .find(|path| !std::path::Path::new(path).exists())
+            .expect("there is always a free attempt number");
+        let result = toml::to_string(self)
+            .map_err(|err| err.to_string())
+            .and_then(|contents| {
+                std::fs::create_dir_all(RECORDING_DIR).map_err(|err| err.to_string())?;
+                std::fs::write(&path, contents).map_err(|err| err.to_string())
+            });
+        match result {
+            Ok(()) => info!("Wrote input recording to {}", path),
+            Err(err) => error!("Failed to write input recording {}: {}", path, err),
+        }
+    }
+
+    fn tick_mut(&mut self, tick: u32) -> &mut RecordedTick {
+        if self.ticks.last().is_none_or(|last| last.tick != tick) {
+            self.ticks.push(RecordedTick {
+                tick,
+                movement: Vec::new(),
+                beam: Vec::new(),
+            });
+        }
+        self.ticks.last_mut().unwrap()
+    }
+}
+
+/// [`Resource`] present when `record_inputs` is set. Holds the attempt being recorded, if any.
+#[derive(Resource)]
+pub struct Recorder(pub Option<InputRecording>);
+
+/// [`Resource`] present when `replay_path` is set.
+#[derive(Resource)]
+pub struct Playback {
+    pub recording: InputRecording,
+    state: PlaybackState,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum PlaybackState {
+    /// Waiting for Lyra to be in the recorded level.
+    Waiting,
+    Playing,
+    Finished,
+}
+
+impl Playback {
+    pub fn new(recording: InputRecording) -> Self {
+        Playback {
+            recording,
+            state: PlaybackState::Waiting,
+        }
+    }
+
+    pub fn state(&self) -> &PlaybackState {
+        &self.state
+    }
+
+    fn playing_tick(&self, tick: u32) -> Option<&RecordedTick> {
+        if self.state != PlaybackState::Playing {
+            return None;
+        }
+        let ticks = &self.recording.ticks;
+        let index = ticks
+            .binary_search_by_key(&tick, |recorded| recorded.tick)
+            .ok()?;
+        Some(&ticks[index])
+    }
+}
+
+/// Run condition that is false while a [`Playback`] is feeding inputs to the game.
+pub fn input_is_live(playback: Option<Res<Playback>>) -> bool {
+    playback.is_none_or(|playback| playback.state != PlaybackState::Playing)
+}
+
+/// Starts recording or playing back an attempt, if none is in progress.
+pub fn begin_attempt(
+    mut fixed_tick: ResMut<FixedTick>,
+    recorder: Option<ResMut<Recorder>>,
+    playback: Option<ResMut<Playback>>,
+    lyra: Single<(&mut Position, &mut LinearVelocity), With<Lyra>>,
+    ldtk_level_param: LdtkLevelParam,
+) {
+    let (mut position, mut velocity) = lyra.into_inner();
+    let Some(level) = ldtk_level_param.cur_level() else {
+        return;
+    };
+
+    if let Some(mut playback) = playback {
+        if playback.state == PlaybackState::Waiting
+            && playback.recording.level_iid == level.raw().iid
+        {
+            info!(
+                "Playing back inputs for level {}",
+                playback.recording.level_id
+            );
+            fixed_tick.0 = 0;
+            position.0 = playback.recording.start_position;
+            velocity.0 = playback.recording.start_velocity;
+            playback.state = PlaybackState::Playing;
+        }
+    }
+
+    if let Some(mut recorder) = recorder {
+        if recorder.0.is_none() {
+            fixed_tick.0 = 0;
+            recorder.0 = Some(InputRecording::new(
+                level.raw().iid.clone(),
+                level.raw().level_id().clone(),
+                position.0,
+                velocity.0,
+                Vec::new(),
+            ));
+        }
+    }
+}
+
+/// Ends the current attempt, writing its recording if there is one. A reset also ends playback,
+/// since the rest of the recording belonged to a different attempt.
+pub fn end_attempt(
+    _: On<ResetLevels>,
+    recorder: Option<ResMut<Recorder>>,
+    playback: Option<ResMut<Playback>>,
+) {
+    if let Some(mut recorder) = recorder {
+        if let Some(recording) = recorder.0.take() {
+            if !recording.ticks.is_empty() {
+                recording.write();
+            }
+        }
+    }
+    if let Some(mut playback) = playback {
+        if playback.state == PlaybackState::Playing {
+            info!("Finished playing back inputs");
+            playback.state = PlaybackState::Finished;
+        }
+    }
+}
+
+pub fn advance_fixed_tick(mut fixed_tick: ResMut<FixedTick>) {
+    fixed_tick.0 += 1;
+}
+
+pub fn play_back_movement(
+    fixed_tick: Res<FixedTick>,
+    playback: Option<Res<Playback>>,
+    mut movement_writer: MessageWriter<MovementAction>,
+) {
+    let Some(recorded) = playback
+        .as_ref()
+        .and_then(|playback| playback.playing_tick(fixed_tick.0))
+    else {
+        return;
+    };
+    movement_writer.write_batch(recorded.movement.iter().cloned());
+}
+
+/// Writes the [`BeamAction`]s recorded during the current tick, right before they get processed.
+pub fn play_back_beam_actions(
+    fixed_tick: Res<FixedTick>,
+    playback: Option<ResMut<Playback>>,
+    mut beam_writer: MessageWriter<BeamAction>,
+) {
+    let Some(mut playback) = playback else {
+        return;
+    };
+    if let Some(recorded) = playback.playing_tick(fixed_tick.0) {
+        beam_writer.write_batch(recorded.beam.iter().cloned());
+    }
+
+    let last_tick = playback.recording.ticks.last().map_or(0, |last| last.tick);
+    if playback.state == PlaybackState::Playing && fixed_tick.0 >= last_tick {
+        info!("Finished playing back inputs");
+        playback.state = PlaybackState::Finished;
+    }
+}
+
+pub fn record_movement(
+    fixed_tick: Res<FixedTick>,
+    recorder: Option<ResMut<Recorder>>,
+    mut movement_reader: MessageReader<MovementAction>,
+) {
+    let Some(mut recorder) = recorder else {
+        return;
+    };
+    let Some(recording) = recorder.0.as_mut() else {
+        return;
+    };
+    let actions = movement_reader.read().cloned().collect::<Vec<_>>();
+    if !actions.is_empty() {
+        recording.tick_mut(fixed_tick.0).movement.extend(actions);
+    }
+}
+
+/// Records the [`BeamAction`]s about to be processed during the current tick.
+pub fn record_beam_actions(
+    fixed_tick: Res<FixedTick>,
+    recorder: Option<ResMut<Recorder>>,
+    mut beam_reader: MessageReader<BeamAction>,
+    lyra: Single<(&Transform, &PlayerLightInventory), With<Lyra>>,
+    cursor: Single<&CursorWorldCoords>,
+) {
+    let Some(mut recorder) = recorder else {
+        return;
+    };
+    let Some(recording) = recorder.0.as_mut() else {
+        return;
+    };
+    let (transform, inventory) = lyra.into_inner();
+
+    // a StickAim earlier in the same tick changes the aim of the Shoot after it
+    let mut stick_aim = inventory.stick_aim;
+    for action in beam_reader.read() {
+        match action {
+            BeamAction::StickAim(stick) => stick_aim = *stick,
+            BeamAction::Shoot => {
+                let ray_pos = transform.translation.truncate();
+                let aim = stick_aim.unwrap_or(cursor.pos - ray_pos);
+                recording
+                    .tick_mut(fixed_tick.0)
+                    .beam
+                    .push(BeamAction::StickAim(Some(aim)));
+            }
+            _ => {}
+        }
+        recording.tick_mut(fixed_tick.0).beam.push(action.clone());
+    }
+}
@@ -2,7 +2,11 @@ use crate::{
     asset::LoadResource,
     camera::{MainCamera, HIGHRES_LAYER},
     config::Config,
-    shared::{GameState, ResetLevels},
+    game::LevelSystems,
+    ldtk::{
+        validation::validate_project, LdtkLevelParam, LdtkParam, LdtkProblems, LdtkProjectReloaded,
+    },
+    shared::{GameState, ResetLevels, UiState},
 };
 use avian2d::prelude::RigidBody;
 use bevy::prelude::*;
@@ -49,6 +53,13 @@ impl Plugin for LevelSetupPlugin {
         app.add_systems(OnEnter(GameState::InGame), spawn_background);
         app.add_systems(OnExit(GameState::InGame), despawn_level);
         app.add_systems(OnExit(GameState::InGame), despawn_background);
+        app.add_systems(
+            PreUpdate,
+            reload_ldtk_project
+                .before(LevelSystems::Processing)
+                .run_if(resource_exists::<LevelAssets>),
+        );
+        app.add_observer(respawn_level_in_place);
     }
 }
 
@@ -100,3 +111,75 @@ pub fn despawn_background(
 
     commands.entity(*level_bg).despawn();
 }
+
+/// [`System`] that notices the LDtk project being reloaded from disk and validates it again. New
+/// problems send the game back to the loading screen to list them, and fixing them lets it continue.
+pub fn reload_ldtk_project(
+    mut commands: Commands,
+    mut ev_asset: MessageReader<AssetEvent<LdtkProject>>,
+    level_assets: Res<LevelAssets>,
+    ldtk_param: LdtkParam,
+    mut ldtk_level_param: LdtkLevelParam,
+    ldtk_problems: Option<Res<LdtkProblems>>,
+    game_state: Res<State<GameState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut next_ui_state: ResMut<NextState<UiState>>,
+) {
+    let modified = ev_asset
+        .read()
+        .any(|ev| ev.is_modified(level_assets.ldtk_file.id()));
+    if !modified {
+        return;
+    }
+    let Some(project) = ldtk_param.project() else {
+        return;
+    };
+    info!("Reloading LDtk project!");
+
+    let problems = validate_project(project.json_data());
+    for problem in problems.iter() {
+        error!("{}", problem);
+    }
+    if !problems.is_empty() {
+        // the level would be respawned from a project it can't be built from, so show the problems
+        // on the loading screen instead, which despawns the level when leaving the game
+        if ldtk_level_param.cur_level().is_none() {
+            // NOTE: despawning the level resets it, which needs the current level to exist
+            *ldtk_level_param.level_selection = LevelSelection::index(0);
+        }
+        commands.insert_resource(LdtkProblems(problems));
+        if *game_state.get() != GameState::Loading {
+            next_game_state.set(GameState::Loading);
+        }
+        return;
+    }
+    if ldtk_problems.is_some() {
+        commands.remove_resource::<LdtkProblems>();
+        next_game_state.set(GameState::Ui);
+        next_ui_state.set(UiState::StartMenu);
+        return;
+    }
+    commands.trigger(LdtkProjectReloaded);
+}
+
+/// Resets the current level, since things like light segments refer to the entities of the old
+/// one. Goes back to level select if the current level was removed from the project.
+pub fn respawn_level_in_place(
+    _: On<LdtkProjectReloaded>,
+    mut commands: Commands,
+    game_state: Res<State<GameState>>,
+    mut ldtk_level_param: LdtkLevelParam,
+    mut next_game_state: ResMut<NextState<GameState>>,
+) {
+    if *game_state.get() != GameState::InGame {
+        return;
+    }
+    if ldtk_level_param.cur_level().is_none() {
+        warn!("Current level was removed from the LDtk project, going back to level select");
+        // NOTE: despawning the level resets it, which needs the current level to exist
+        *ldtk_level_param.level_selection = LevelSelection::index(0);
+        next_game_state.set(GameState::Ui);
+        return;
+    }
+    commands.trigger(ResetLevels);
+}
@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::ldtk::ParentLevelParam;
+
+pub struct LevelSignalPlugin;
+
+impl Plugin for LevelSignalPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(dispatch_level_signal);
+    }
+}
+
+/// Returns true if `id` refers to a signal. Signal ids start at 1, so the `0` and negative
+/// defaults used by LDtk fields mean "not connected to anything".
+pub fn is_signal_id(id: i32) -> bool {
+    id > 0
+}
+
+/// Event broadcast by signal emitters (such as [`LightSensor`](super::defs::sensor::LightSensor)s)
+/// whenever their state changes. Receivers subscribe to an id with a [`SignalListener`], and only
+/// hear the signals of the level they are in, since ids are only unique per level.
+#[derive(Event, Debug, Clone)]
+pub struct LevelSignal {
+    pub level: LevelIid,
+    pub id: i32,
+    pub active: bool,
+}
+
+/// [`Component`] that subscribes an entity to the [`LevelSignal`]s with a matching id. The entity
+/// should observe [`SignalReceived`] to react to them.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct SignalListener(pub i32);
+
+impl From<&EntityInstance> for SignalListener {
+    /// Subscribes an LDtk entity to the id in its optional `signal_id` field. Entities without one
+    /// never receive anything.
+    fn from(entity_instance: &EntityInstance) -> Self {
+        SignalListener(
+            entity_instance
+                .get_int_field("signal_id")
+                .copied()
+                .unwrap_or(0),
+        )
+    }
+}
+
+/// [`EntityEvent`] sent to every [`SignalListener`] subscribed to a [`LevelSignal`].
+#[derive(EntityEvent, Debug)]
+pub struct SignalReceived {
+    pub entity: Entity,
+    pub active: bool,
+}
+
+pub fn dispatch_level_signal(
+    event: On<LevelSignal>,
+    mut commands: Commands,
+    q_listeners: Query<(Entity, &SignalListener)>,
+    parent_level: ParentLevelParam,
+) {
+    for (entity, listener) in q_listeners.iter() {
+        if listener.0 != event.id || parent_level.level_iid(entity) != Some(&event.level) {
+            continue;
+        }
+        commands.trigger(SignalReceived {
+            entity,
+            active: event.active,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Resource, Default)]
+    struct Received(Vec<Entity>);
+
+    #[test]
+    fn signals_stay_in_their_level() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, LevelSignalPlugin));
+        app.init_resource::<Received>();
+        app.add_observer(
+            |event: On<SignalReceived>, mut received: ResMut<Received>| {
+                received.0.push(event.entity);
+            },
+        );
+        let world = app.world_mut();
+        let level = world.spawn(LevelIid::new("level")).id();
+        let neighbour = world.spawn(LevelIid::new("neighbour")).id();
+        // listeners are nested in an entity layer, like the ones spawned by bevy_ecs_ldtk
+        let layer = world.spawn(ChildOf(level)).id();
+        let listener = world.spawn((SignalListener(1), ChildOf(layer))).id();
+        world.spawn((SignalListener(1), ChildOf(neighbour)));
+        world.spawn((SignalListener(2), ChildOf(level)));
+
+        world.trigger(LevelSignal {
+            level: LevelIid::new("level"),
+            id: 1,
+            active: true,
+        });
+        world.flush();
+        assert_eq!(world.resource::<Received>().0, vec![listener]);
+    }
+}
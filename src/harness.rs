@@ -0,0 +1,456 @@
+use std::time::Duration;
+
+use avian2d::prelude::*;
+use bevy::{
+    log::LogPlugin,
+    prelude::*,
+    render::{
+        settings::{RenderCreation, WgpuSettings},
+        RenderPlugin,
+    },
+    time::TimeUpdateStrategy,
+    window::ExitCondition,
+    winit::WinitPlugin,
+};
+use bevy_ecs_ldtk::{assets::LdtkProject, EntityIid, LevelIid, LevelSelection};
+
+use crate::{
+    asset::{AssetLoadPlugin, ResourceHandles},
+    config::{Config, LevelConfig},
+    game::{
+        defs::sensor::LightSensor,
+        level_completion::LevelCompleted,
+        light::LightColor,
+        lyra::{beam::BeamAction, controller::MovementAction, kill::KillPlayer, Lyra},
+        recording::{InputRecording, Playback, RecordedTick, Recorder},
+        setup::LevelAssets,
+    },
+    input::InputMapPlugin,
+    ldtk::LevelExt,
+    save::SaveData,
+    shared::{GameState, PlayState},
+};
+
+/// Updates to wait for assets or the level to load before giving up.
+const LOAD_TIMEOUT: u32 = 10_000;
+
+/// Default length of a single [`App::update`]. Matches the default [`Time<Fixed>`] timestep, so
+/// that every update runs exactly one [`FixedUpdate`] step.
+const TICK: Duration = Duration::from_nanos(15_625_000);
+
+/// [`Resource`] collecting what happened during a [`LevelHarness`] run.
+#[derive(Resource, Default, Debug)]
+struct HarnessLog {
+    completed: Vec<LevelIid>,
+    kills: u32,
+    fixed_ticks: u32,
+}
+
+fn project(world: &World) -> &LdtkProject {
+    world
+        .resource::<Assets<LdtkProject>>()
+        .get(&world.resource::<LevelAssets>().ldtk_file)
+        .expect("LdtkProject should be loaded with the LevelAssets")
+}
+
+fn update_until(app: &mut App, what: &str, condition: impl Fn(&mut World) -> bool) {
+    for _ in 0..LOAD_TIMEOUT {
+        app.update();
+        if condition(app.world_mut()) {
+            return;
+        }
+        // assets are loaded on other threads
+        std::thread::sleep(Duration::from_micros(100));
+    }
+    panic!("Timed out waiting for {}", what);
+}
+
+/// A headless game, started in a single level.
+pub struct LevelHarness {
+    app: App,
+    level_iid: LevelIid,
+}
+
+impl LevelHarness {
+    /// Loads the level with the given `LevelId` from the main `levels/lightborne.ldtk` project.
+    pub fn new(level_id: &str) -> Self {
+        Self::with_project(&Config::default().level_config.level_path, level_id)
+    }
+
+    /// Loads the level with the given `LevelId` from the `.ldtk` file at `ldtk_path`, relative to
+    /// the assets directory.
+    pub fn with_project(ldtk_path: &str, level_id: &str) -> Self {
+        let mut app = App::new();
+        // NOTE: MinimalPlugins doesn't register the asset types the game spawns, so the window and
+        // every wgpu backend are turned off instead, and the render sub-app never gets created
+        app.add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: None,
+                    exit_condition: ExitCondition::DontExit,
+                    close_when_requested: false,
+                    ..default()
+                })
+                .set(RenderPlugin {
+                    render_creation: RenderCreation::Automatic(WgpuSettings {
+                        backends: None,
+                        ..default()
+                    }),
+                    ..default()
+                })
+                .disable::<WinitPlugin>()
+                // several harnesses run in the same test process, and only one can own the logger
+                .disable::<LogPlugin>(),
+        );
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(TICK));
+
+        app.add_plugins(AssetLoadPlugin);
+        app.insert_resource(Config {
+            level_config: LevelConfig {
+                level_path: ldtk_path.into(),
+            },
+            ..default()
+        });
+        // a fresh save, and no SavePlugin, so tests never touch the player's save file
+        app.insert_resource(SaveData::default());
+        app.add_plugins(InputMapPlugin);
+        crate::add_game_plugins(&mut app);
+
+        app.init_resource::<HarnessLog>();
+        app.add_observer(|event: On<LevelCompleted>, mut log: ResMut<HarnessLog>| {
+            log.completed.push(event.level_iid.clone());
+        });
+        app.add_observer(|_: On<KillPlayer>, mut log: ResMut<HarnessLog>| {
+            log.kills += 1;
+        });
+        app.add_systems(FixedUpdate, |mut log: ResMut<HarnessLog>| {
+            log.fixed_ticks += 1;
+        });
+
+        update_until(&mut app, "assets to load", |world| {
+            world.resource::<ResourceHandles>().is_all_done()
+                && world.get_resource::<LevelAssets>().is_some()
+        });
+
+        let level_iid = project(app.world())
+            .json_data()
+            .levels
+            .iter()
+            .find(|level| level.level_id() == level_id)
+            .map(|level| LevelIid::new(level.iid.clone()))
+            .unwrap_or_else(|| panic!("No level with LevelId {} in the project", level_id));
+        app.insert_resource(LevelSelection::Iid(level_iid.clone()));
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::InGame);
+        update_until(&mut app, "the level to spawn", |world| {
+            world
+                .get_resource::<State<PlayState>>()
+                .is_some_and(|state| *state.get() == PlayState::Playing)
+                && world
+                    .query_filtered::<(), With<Lyra>>()
+                    .iter(world)
+                    .next()
+                    .is_some()
+        });
+
+        LevelHarness { app, level_iid }
+    }
+
+    /// Plays `script` from where Lyra currently is. The rest of the script is dropped if Lyra dies,
+    /// since the level gets reset.
+    pub fn play(&mut self, script: Script) {
+        let (position, velocity) = self.lyra_state();
+        let last_tick = script.tick;
+        let recording = InputRecording::new(
+            self.level_iid.get().clone(),
+            self.level_id(),
+            position,
+            velocity,
+            script.ticks,
+        );
+        self.replay(recording, last_tick);
+    }
+
+    /// Plays `recording` from its start position, for `last_tick + 1` fixed timesteps.
+    pub fn replay(&mut self, recording: InputRecording, last_tick: u32) {
+        self.app.insert_resource(Playback::new(recording));
+        self.wait(last_tick + 1);
+        self.app.world_mut().remove_resource::<Playback>();
+    }
+
+    /// Plays `script` like [`LevelHarness::play`], and returns the inputs the [`Recorder`] saw.
+    pub fn record(&mut self, script: Script) -> InputRecording {
+        self.app.insert_resource(Recorder(None));
+        self.play(script);
+        self.app
+            .world_mut()
+            .remove_resource::<Recorder>()
+            .and_then(|recorder| recorder.0)
+            .expect("the recorder should have started an attempt")
+    }
+
+    /// Runs the game without any input for `ticks` fixed timesteps.
+    pub fn wait(&mut self, ticks: u32) {
+        let fixed_ticks = |app: &App| app.world().resource::<HarnessLog>().fixed_ticks;
+        let target = fixed_ticks(&self.app) + ticks;
+        while fixed_ticks(&self.app) < target {
+            self.app.update();
+        }
+    }
+
+    /// Sets the length of each [`App::update`] from now on. Frames that aren't a fraction of the
+    /// fixed timestep can run past the end of a [`LevelHarness::wait`].
+    pub fn set_frame_time(&mut self, frame_time: Duration) {
+        self.app
+            .insert_resource(TimeUpdateStrategy::ManualDuration(frame_time));
+    }
+
+    fn level_id(&self) -> String {
+        project(self.app.world())
+            .json_data()
+            .levels
+            .iter()
+            .find(|level| &level.iid == self.level_iid.get())
+            .map(|level| level.level_id().clone())
+            .unwrap_or_default()
+    }
+
+    fn lyra_state(&mut self) -> (Vec2, Vec2) {
+        let world = self.app.world_mut();
+        let (position, velocity) = world
+            .query_filtered::<(&Position, &LinearVelocity), With<Lyra>>()
+            .single(world)
+            .expect("Lyra should exist while the level is loaded");
+        (position.0, velocity.0)
+    }
+
+    pub fn lyra_position(&mut self) -> Vec2 {
+        self.lyra_state().0
+    }
+
+    /// Whether Lyra reached the end marker of the level the harness started in.
+    pub fn level_completed(&self) -> bool {
+        self.app
+            .world()
+            .resource::<HarnessLog>()
+            .completed
+            .contains(&self.level_iid)
+    }
+
+    /// Number of times Lyra died since the harness started.
+    pub fn kills(&self) -> u32 {
+        self.app.world().resource::<HarnessLog>().kills
+    }
+
+    /// Whether the [`LightSensor`] with the given LDtk entity iid is currently active.
+    pub fn sensor_active(&mut self, entity_iid: &str) -> bool {
+        let world = self.app.world_mut();
+        world
+            .query::<(&EntityIid, &LightSensor)>()
+            .iter(world)
+            .find(|(iid, _)| iid.get() == entity_iid)
+            .map(|(_, sensor)| sensor.is_active)
+            .unwrap_or_else(|| panic!("No LightSensor with iid {}", entity_iid))
+    }
+}
+
+/// Inputs to feed a [`LevelHarness`], built one fixed timestep at a time. Actions that don't take
+/// a number of ticks happen on the current tick, so `.jump().walk(1.0, 30)` jumps while walking.
+#[derive(Default)]
+pub struct Script {
+    ticks: Vec<RecordedTick>,
+    tick: u32,
+}
+
+impl Script {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn at(&mut self, tick: u32) -> &mut RecordedTick {
+        let index = match self
+            .ticks
+            .binary_search_by_key(&tick, |recorded| recorded.tick)
+        {
+            Ok(index) => index,
+            Err(index) => {
+                self.ticks.insert(
+                    index,
+                    RecordedTick {
+                        tick,
+                        movement: Vec::new(),
+                        beam: Vec::new(),
+                    },
+                );
+                index
+            }
+        };
+        &mut self.ticks[index]
+    }
+
+    /// Holds a direction, -1 for left and 1 for right, for `ticks` fixed timesteps.
+    pub fn walk(mut self, direction: f32, ticks: u32) -> Self {
+        for tick in self.tick..self.tick + ticks {
+            self.at(tick).movement.push(MovementAction::Move(direction));
+        }
+        self.tick += ticks;
+        self
+    }
+
+    /// Does nothing for `ticks` fixed timesteps.
+    pub fn wait(mut self, ticks: u32) -> Self {
+        self.tick += ticks;
+        self
+    }
+
+    /// Starts a full height jump.
+    pub fn jump(mut self) -> Self {
+        let tick = self.tick;
+        self.at(tick).movement.push(MovementAction::Jump);
+        self
+    }
+
+    /// Jumps and releases the jump after `ticks`, while the script keeps going.
+    pub fn short_jump(mut self, ticks: u32) -> Self {
+        let tick = self.tick;
+        self.at(tick).movement.push(MovementAction::Jump);
+        self.at(tick + ticks).movement.push(MovementAction::JumpCut);
+        self
+    }
+
+    /// Shoots a beam of `color` towards `aim`, relative to Lyra.
+    pub fn shoot(mut self, color: LightColor, aim: Vec2) -> Self {
+        let tick = self.tick;
+        self.at(tick).beam.extend([
+            BeamAction::SwitchColor(Some(color)),
+            BeamAction::StickAim(Some(aim)),
+            BeamAction::Preview,
+            BeamAction::Shoot,
+            BeamAction::StickAim(None),
+        ]);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standing_still_is_safe() {
+        let mut harness = LevelHarness::new("1A");
+        let start = harness.lyra_position();
+        harness.wait(128);
+        assert_eq!(harness.kills(), 0);
+        assert!(!harness.level_completed());
+        assert!((harness.lyra_position() - start).length() < 1.0);
+    }
+
+    #[test]
+    fn walking_moves_lyra() {
+        let mut harness = LevelHarness::new("1A");
+        let start = harness.lyra_position();
+        harness.play(Script::new().walk(1.0, 32));
+        assert!(harness.lyra_position().x > start.x);
+    }
+
+    #[test]
+    fn short_jumps_are_lower() {
+        let mut full = LevelHarness::new("3#");
+        let start = full.lyra_position();
+        full.play(Script::new().jump().wait(12));
+
+        let mut short = LevelHarness::new("3#");
+        short.play(Script::new().short_jump(4).wait(12));
+
+        assert!(short.lyra_position().y > start.y);
+        assert!(short.lyra_position().y < full.lyra_position().y);
+    }
+
+    #[test]
+    fn jumping_the_bump_reaches_the_end() {
+        let mut harness = LevelHarness::new("3#");
+        harness.play(Script::new().walk(1.0, 74).jump().walk(1.0, 160));
+        assert_eq!(harness.kills(), 0);
+        assert!(harness.level_completed());
+    }
+
+    #[test]
+    fn shooting_lights_the_sensor() {
+        const SENSOR: &str = "3bc08660-e920-11ef-bce6-8173365acfd2";
+
+        let mut harness = LevelHarness::new("2E");
+        harness.wait(16);
+        assert!(!harness.sensor_active(SENSOR));
+        // straight across the room, just under the wall above the sensor
+        harness.play(Script::new().shoot(LightColor::Green, Vec2::new(-24.0, -1.0)));
+        // the crystals it toggles cut the beam off again, so it doesn't stay active for long
+        let lit = (0..200).any(|_| {
+            harness.wait(1);
+            harness.sensor_active(SENSOR)
+        });
+        assert!(lit);
+    }
+
+    #[test]
+    fn replaying_a_shot_aims_the_same_way() {
+        const SENSOR: &str = "3bc08660-e920-11ef-bce6-8173365acfd2";
+        // the stick is moved on the same tick as the shot
+        let script = Script::new()
+            .shoot(LightColor::Green, Vec2::new(-24.0, -1.0))
+            .wait(1);
+
+        let mut recorded = LevelHarness::new("2E");
+        recorded.wait(16);
+        let recording = recorded.record(script);
+        let last_tick = recording.ticks.last().map_or(0, |last| last.tick);
+
+        let mut replayed = LevelHarness::new("2E");
+        replayed.wait(16);
+        replayed.replay(recording, last_tick);
+        let lit = (0..200).any(|_| {
+            replayed.wait(1);
+            replayed.sensor_active(SENSOR)
+        });
+        assert!(lit);
+    }
+
+    #[test]
+    fn replays_do_not_depend_on_the_frame_rate() {
+        const SENSOR: &str = "3bc08660-e920-11ef-bce6-8173365acfd2";
+
+        let mut recorded = LevelHarness::new("2E");
+        recorded.wait(16);
+        let recording = recorded.record(
+            Script::new()
+                .shoot(LightColor::Green, Vec2::new(-24.0, -1.0))
+                .wait(1),
+        );
+        let last_tick = recording.ticks.last().map_or(0, |last| last.tick);
+
+        // whether the sensor is active on each tick after the shot
+        let replay = |frame_time: Duration| {
+            let mut harness = LevelHarness::new("2E");
+            harness.set_frame_time(frame_time);
+            harness.wait(16);
+            harness.replay(recording.clone(), last_tick);
+            (0..200)
+                .map(|_| {
+                    harness.wait(1);
+                    harness.sensor_active(SENSOR)
+                })
+                .collect::<Vec<_>>()
+        };
+        let at_tick_rate = replay(TICK);
+        assert!(at_tick_rate.contains(&true));
+        assert_eq!(replay(TICK / 2), at_tick_rate);
+    }
+
+    #[test]
+    fn falling_onto_spikes_kills_lyra() {
+        let mut harness = LevelHarness::new("2D");
+        harness.play(Script::new().walk(1.0, 30).wait(120));
+        assert!(harness.kills() > 0);
+    }
+}
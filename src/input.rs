@@ -0,0 +1,503 @@
+use std::{collections::BTreeMap, fmt};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use enum_map::{enum_map, Enum, EnumMap};
+use serde::{
+    de::{value::StrDeserializer, IntoDeserializer},
+    Deserialize, Serialize,
+};
+
+use crate::{
+    config::{Config, ControlsConfig},
+    save::SaveData,
+};
+
+pub struct InputMapPlugin;
+
+impl Plugin for InputMapPlugin {
+    fn build(&self, app: &mut App) {
+        let mut input_map =
+            InputMap::from_config(&app.world().resource::<Config>().controls_config);
+        input_map.apply(&app.world().resource::<SaveData>().settings.bindings);
+        app.insert_resource(input_map);
+    }
+}
+
+/// Every action the player can bind an input to.
+#[derive(
+    Enum, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize,
+)]
+pub enum InputAction {
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Crouch,
+    Snap,
+    Aim,
+    CancelAim,
+    Restart,
+    /// Takes back the last light beam shot.
+    Rewind,
+    Pause,
+    /// Advances dialogue.
+    Confirm,
+    NextColor,
+    PrevColor,
+    Green,
+    Purple,
+    White,
+    Blue,
+    Black,
+}
+
+impl InputAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            InputAction::MoveLeft => "Move Left",
+            InputAction::MoveRight => "Move Right",
+            InputAction::Jump => "Jump",
+            InputAction::Crouch => "Sneak",
+            InputAction::Snap => "Snap Angles",
+            InputAction::Aim => "Aim/Shoot Light",
+            InputAction::CancelAim => "Cancel Shoot Light",
+            InputAction::Restart => "Restart",
+            InputAction::Rewind => "Undo Shot",
+            InputAction::Pause => "Pause",
+            InputAction::Confirm => "Continue Dialogue",
+            InputAction::NextColor => "Next Light",
+            InputAction::PrevColor => "Previous Light",
+            InputAction::Green => "Green Light",
+            InputAction::Purple => "Purple Light",
+            InputAction::White => "White Light",
+            InputAction::Blue => "Blue Light",
+            InputAction::Black => "Black Light",
+        }
+    }
+}
+
+/// Stick deflection under which gamepad sticks are considered centered.
+pub const STICK_DEADZONE: f32 = 0.25;
+
+/// Prefix of the names of [`Binding::Mouse`] buttons, e.g. `"Mouse:Left"`.
+const MOUSE_PREFIX: &str = "Mouse:";
+/// Prefix of the names of [`Binding::Gamepad`] buttons, e.g. `"Pad:South"`.
+const GAMEPAD_PREFIX: &str = "Pad:";
+
+/// A single physical input. (De)serializes from the name of a [`KeyCode`] (`"KeyW"`), or of a
+/// [`MouseButton`] (`"Mouse:Left"`) or [`GamepadButton`] (`"Pad:South"`) with the prefix of their
+/// device, since some names like `Select` exist on both keyboards and gamepads.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+}
+
+impl From<Binding> for String {
+    fn from(binding: Binding) -> Self {
+        // the Debug names of the buttons are the names of their variants
+        match binding {
+            Binding::Key(key) => format!("{:?}", key),
+            Binding::Mouse(button) => format!("{}{:?}", MOUSE_PREFIX, button),
+            Binding::Gamepad(button) => format!("{}{:?}", GAMEPAD_PREFIX, button),
+        }
+    }
+}
+
+impl TryFrom<String> for Binding {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        fn parse<'de, T: Deserialize<'de>>(name: &'de str) -> Option<T> {
+            let deserializer: StrDeserializer<'de, serde::de::value::Error> =
+                name.into_deserializer();
+            T::deserialize(deserializer).ok()
+        }
+
+        let binding = if let Some(button) = name.strip_prefix(MOUSE_PREFIX) {
+            parse(button).map(Binding::Mouse)
+        } else if let Some(button) = name.strip_prefix(GAMEPAD_PREFIX) {
+            parse(button).map(Binding::Gamepad)
+        } else {
+            parse(&name).map(Binding::Key)
+        };
+        binding.ok_or_else(|| format!("{} is not a key, mouse or gamepad button", name))
+    }
+}
+
+impl Binding {
+    /// Whether this is a gamepad button, as opposed to a key or mouse button. Rebinding an action
+    /// only replaces its bindings on the same side, so a keyboard player doesn't unbind the gamepad.
+    pub fn is_gamepad(self) -> bool {
+        matches!(self, Binding::Gamepad(_))
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key(key) => {
+                let name = format!("{:?}", key);
+                let name = name
+                    .strip_prefix("Key")
+                    .or_else(|| name.strip_prefix("Digit"))
+                    .unwrap_or(&name);
+                write!(f, "{}", name)
+            }
+            Binding::Mouse(MouseButton::Left) => write!(f, "Left Click"),
+            Binding::Mouse(MouseButton::Right) => write!(f, "Right Click"),
+            Binding::Mouse(MouseButton::Middle) => write!(f, "Middle Click"),
+            Binding::Mouse(button) => write!(f, "Mouse {:?}", button),
+            Binding::Gamepad(button) => write!(f, "Pad {:?}", button),
+        }
+    }
+}
+
+/// Either a single [`Binding`] or a list of them, so that config files can write
+/// `key_jump = "Space"` as well as `key_jump = ["Space", "KeyW"]`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Bindings {
+    One(Binding),
+    Many(Vec<Binding>),
+}
+
+impl From<Bindings> for Vec<Binding> {
+    fn from(bindings: Bindings) -> Self {
+        match bindings {
+            Bindings::One(binding) => vec![binding],
+            Bindings::Many(bindings) => bindings,
+        }
+    }
+}
+
+/// [`Resource`] mapping every [`InputAction`] to the inputs that trigger it. Built from the
+/// defaults, then the `[controls_config]` of `Lightborne.toml`, then the bindings changed in the
+/// settings menu, which are kept in the save file.
+#[derive(Resource, Clone, Debug)]
+pub struct InputMap(pub EnumMap<InputAction, Vec<Binding>>);
+
+impl Default for InputMap {
+    fn default() -> Self {
+        use Binding::*;
+        InputMap(enum_map! {
+            InputAction::MoveLeft => vec![
+                Key(KeyCode::KeyA),
+                Key(KeyCode::ArrowLeft),
+                Gamepad(GamepadButton::DPadLeft),
+            ],
+            InputAction::MoveRight => vec![
+                Key(KeyCode::KeyD),
+                Key(KeyCode::ArrowRight),
+                Gamepad(GamepadButton::DPadRight),
+            ],
+            InputAction::Jump => vec![Key(KeyCode::Space), Gamepad(GamepadButton::South)],
+            InputAction::Crouch => vec![Key(KeyCode::ShiftLeft), Gamepad(GamepadButton::West)],
+            InputAction::Snap => vec![
+                Key(KeyCode::ShiftLeft),
+                Gamepad(GamepadButton::LeftTrigger2),
+            ],
+            InputAction::Aim => vec![
+                Mouse(MouseButton::Left),
+                Gamepad(GamepadButton::RightTrigger2),
+            ],
+            InputAction::CancelAim => vec![
+                Mouse(MouseButton::Right),
+                Gamepad(GamepadButton::East),
+            ],
+            InputAction::Restart => vec![Key(KeyCode::KeyR), Gamepad(GamepadButton::Select)],
+            InputAction::Rewind => vec![Key(KeyCode::KeyZ), Gamepad(GamepadButton::North)],
+            InputAction::Pause => vec![Key(KeyCode::Escape), Gamepad(GamepadButton::Start)],
+            InputAction::Confirm => vec![
+                Key(KeyCode::Space),
+                Key(KeyCode::Enter),
+                Mouse(MouseButton::Left),
+                Gamepad(GamepadButton::South),
+            ],
+            InputAction::NextColor => vec![Gamepad(GamepadButton::RightTrigger)],
+            InputAction::PrevColor => vec![Gamepad(GamepadButton::LeftTrigger)],
+            InputAction::Green => vec![Key(KeyCode::Digit1)],
+            InputAction::Purple => vec![Key(KeyCode::Digit2)],
+            InputAction::White => vec![Key(KeyCode::Digit3)],
+            InputAction::Blue => vec![Key(KeyCode::Digit4)],
+            InputAction::Black => vec![Key(KeyCode::Digit5)],
+        })
+    }
+}
+
+impl InputMap {
+    /// The default bindings, overridden by the ones in `[controls_config]`. Each key only replaces
+    /// the bindings from the same side as the ones it lists (see [`Binding::is_gamepad`]), so that
+    /// `key_jump = "Space"` keeps jumping bound on gamepads.
+    pub fn from_config(config: &ControlsConfig) -> Self {
+        let mut input_map = InputMap::default();
+        for (action, bindings) in config.bindings() {
+            for gamepad in [false, true] {
+                let side = bindings
+                    .iter()
+                    .copied()
+                    .filter(|binding| binding.is_gamepad() == gamepad)
+                    .collect::<Vec<_>>();
+                if !side.is_empty() {
+                    input_map.replace_side(action, gamepad, side);
+                }
+            }
+        }
+        input_map
+    }
+
+    /// Replaces the bindings of every action present in `overrides`.
+    pub fn apply(&mut self, overrides: &BTreeMap<InputAction, Vec<Binding>>) {
+        for (action, bindings) in overrides {
+            self.0[*action] = bindings.clone();
+        }
+    }
+
+    /// Binds `binding` to `action`, in place of the bindings of `action` from the same side (see
+    /// [`Binding::is_gamepad`]).
+    pub fn rebind(&mut self, action: InputAction, binding: Binding) {
+        self.replace_side(action, binding.is_gamepad(), [binding]);
+    }
+
+    /// Replaces the gamepad bindings of `action`, or its key and mouse bindings, where the first of
+    /// them was.
+    fn replace_side(
+        &mut self,
+        action: InputAction,
+        gamepad: bool,
+        replacements: impl IntoIterator<Item = Binding>,
+    ) {
+        let bindings = &mut self.0[action];
+        let index = bindings
+            .iter()
+            .position(|b| b.is_gamepad() == gamepad)
+            .unwrap_or(bindings.len());
+        bindings.retain(|b| b.is_gamepad() != gamepad);
+        let index = index.min(bindings.len());
+        bindings.splice(index..index, replacements);
+    }
+
+    /// Human readable list of the bindings of an action, e.g. "A / ArrowLeft".
+    pub fn describe(&self, action: InputAction) -> String {
+        let names = self.0[action]
+            .iter()
+            .map(Binding::to_string)
+            .collect::<Vec<_>>();
+        if names.is_empty() {
+            "Unbound".to_string()
+        } else {
+            names.join(" / ")
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum ButtonCheck {
+    Pressed,
+    JustPressed,
+    JustReleased,
+}
+
+impl ButtonCheck {
+    fn test<T: Copy + Eq + std::hash::Hash + Send + Sync + 'static>(
+        self,
+        input: &ButtonInput<T>,
+        button: T,
+    ) -> bool {
+        match self {
+            ButtonCheck::Pressed => input.pressed(button),
+            ButtonCheck::JustPressed => input.just_pressed(button),
+            ButtonCheck::JustReleased => input.just_released(button),
+        }
+    }
+}
+
+/// [`SystemParam`] to read the state of [`InputAction`]s, regardless of what they are bound to.
+#[derive(SystemParam)]
+pub struct ActionInput<'w, 's> {
+    pub input_map: Res<'w, InputMap>,
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    mouse: Res<'w, ButtonInput<MouseButton>>,
+    gamepads: Query<'w, 's, &'static Gamepad>,
+}
+
+impl ActionInput<'_, '_> {
+    fn any(&self, action: InputAction, check: ButtonCheck) -> bool {
+        self.input_map.0[action]
+            .iter()
+            .any(|binding| match binding {
+                Binding::Key(code) => check.test(&self.keys, *code),
+                Binding::Mouse(button) => check.test(&self.mouse, *button),
+                Binding::Gamepad(button) => self
+                    .gamepads
+                    .iter()
+                    .any(|gamepad| check.test(gamepad.digital(), *button)),
+            })
+    }
+
+    /// Position of the left stick of the first gamepad outside of the [`STICK_DEADZONE`].
+    pub fn left_stick(&self) -> Option<Vec2> {
+        self.gamepads
+            .iter()
+            .map(Gamepad::left_stick)
+            .find(|stick| stick.length() > STICK_DEADZONE)
+    }
+
+    /// Position of the right stick of the first gamepad outside of the [`STICK_DEADZONE`].
+    pub fn right_stick(&self) -> Option<Vec2> {
+        self.gamepads
+            .iter()
+            .map(Gamepad::right_stick)
+            .find(|stick| stick.length() > STICK_DEADZONE)
+    }
+
+    pub fn pressed(&self, action: InputAction) -> bool {
+        self.any(action, ButtonCheck::Pressed)
+    }
+
+    pub fn just_pressed(&self, action: InputAction) -> bool {
+        self.any(action, ButtonCheck::JustPressed)
+    }
+
+    pub fn just_released(&self, action: InputAction) -> bool {
+        self.any(action, ButtonCheck::JustReleased)
+    }
+}
+
+/// The first key or mouse button pressed this frame, if any. Used when rebinding actions.
+pub fn first_just_pressed<'a>(
+    keys: &ButtonInput<KeyCode>,
+    mouse: &ButtonInput<MouseButton>,
+    gamepads: impl IntoIterator<Item = &'a Gamepad>,
+) -> Option<Binding> {
+    keys.get_just_pressed()
+        .next()
+        .map(|key| Binding::Key(*key))
+        .or_else(|| {
+            mouse
+                .get_just_pressed()
+                .next()
+                .map(|button| Binding::Mouse(*button))
+        })
+        .or_else(|| {
+            gamepads
+                .into_iter()
+                .find_map(|gamepad| gamepad.get_just_pressed().next())
+                .map(|button| Binding::Gamepad(*button))
+        })
+}
+
+/// Run condition that is true on the frame any input bound to `action` is pressed.
+pub fn action_just_pressed(action: InputAction) -> impl FnMut(ActionInput) -> bool + Clone {
+    move |input: ActionInput| input.just_pressed(action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_bindings_round_trip() {
+        for (action, bindings) in InputMap::default().0.iter() {
+            for binding in bindings {
+                let name = String::from(*binding);
+                assert_eq!(
+                    Binding::try_from(name.clone()).as_ref(),
+                    Ok(binding),
+                    "{:?} {}",
+                    action,
+                    name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn example_config_has_the_default_bindings() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/Lightborne_example.toml");
+        let contents = std::fs::read_to_string(path).expect("Lightborne_example.toml should exist");
+        let config: Config = toml::from_str(&contents).expect("should be a valid config");
+        assert_eq!(
+            InputMap::from_config(&config.controls_config).0,
+            InputMap::default().0
+        );
+    }
+
+    #[test]
+    fn config_keys_keep_the_other_device() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/Lightborne_example.toml");
+        let contents = std::fs::read_to_string(path).expect("Lightborne_example.toml should exist");
+        // keys of older example files, which don't bind anything anymore
+        let contents = contents.replace(
+            "[controls_config]",
+            "[controls_config]\nkey_up = \"KeyW\"\nkey_down = \"KeyS\"",
+        );
+        let mut config: Config = toml::from_str(&contents).expect("should be a valid config");
+        // older example files also only listed keys
+        config.controls_config.key_jump = Some(Bindings::One(Binding::Key(KeyCode::Space)));
+
+        let input_map = InputMap::from_config(&config.controls_config);
+        for action in [InputAction::MoveLeft, InputAction::MoveRight] {
+            assert_eq!(input_map.0[action], InputMap::default().0[action]);
+        }
+        assert!(input_map.0[InputAction::MoveLeft].contains(&Binding::Key(KeyCode::ArrowLeft)));
+        assert!(input_map.0[InputAction::MoveRight].contains(&Binding::Key(KeyCode::ArrowRight)));
+        assert_eq!(
+            input_map.0[InputAction::Jump],
+            vec![
+                Binding::Key(KeyCode::Space),
+                Binding::Gamepad(GamepadButton::South)
+            ]
+        );
+        assert_eq!(
+            input_map.0[InputAction::Crouch],
+            InputMap::default().0[InputAction::Crouch]
+        );
+    }
+
+    #[test]
+    fn rebinding_keeps_the_other_device() {
+        let mut input_map = InputMap::default();
+        input_map.rebind(InputAction::MoveLeft, Binding::Key(KeyCode::KeyQ));
+        assert_eq!(
+            input_map.0[InputAction::MoveLeft],
+            vec![
+                Binding::Key(KeyCode::KeyQ),
+                Binding::Gamepad(GamepadButton::DPadLeft)
+            ]
+        );
+        input_map.rebind(
+            InputAction::Aim,
+            Binding::Gamepad(GamepadButton::RightTrigger),
+        );
+        assert_eq!(
+            input_map.0[InputAction::Aim],
+            vec![
+                Binding::Mouse(MouseButton::Left),
+                Binding::Gamepad(GamepadButton::RightTrigger)
+            ]
+        );
+        input_map.rebind(InputAction::Green, Binding::Gamepad(GamepadButton::North));
+        assert_eq!(
+            input_map.0[InputAction::Green],
+            vec![
+                Binding::Key(KeyCode::Digit1),
+                Binding::Gamepad(GamepadButton::North)
+            ]
+        );
+    }
+
+    #[test]
+    fn prefixes_pick_the_device() {
+        assert_eq!(
+            Binding::try_from("Pad:Select".to_string()),
+            Ok(Binding::Gamepad(GamepadButton::Select))
+        );
+        assert_eq!(
+            Binding::try_from("Mouse:Left".to_string()),
+            Ok(Binding::Mouse(MouseButton::Left))
+        );
+        assert!(Binding::try_from("Pad:KeyR".to_string()).is_err());
+    }
+}
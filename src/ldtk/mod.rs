@@ -9,6 +9,19 @@ use enum_map::{enum_map, EnumMap};
 
 use crate::game::{light::LightColor, setup::LevelAssets};
 
+pub mod validation;
+
+/// [`Resource`] inserted when the LDtk project failed validation. The game stays on the loading
+/// screen, which lists the problems.
+#[derive(Resource)]
+pub struct LdtkProblems(pub Vec<validation::LdtkProblem>);
+
+/// [`Event`] triggered when the LDtk project was modified on disk and reloaded, which only happens
+/// with the `dev_mode` feature since it enables asset watching. `bevy_ecs_ldtk` respawns the levels
+/// by itself, observers of this rebuild everything else derived from the project.
+#[derive(Event)]
+pub struct LdtkProjectReloaded;
+
 pub trait LevelExt {
     const START_FLAG_IDENT: &'static str;
     fn start_flag_pos(&self) -> Option<Vec2>;
@@ -18,7 +31,7 @@ pub trait LevelExt {
 }
 
 impl LevelExt for Level {
-    const START_FLAG_IDENT: &'static str = "Start";
+    const START_FLAG_IDENT: &'static str = validation::START_FLAG_IDENT;
 
     fn start_flag_pos(&self) -> Option<Vec2> {
         let layers = self.layer_instances.as_ref().expect("Layers not found! (This is probably because you are using the \"Separate level files\" option.)");
@@ -125,3 +138,23 @@ impl LdtkLevelParam<'_> {
             .map(|level| LevelIid::new(level.raw().iid.as_str()))
     }
 }
+
+/// [`SystemParam`] to find the level that an LDtk entity or tile was spawned in.
+#[derive(SystemParam)]
+pub struct ParentLevelParam<'w, 's> {
+    q_parents: Query<'w, 's, &'static ChildOf>,
+    q_levels: Query<'w, 's, &'static LevelIid>,
+}
+
+impl ParentLevelParam<'_, '_> {
+    /// Returns the [`LevelIid`] of `entity` if it is a level, or else of the closest level above it.
+    pub fn level_iid(&self, entity: Entity) -> Option<&LevelIid> {
+        let mut entity = entity;
+        loop {
+            if let Ok(iid) = self.q_levels.get(entity) {
+                return Some(iid);
+            }
+            entity = self.q_parents.get(entity).ok()?.parent();
+        }
+    }
+}
@@ -0,0 +1,525 @@
+// NOTE: only depends on `bevy_ecs_ldtk`, so that `lightborne-lint` can include this file too.
+
+use std::{collections::HashMap, fmt};
+
+use bevy_ecs_ldtk::{
+    ldtk::{EntityInstance, LdtkJson, Level, Type},
+    prelude::LdtkFields,
+};
+
+/// Values of the `LightColor` enum understood by the game.
+pub const LIGHT_COLORS: &[&str] = &["Green", "Purple", "White", "Blue", "Black"];
+/// Values of the `CrystalColor` enum understood by the game.
+pub const CRYSTAL_COLORS: &[&str] = &["Pink", "Red", "White", "Blue"];
+const DEFAULT_STATES: &[&str] = &["Play", "Pause"];
+const SENSOR_MODES: &[&str] = &["Meter", "Momentary", "Latching", "Timed"];
+const SENSOR_LOGICS: &[&str] = &["Or", "And", "Xor"];
+
+/// Values of the `Terrain` int grid holding the active and inactive crystals of each color.
+pub const CRYSTAL_INT_CELLS: &[(&str, [i32; 2])] = &[
+    ("Pink", [3, 4]),
+    ("Red", [5, 6]),
+    ("White", [7, 8]),
+    ("Blue", [9, 10]),
+];
+
+pub const START_FLAG_IDENT: &str = "Start";
+
+#[derive(Clone, Copy)]
+enum FieldKind {
+    Int,
+    Float,
+    Bool,
+    Point,
+    Points,
+    Bools,
+    Floats,
+    EntityRef,
+    String,
+    Enum(&'static [&'static str]),
+    Enums(&'static [&'static str]),
+}
+
+impl FieldKind {
+    fn name(&self) -> &'static str {
+        match self {
+            FieldKind::Int => "an int field",
+            FieldKind::Float => "a float field",
+            FieldKind::Bool => "a bool field",
+            FieldKind::Point => "a point field",
+            FieldKind::Points => "a point array field",
+            FieldKind::Bools => "a bool array field",
+            FieldKind::Floats => "a float array field",
+            FieldKind::EntityRef => "an entity reference field",
+            FieldKind::String => "a string field",
+            FieldKind::Enum(_) => "an enum field",
+            FieldKind::Enums(_) => "an enum array field",
+        }
+    }
+}
+
+struct FieldRule {
+    name: &'static str,
+    kind: FieldKind,
+    /// Optional fields may be missing or null, but must still hold a known value if they are enums.
+    required: bool,
+}
+
+const fn field(name: &'static str, kind: FieldKind) -> FieldRule {
+    FieldRule {
+        name,
+        kind,
+        required: true,
+    }
+}
+
+const fn optional(name: &'static str, kind: FieldKind) -> FieldRule {
+    FieldRule {
+        name,
+        kind,
+        required: false,
+    }
+}
+
+/// Fields read by the `LdtkEntity`/`From<&EntityInstance>` implementations, by entity identifier.
+const ENTITY_RULES: &[(&str, &[FieldRule])] = &[
+    (
+        "Sensor",
+        &[
+            field("id", FieldKind::Int),
+            field("platform_id", FieldKind::Int),
+            field("activation_time", FieldKind::Int),
+            field("toggle_color", FieldKind::Enum(CRYSTAL_COLORS)),
+            optional("toggle_crystals", FieldKind::Bool),
+            optional("required_colors", FieldKind::Enums(LIGHT_COLORS)),
+            optional("inverted", FieldKind::Bool),
+            optional("mode", FieldKind::Enum(SENSOR_MODES)),
+            optional("hold_time", FieldKind::Int),
+            optional("group", FieldKind::Int),
+            optional("logic", FieldKind::Enum(SENSOR_LOGICS)),
+        ],
+    ),
+    (
+        "CrystalShard",
+        &[field("light_color", FieldKind::Enum(LIGHT_COLORS))],
+    ),
+    (
+        "MovingPlatform",
+        &[
+            field("path", FieldKind::Points),
+            field("path_curve_points", FieldKind::Bools),
+            field("speed", FieldKind::Float),
+            field("does_reverse", FieldKind::Bool),
+            field("does_repeat", FieldKind::Bool),
+            field("can_reactivate", FieldKind::Bool),
+            field("event_id", FieldKind::Int),
+            field("DefaultState", FieldKind::Enum(DEFAULT_STATES)),
+        ],
+    ),
+    (
+        "LightSource",
+        &[
+            field("XOffset", FieldKind::Float),
+            field("YOffset", FieldKind::Float),
+            field("Direction", FieldKind::Point),
+            field("BothDirections", FieldKind::Bool),
+            optional("light_color", FieldKind::Enum(LIGHT_COLORS)),
+            optional("signal_id", FieldKind::Int),
+        ],
+    ),
+    (
+        "ColorFilter",
+        &[
+            field("light_color", FieldKind::Enum(LIGHT_COLORS)),
+            field("recolor", FieldKind::Bool),
+        ],
+    ),
+    (
+        "Portal",
+        &[
+            field("target", FieldKind::EntityRef),
+            field("facing", FieldKind::Float),
+            optional("teleport_lyra", FieldKind::Bool),
+        ],
+    ),
+    (
+        "Prism",
+        &[
+            field("colors", FieldKind::Enums(LIGHT_COLORS)),
+            field("angles", FieldKind::Floats),
+        ],
+    ),
+    (
+        "CrystalSignal",
+        &[
+            field("toggle_color", FieldKind::Enum(CRYSTAL_COLORS)),
+            field("signal_id", FieldKind::Int),
+        ],
+    ),
+    ("Door", &[field("signal_id", FieldKind::Int)]),
+    ("Lantern", &[optional("signal_id", FieldKind::Int)]),
+    ("Lantern2", &[optional("signal_id", FieldKind::Int)]),
+    ("StaticTooltip", &[field("Text", FieldKind::String)]),
+];
+
+/// A single thing wrong with an LDtk project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LdtkProblem {
+    /// `LevelId` of the level the problem is in, or its LDtk identifier if it has no valid id.
+    /// [`None`] for problems with the whole project.
+    pub level: Option<String>,
+    /// Identifier and iid of the entity the problem is on, if any.
+    pub entity: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for LdtkProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.level, &self.entity) {
+            (Some(level), Some(entity)) => write!(f, "[{}] {}: {}", level, entity, self.message),
+            (Some(level), None) => write!(f, "[{}] {}", level, self.message),
+            (None, _) => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// The `LevelId` of a level if it has a valid one, otherwise its LDtk identifier.
+pub fn level_name(level: &Level) -> String {
+    level
+        .get_string_field("LevelId")
+        .ok()
+        .filter(|level_id| !level_id.is_empty())
+        .cloned()
+        .unwrap_or_else(|| level.identifier.clone())
+}
+
+/// Every entity of a level, across all of its entity layers.
+pub fn level_entities(level: &Level) -> impl Iterator<Item = &EntityInstance> {
+    level
+        .layer_instances
+        .iter()
+        .flatten()
+        .filter(|layer| layer.layer_instance_type == Type::Entities)
+        .flat_map(|layer| layer.entity_instances.iter())
+}
+
+/// Number of cells of the `Terrain` int grid of a level with each value.
+pub fn terrain_cell_counts(level: &Level) -> HashMap<i32, usize> {
+    let mut counts = HashMap::new();
+    let terrain = level
+        .layer_instances
+        .iter()
+        .flatten()
+        .filter(|layer| layer.identifier == "Terrain");
+    for layer in terrain {
+        for value in layer.int_grid_csv.iter().filter(|value| **value != 0) {
+            *counts.entry(*value).or_default() += 1;
+        }
+    }
+    counts
+}
+
+fn check_field<F: LdtkFields>(fields: &F, rule: &FieldRule) -> Option<String> {
+    let valid = match rule.kind {
+        FieldKind::Int => fields.get_int_field(rule.name).is_ok(),
+        FieldKind::Float => fields.get_float_field(rule.name).is_ok(),
+        FieldKind::Bool => fields.get_bool_field(rule.name).is_ok(),
+        FieldKind::Point => fields.get_point_field(rule.name).is_ok(),
+        FieldKind::Points => fields.iter_points_field(rule.name).is_ok(),
+        FieldKind::Bools => fields.get_bools_field(rule.name).is_ok(),
+        FieldKind::Floats => fields.iter_floats_field(rule.name).is_ok(),
+        FieldKind::EntityRef => fields.get_entity_ref_field(rule.name).is_ok(),
+        FieldKind::String => fields.get_string_field(rule.name).is_ok(),
+        FieldKind::Enum(values) => match fields.get_enum_field(rule.name) {
+            Ok(value) if values.contains(&value.as_str()) => true,
+            Ok(value) => {
+                return Some(format!(
+                    "{} has unknown value {} (expected one of {})",
+                    rule.name,
+                    value,
+                    values.join(", ")
+                ))
+            }
+            Err(_) => false,
+        },
+        FieldKind::Enums(values) => match fields.iter_enums_field(rule.name) {
+            Ok(mut field_values) => {
+                if let Some(value) = field_values.find(|value| !values.contains(&value.as_str())) {
+                    return Some(format!(
+                        "{} has unknown value {} (expected one of {})",
+                        rule.name,
+                        value,
+                        values.join(", ")
+                    ));
+                }
+                true
+            }
+            Err(_) => false,
+        },
+    };
+    if valid || !rule.required {
+        return None;
+    }
+    Some(format!(
+        "{} needs to be {} with a value",
+        rule.name,
+        rule.kind.name()
+    ))
+}
+
+/// Whether the `Direction` of a `LightSource` resolves to the start of its beam, which leaves the
+/// emitter with nowhere to shoot.
+fn light_source_points_at_itself(entity: &EntityInstance, grid_size: i32) -> bool {
+    let (Ok(x_offset), Ok(y_offset), Ok(direction)) = (
+        entity.get_float_field("XOffset"),
+        entity.get_float_field("YOffset"),
+        entity.get_point_field("Direction"),
+    ) else {
+        return false;
+    };
+    let grid = grid_size as f32;
+    (direction.x - entity.grid.x) as f32 * grid == *x_offset
+        && (direction.y - entity.grid.y) as f32 * grid == *y_offset
+}
+
+fn validate_level(level: &Level, problems: &mut Vec<LdtkProblem>) {
+    let name = level_name(level);
+    let mut problem = |entity: Option<&EntityInstance>, message: String| {
+        problems.push(LdtkProblem {
+            level: Some(name.clone()),
+            entity: entity.map(|entity| format!("{} ({})", entity.identifier, entity.iid)),
+            message,
+        });
+    };
+
+    match level.get_string_field("LevelId") {
+        Ok(level_id) if level_id.is_empty() => problem(None, "LevelId is empty".into()),
+        Ok(_) => {}
+        Err(_) => problem(None, "LevelId needs to be a string level field".into()),
+    }
+    if level.get_bool_field("Selectable").is_err() {
+        problem(None, "Selectable needs to be a bool level field".into());
+    }
+    match level.iter_enums_field("AllowedColors") {
+        Ok(colors) => {
+            for color in colors.filter(|color| !LIGHT_COLORS.contains(&color.as_str())) {
+                problem(None, format!("AllowedColors has unknown color {}", color));
+            }
+        }
+        Err(_) => problem(
+            None,
+            "AllowedColors needs to be an enum array level field".into(),
+        ),
+    }
+
+    if level.layer_instances.is_none() {
+        problem(
+            None,
+            "level has no layers (the \"Separate level files\" option is not supported)".into(),
+        );
+        return;
+    }
+
+    let start_flags = level_entities(level)
+        .filter(|entity| entity.identifier == START_FLAG_IDENT)
+        .collect::<Vec<_>>();
+    match start_flags.as_slice() {
+        [] => problem(None, "level has no Start flag".into()),
+        [flag] if flag.world_x.is_none() || flag.world_y.is_none() => problem(
+            Some(flag),
+            "Start flag has no world coordinates (the world needs to be in free layout mode)"
+                .into(),
+        ),
+        [_] => {}
+        [_, extra @ ..] => {
+            for flag in extra {
+                problem(Some(flag), "level has more than one Start flag".into());
+            }
+        }
+    }
+
+    let entity_layers = level
+        .layer_instances
+        .iter()
+        .flatten()
+        .filter(|layer| layer.layer_instance_type == Type::Entities);
+    for layer in entity_layers {
+        let light_sources = layer
+            .entity_instances
+            .iter()
+            .filter(|entity| entity.identifier == "LightSource");
+        for entity in light_sources {
+            if light_source_points_at_itself(entity, layer.grid_size) {
+                problem(
+                    Some(entity),
+                    "Direction points at the start of the beam (XOffset, YOffset), so the light \
+                     source has no direction"
+                        .into(),
+                );
+            }
+        }
+    }
+
+    let cells = terrain_cell_counts(level);
+    let has_crystals = |toggle_color: &String| {
+        CRYSTAL_INT_CELLS
+            .iter()
+            .filter(|(color, _)| *color == toggle_color.as_str())
+            .flat_map(|(_, values)| values.iter())
+            .any(|value| cells.contains_key(value))
+    };
+    let mut sensor_groups: HashMap<i32, (&String, Option<&String>, bool)> = HashMap::new();
+    for entity in level_entities(level) {
+        let Some((_, rules)) = ENTITY_RULES
+            .iter()
+            .find(|(identifier, _)| *identifier == entity.identifier)
+        else {
+            continue;
+        };
+        for rule in rules.iter() {
+            if let Some(message) = check_field(entity, rule) {
+                problem(Some(entity), message);
+            }
+        }
+
+        if entity.identifier == "Prism" {
+            if let (Ok(colors), Ok(angles)) = (
+                entity.iter_enums_field("colors"),
+                entity.iter_floats_field("angles"),
+            ) {
+                let (colors, angles) = (colors.count(), angles.count());
+                if colors != angles {
+                    problem(
+                        Some(entity),
+                        format!(
+                            "prism has {} colors but {} angles, they need to match",
+                            colors, angles
+                        ),
+                    );
+                }
+            }
+        }
+        if entity.identifier == "Portal" {
+            if let Ok(target) = entity.get_entity_ref_field("target") {
+                let is_portal = level_entities(level).any(|other| {
+                    other.iid != entity.iid
+                        && other.iid == target.entity_iid
+                        && other.identifier == "Portal"
+                });
+                if !is_portal {
+                    problem(
+                        Some(entity),
+                        "target needs to be another Portal in the same level".into(),
+                    );
+                }
+            }
+        }
+        if entity.identifier == "CrystalSignal" {
+            if let Ok(toggle_color) = entity.get_enum_field("toggle_color") {
+                if !has_crystals(toggle_color) {
+                    problem(
+                        Some(entity),
+                        format!(
+                            "crystal signal toggles {} crystals, but the level has none",
+                            toggle_color
+                        ),
+                    );
+                }
+            }
+        }
+        if entity.identifier != "Sensor" {
+            continue;
+        }
+        let Ok(toggle_color) = entity.get_enum_field("toggle_color") else {
+            continue;
+        };
+        let toggle_crystals = entity
+            .get_bool_field("toggle_crystals")
+            .map_or(true, |toggle_crystals| *toggle_crystals);
+        if let Some(group) = entity
+            .get_int_field("group")
+            .ok()
+            .filter(|group| **group > 0)
+        {
+            let logic = entity.get_enum_field("logic").ok();
+            let group_state = (toggle_color, logic, toggle_crystals);
+            if *sensor_groups.entry(*group).or_insert(group_state) != group_state {
+                problem(
+                    Some(entity),
+                    format!(
+                        "sensors in group {} need to have the same toggle_color, toggle_crystals \
+                         and logic",
+                        group
+                    ),
+                );
+            }
+        }
+        if toggle_crystals && !has_crystals(toggle_color) {
+            problem(
+                Some(entity),
+                format!(
+                    "sensor toggles {} crystals, but the level has none",
+                    toggle_color
+                ),
+            );
+        }
+    }
+}
+
+/// Every problem in the project, in level order.
+pub fn validate_project(project: &LdtkJson) -> Vec<LdtkProblem> {
+    let mut problems = Vec::new();
+
+    let mut level_ids: HashMap<&String, Vec<&String>> = HashMap::new();
+    for level in project.levels.iter() {
+        if let Ok(level_id) = level.get_string_field("LevelId") {
+            level_ids
+                .entry(level_id)
+                .or_default()
+                .push(&level.identifier);
+        }
+    }
+    let mut duplicates = level_ids
+        .into_iter()
+        .filter(|(level_id, levels)| !level_id.is_empty() && levels.len() > 1)
+        .collect::<Vec<_>>();
+    duplicates.sort();
+    for (level_id, levels) in duplicates {
+        problems.push(LdtkProblem {
+            level: None,
+            entity: None,
+            message: format!(
+                "LevelId {} is used by more than one level: {}",
+                level_id,
+                levels
+                    .iter()
+                    .map(|level| level.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        });
+    }
+
+    for level in project.levels.iter() {
+        validate_level(level, &mut problems);
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lightborne_project_is_valid() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/levels/lightborne.ldtk");
+        let contents = std::fs::read_to_string(path).expect("lightborne.ldtk should exist");
+        let project: LdtkJson = serde_json::from_str(&contents).expect("should be valid LDtk");
+
+        let problems = validate_project(&project)
+            .iter()
+            .map(LdtkProblem::to_string)
+            .collect::<Vec<_>>();
+        assert!(problems.is_empty(), "{}", problems.join("\n"));
+    }
+}
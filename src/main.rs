@@ -7,6 +7,9 @@ use bevy::{asset::AssetMetaCheck, diagnostic::LogDiagnosticsPlugin};
 
 use camera::{CameraPlugin, HIGHRES_LAYER};
 use config::ConfigPlugin;
+use input::InputMapPlugin;
+use ldtk::{validation::validate_project, LdtkParam, LdtkProblems};
+use save::SavePlugin;
 use shared::{AnimationState, GameState, UiState};
 use sound::SoundPlugin;
 use ui::UiPlugin;
@@ -20,7 +23,11 @@ mod callback;
 mod camera;
 mod config;
 mod game;
+#[cfg(test)]
+mod harness;
+mod input;
 mod ldtk;
+mod save;
 mod shared;
 mod sound;
 mod ui;
@@ -51,25 +58,12 @@ fn main() {
                 ..default()
             }),
     );
-    app.insert_gizmo_config::<DefaultGizmoConfigGroup>(
-        DefaultGizmoConfigGroup,
-        GizmoConfig {
-            enabled: true,
-            render_layers: HIGHRES_LAYER,
-            ..Default::default()
-        },
-    );
     app.add_plugins(AssetLoadPlugin);
     app.add_plugins(ConfigPlugin);
+    app.add_plugins(SavePlugin);
+    app.add_plugins(InputMapPlugin);
     app.add_plugins(LogDiagnosticsPlugin::default());
-    app.add_plugins(SoundPlugin);
-    app.add_plugins(CameraPlugin);
-    app.add_plugins(UiPlugin);
-    app.add_plugins(GamePlugin);
-    app.insert_state(GameState::Loading);
-    app.add_sub_state::<UiState>();
-    app.add_sub_state::<PlayState>();
-    app.add_sub_state::<AnimationState>();
+    add_game_plugins(&mut app);
 
     #[cfg(feature = "dev_mode")]
     app.add_plugins(FpsOverlayPlugin {
@@ -87,10 +81,23 @@ fn main() {
 
     app.add_observer(
         |event: On<ResourceLoaded>,
+         mut commands: Commands,
+         ldtk_param: LdtkParam,
          mut next_game_state: ResMut<NextState<GameState>>,
          mut next_ui_state: ResMut<NextState<UiState>>| match *event {
             ResourceLoaded::Finished => {
                 info!("Resources Loaded");
+                let problems = ldtk_param
+                    .project()
+                    .map(|project| validate_project(project.json_data()))
+                    .unwrap_or_default();
+                if !problems.is_empty() {
+                    for problem in problems.iter() {
+                        error!("{}", problem);
+                    }
+                    commands.insert_resource(LdtkProblems(problems));
+                    return;
+                }
                 next_game_state.set(GameState::Ui);
                 next_ui_state.set(UiState::StartMenu);
             }
@@ -105,3 +112,27 @@ fn main() {
 
     app.run();
 }
+
+/// Adds everything that makes up the game on top of the engine plugins, assets, [`Config`] and
+/// [`SaveData`]. Shared with the headless test harness.
+///
+/// [`Config`]: config::Config
+/// [`SaveData`]: save::SaveData
+fn add_game_plugins(app: &mut App) {
+    app.insert_gizmo_config::<DefaultGizmoConfigGroup>(
+        DefaultGizmoConfigGroup,
+        GizmoConfig {
+            enabled: true,
+            render_layers: HIGHRES_LAYER,
+            ..Default::default()
+        },
+    );
+    app.add_plugins(SoundPlugin);
+    app.add_plugins(CameraPlugin);
+    app.add_plugins(UiPlugin);
+    app.add_plugins(GamePlugin);
+    app.insert_state(GameState::Loading);
+    app.add_sub_state::<UiState>();
+    app.add_sub_state::<PlayState>();
+    app.add_sub_state::<AnimationState>();
+}
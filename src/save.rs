@@ -0,0 +1,324 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{ldtk::Level, LevelIid};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::{
+        defs::shard::CrystalShardMods, ghost::GhostSettings, level_completion::LevelCompleted,
+        light::LightColor,
+    },
+    input::{Binding, InputAction},
+    ldtk::LevelExt,
+    ui::{level_select::LevelProgress, speedrun::SpeedrunTimer},
+};
+
+/// Bumped whenever the layout of [`SaveData`] changes in a way serde can't handle by itself.
+/// Older saves are brought up to date in [`SaveData::migrate`].
+const SAVE_VERSION: u32 = 1;
+
+const SAVE_NAME: &str = "lightborne_save";
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SaveData::load());
+        app.add_systems(Startup, apply_saved_settings);
+        app.add_observer(record_level_completion);
+    }
+}
+
+/// [`Resource`] mirroring the save file on disk (or in `localStorage` on the web). Loaded once at
+/// startup, then written back every time a level is completed.
+#[derive(Resource, Serialize, Deserialize, Debug)]
+pub struct SaveData {
+    /// Saves from before the version was written count as version 0.
+    #[serde(default)]
+    version: u32,
+    #[serde(default)]
+    pub settings: SaveSettings,
+    #[serde(default)]
+    levels: Vec<LevelRecord>,
+}
+
+impl Default for SaveData {
+    fn default() -> Self {
+        SaveData {
+            version: SAVE_VERSION,
+            settings: SaveSettings::default(),
+            levels: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct SaveSettings {
+    #[serde(default)]
+    pub speedrun_timer: bool,
+    /// Show the ghost of the best run of each level.
+    #[serde(default)]
+    pub ghost: bool,
+    /// Bindings changed in the settings menu, on top of the ones from `Lightborne.toml`.
+    #[serde(default)]
+    pub bindings: BTreeMap<InputAction, Vec<Binding>>,
+}
+
+/// The saved progress of a single level. Levels are matched by iid first, then by their `LevelId`
+/// field, so that a level keeps its progress if only one of the two changes in the `.ldtk` file.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct LevelRecord {
+    pub level_id: String,
+    pub level_iid: String,
+    #[serde(default)]
+    pub complete: bool,
+    #[serde(default)]
+    pub unlocked: bool,
+    /// Best time between the start and end markers of the level, in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub best_time_ms: Option<u64>,
+    /// Colors of the crystal shards collected in the level.
+    #[serde(default)]
+    pub shards: Vec<LightColor>,
+}
+
+impl SaveData {
+    pub fn load() -> Self {
+        let Some(contents) = storage::read(SAVE_NAME) else {
+            return SaveData::default();
+        };
+        match toml::from_str::<SaveData>(&contents) {
+            Ok(save) => save.migrate(),
+            Err(err) => {
+                // keep the old file around, since the new one overwrites it on the next write
+                let backup = format!("{}_backup", SAVE_NAME);
+                warn!(
+                    "Failed to parse save file, starting a new one and keeping the old one as {}: {}",
+                    backup, err
+                );
+                storage::write(&backup, &contents);
+                SaveData::default()
+            }
+        }
+    }
+
+    pub fn write(&self) {
+        match toml::to_string(self) {
+            Ok(contents) => storage::write(SAVE_NAME, &contents),
+            Err(err) => error!("Failed to serialize save file: {}", err),
+        }
+    }
+
+    /// Brings a save written by another version of the game up to [`SAVE_VERSION`], one version
+    /// at a time. Fields serde doesn't know are dropped and missing ones get their defaults, so
+    /// saves from newer versions keep everything this version understands.
+    fn migrate(mut self) -> Self {
+        if self.version > SAVE_VERSION {
+            warn!(
+                "Save file version {} is newer than this game's ({}), keeping what can be read",
+                self.version, SAVE_VERSION
+            );
+            self.version = SAVE_VERSION;
+        }
+        if self.version == 0 {
+            // saves from before versioning already have the layout of version 1
+            self.version = 1;
+        }
+        self
+    }
+
+    /// Colors of the crystal shards collected in earlier completed runs of `level`.
+    pub fn shards(&self, level: &Level) -> &[LightColor] {
+        self.level(level.level_id(), &LevelIid::new(level.iid.as_str()))
+            .map_or(&[], |record| record.shards.as_slice())
+    }
+
+    /// Finds the record of a level, preferring a match on the iid over one on the level id.
+    pub fn level(&self, level_id: &str, level_iid: &LevelIid) -> Option<&LevelRecord> {
+        self.levels
+            .iter()
+            .find(|record| &record.level_iid == level_iid.get())
+            .or_else(|| {
+                self.levels
+                    .iter()
+                    .find(|record| record.level_id == level_id)
+            })
+    }
+
+    fn level_mut(&mut self, level_id: &str, level_iid: &LevelIid) -> &mut LevelRecord {
+        let index = self
+            .levels
+            .iter()
+            .position(|record| &record.level_iid == level_iid.get())
+            .or_else(|| {
+                self.levels
+                    .iter()
+                    .position(|record| record.level_id == level_id)
+            });
+        let index = index.unwrap_or_else(|| {
+            self.levels.push(LevelRecord::default());
+            self.levels.len() - 1
+        });
+
+        // keep the record in sync with the current ldtk project, in case it was matched by only
+        // one of the two
+        let record = &mut self.levels[index];
+        record.level_id = level_id.to_string();
+        record.level_iid = level_iid.get().clone();
+        record
+    }
+}
+
+pub fn apply_saved_settings(
+    save: Res<SaveData>,
+    mut speedrun_timer: ResMut<SpeedrunTimer>,
+    mut ghost_settings: ResMut<GhostSettings>,
+) {
+    speedrun_timer.enabled = save.settings.speedrun_timer;
+    ghost_settings.enabled = save.settings.ghost;
+}
+
+/// Copies [`LevelProgress`], the time of the completed level and the shards collected in it to
+/// the [`SaveData`], then writes it out.
+pub fn record_level_completion(
+    event: On<LevelCompleted>,
+    mut save: ResMut<SaveData>,
+    res_levels: Res<LevelProgress>,
+    shard_mods: Res<CrystalShardMods>,
+) {
+    for level in res_levels.0.iter() {
+        let record = save.level_mut(&level.level_id, &level.level_iid);
+        record.complete |= level.complete;
+        record.unlocked |= !level.locked;
+
+        if level.level_iid != event.level_iid {
+            continue;
+        }
+        let time = event.time.as_millis() as u64;
+        record.best_time_ms = Some(record.best_time_ms.map_or(time, |best| best.min(time)));
+        for (color, collected) in shard_mods.0.iter() {
+            if *collected && !record.shards.contains(&color) {
+                record.shards.push(color);
+            }
+        }
+    }
+    save.write();
+}
+
+/// Key-value storage for small files: `<name>.toml` relative to the working directory on native,
+/// `localStorage` on the web.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub mod storage {
+    use std::path::PathBuf;
+
+    use bevy::prelude::*;
+
+    fn path(name: &str) -> PathBuf {
+        PathBuf::from(format!("{}.toml", name))
+    }
+
+    pub fn read(name: &str) -> Option<String> {
+        std::fs::read_to_string(path(name)).ok()
+    }
+
+    pub fn write(name: &str, contents: &str) {
+        let path = path(name);
+        let result = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => std::fs::create_dir_all(dir),
+            _ => Ok(()),
+        }
+        .and_then(|()| std::fs::write(&path, contents));
+        if let Err(err) = result {
+            error!("Failed to write {}: {}", path.display(), err);
+        }
+    }
+}
+
+/// Key-value storage for small files: `<name>.toml` relative to the working directory on native,
+/// `localStorage` on the web.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub mod storage {
+    use bevy::prelude::*;
+
+    fn local_storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok().flatten()
+    }
+
+    pub fn read(name: &str) -> Option<String> {
+        local_storage()?.get_item(name).ok().flatten()
+    }
+
+    pub fn write(name: &str, contents: &str) {
+        let Some(storage) = local_storage() else {
+            error!("localStorage is not available, {} will not be saved", name);
+            return;
+        };
+        if storage.set_item(name, contents).is_err() {
+            error!("Failed to write {} to localStorage", name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::InputMap;
+
+    #[test]
+    fn unversioned_save_keeps_its_levels() {
+        let save = toml::from_str::<SaveData>(
+            r#"
+            [[levels]]
+            level_id = "0-1"
+            level_iid = "iid"
+            complete = true
+            shards = ["Green"]
+            "#,
+        )
+        .expect("should parse")
+        .migrate();
+
+        assert_eq!(save.version, SAVE_VERSION);
+        let record = save
+            .level("0-1", &LevelIid::new("iid"))
+            .expect("level should be kept");
+        assert!(record.complete);
+        assert_eq!(record.shards, vec![LightColor::Green]);
+    }
+
+    #[test]
+    fn newer_save_keeps_what_it_can() {
+        let save = toml::from_str::<SaveData>(
+            r#"
+            version = 99
+            unknown = "field"
+
+            [settings]
+            ghost = true
+            "#,
+        )
+        .expect("should parse")
+        .migrate();
+
+        assert_eq!(save.version, SAVE_VERSION);
+        assert!(save.settings.ghost);
+    }
+
+    #[test]
+    fn default_bindings_survive_the_save_file() {
+        let mut save = SaveData::default();
+        let defaults = InputMap::default();
+        for (action, bindings) in defaults.0.iter() {
+            save.settings.bindings.insert(action, bindings.clone());
+        }
+
+        let contents = toml::to_string(&save).expect("should serialize");
+        let loaded = toml::from_str::<SaveData>(&contents)
+            .expect("should parse")
+            .migrate();
+        for (action, bindings) in defaults.0.iter() {
+            assert_eq!(&loaded.settings.bindings[&action], bindings, "{:?}", action);
+        }
+    }
+}
@@ -31,6 +31,8 @@ pub enum UiState {
     LevelSelect,
     Settings,
     StartMenu,
+    /// Split times of the run, shown after the last level is completed with the speedrun timer on.
+    Results,
 }
 
 #[derive(Event)]
@@ -4,13 +4,14 @@ use bevy::asset::RenderAssetUsages;
 use bevy::image::{BevyDefault, TextureFormatPixelInfo};
 use bevy::prelude::*;
 use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
-use bevy_ecs_ldtk::ldtk::FieldValue;
+use bevy_ecs_ldtk::ldtk::{FieldValue, Level};
 use bevy_ecs_ldtk::prelude::LdtkFields;
 use bevy_ecs_ldtk::{LevelIid, LevelSelection};
 
 use crate::asset::LoadResource;
 use crate::config::Config;
-use crate::ldtk::LdtkParam;
+use crate::ldtk::{LdtkParam, LdtkProjectReloaded};
+use crate::save::SaveData;
 use crate::shared::{GameState, UiState};
 use crate::sound::{BgmTrack, ChangeBgmEvent};
 use crate::ui::{UiButton, UiClick, UiFont, UiFontSize};
@@ -33,6 +34,7 @@ impl Plugin for LevelSelectPlugin {
             handle_level_selection.run_if(in_state(UiState::LevelSelect)),
         );
         app.add_systems(OnExit(UiState::LevelSelect), despawn_level_select);
+        app.add_observer(rebuild_level_select);
     }
 }
 
@@ -105,7 +107,7 @@ pub struct LevelSelectButtonIndex(usize, usize);
 
 #[derive(PartialEq, Eq)]
 pub struct LevelSaveData {
-    level_id: String,
+    pub level_id: String,
     pub level_iid: LevelIid,
     level_index: usize,
     pub complete: bool,
@@ -127,14 +129,25 @@ impl PartialOrd for LevelSaveData {
 #[derive(Resource)]
 pub struct LevelProgress(pub Vec<LevelSaveData>);
 
-fn init_levels(mut res_levels: ResMut<LevelProgress>, ldtk_param: LdtkParam, config: Res<Config>) {
+fn init_levels(
+    mut res_levels: ResMut<LevelProgress>,
+    ldtk_param: LdtkParam,
+    config: Res<Config>,
+    save: Res<SaveData>,
+) {
     if !res_levels.0.is_empty() {
         return;
     }
     let Some(project) = ldtk_param.project() else {
         return;
     };
-    for (i, level) in project.json_data().levels.iter().enumerate() {
+    res_levels.0 = level_progress(&project.json_data().levels, &config, &save);
+}
+
+/// Every selectable level sorted by id, with its progress from the save.
+fn level_progress(levels: &[Level], config: &Config, save: &SaveData) -> Vec<LevelSaveData> {
+    let mut progress = Vec::new();
+    for (i, level) in levels.iter().enumerate() {
         let level_id = level
             .get_string_field("LevelId")
             .expect("Levels should always have a level id!");
@@ -150,16 +163,62 @@ fn init_levels(mut res_levels: ResMut<LevelProgress>, ldtk_param: LdtkParam, con
         if !should_show {
             continue;
         }
-        res_levels.0.push(LevelSaveData {
+        let level_iid = LevelIid::new(level.iid.clone());
+        let record = save.level(level_id, &level_iid);
+        let complete = record.is_some_and(|record| record.complete);
+        let unlocked = record.is_some_and(|record| record.unlocked);
+
+        progress.push(LevelSaveData {
             level_id: level_id.to_string(),
-            level_iid: LevelIid::new(level.iid.clone()),
+            level_iid,
             level_index: i,
-            complete: config.debug_config.unlock_levels,
-            locked: !config.debug_config.unlock_levels,
+            complete: complete || config.debug_config.unlock_levels,
+            locked: !unlocked && !config.debug_config.unlock_levels,
         });
     }
-    res_levels.0.sort();
-    res_levels.0[0].locked = false;
+    progress.sort();
+    progress[0].locked = false;
+    progress
+}
+
+/// Rebuilds the level previews and [`LevelProgress`] from the reloaded LDtk project, keeping the
+/// progress made so far.
+#[allow(clippy::too_many_arguments)]
+fn rebuild_level_select(
+    _: On<LdtkProjectReloaded>,
+    mut level_preview_store: ResMut<LevelPreviewStore>,
+    mut res_levels: ResMut<LevelProgress>,
+    ldtk_param: LdtkParam,
+    config: Res<Config>,
+    save: Res<SaveData>,
+    ui_state: Option<Res<State<UiState>>>,
+    mut next_ui_state: ResMut<NextState<UiState>>,
+) {
+    level_preview_store.0.clear();
+    if res_levels.0.is_empty() {
+        return;
+    }
+    let Some(project) = ldtk_param.project() else {
+        return;
+    };
+
+    let mut progress = level_progress(&project.json_data().levels, &config, &save);
+    for level in progress.iter_mut() {
+        if let Some(old) = res_levels
+            .0
+            .iter()
+            .find(|old| old.level_id == level.level_id)
+        {
+            level.complete |= old.complete;
+            level.locked &= old.locked;
+        }
+    }
+    res_levels.0 = progress;
+
+    // NOTE: the buttons hold indices into the old levels, so spawn them again
+    if ui_state.is_some_and(|ui_state| *ui_state.get() == UiState::LevelSelect) {
+        next_ui_state.set(UiState::LevelSelect);
+    }
 }
 
 fn spawn_level_select(
@@ -338,7 +397,7 @@ fn despawn_level_select(
 }
 
 fn ensure_level_preview_image(
-    level: &bevy_ecs_ldtk::ldtk::Level,
+    level: &Level,
     level_preview_store: &mut LevelPreviewStore,
     assets: &mut Assets<Image>,
 ) -> (Vec2, Handle<Image>) {
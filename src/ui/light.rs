@@ -37,7 +37,7 @@ pub struct LightUiIcons {
 #[reflect(Resource)]
 pub struct LightUiAssets {
     #[dependency]
-    icons: [Handle<Image>; 4],
+    icons: [Handle<Image>; 5],
 }
 
 impl FromWorld for LightUiAssets {
@@ -50,6 +50,7 @@ impl FromWorld for LightUiAssets {
                 asset_server.load("ui/purple_light_icon.png"),
                 asset_server.load("ui/blue_light_icon.png"),
                 asset_server.load("ui/white_light_icon.png"),
+                asset_server.load("ui/black_light_icon.png"),
             ],
         }
     }
@@ -65,6 +66,7 @@ pub fn spawn_light_icons(
         LightColor::Purple => light_ui_assets.icons[1].clone(),
         LightColor::Blue => light_ui_assets.icons[2].clone(),
         LightColor::White => light_ui_assets.icons[3].clone(),
+        LightColor::Black => light_ui_assets.icons[4].clone(),
     };
 
     let overlay = commands
@@ -99,6 +101,7 @@ pub fn spawn_light_icons(
             LightColor::Purple => "2",
             LightColor::White => "3",
             LightColor::Blue => "4",
+            LightColor::Black => "5",
         };
         let icon = commands
             .spawn(ImageNode::from(icons[val].clone()))
@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-use crate::{asset::ResourceLoaded, shared::GameState, ui::UiFontSize};
+use crate::{asset::ResourceLoaded, ldtk::LdtkProblems, shared::GameState, ui::UiFontSize};
 
 pub struct LoadingUiPlugin;
 
@@ -8,6 +8,10 @@ impl Plugin for LoadingUiPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(GameState::Loading), spawn_loading_ui)
             .add_observer(update_loading_ui)
+            .add_systems(
+                Update,
+                show_ldtk_problems.run_if(resource_exists_and_changed::<LdtkProblems>),
+            )
             .add_systems(OnExit(GameState::Loading), despawn_loading_ui);
     }
 }
@@ -81,6 +85,48 @@ pub fn update_loading_ui(
     loading_menu_progress.width = Val::Percent(progress);
 }
 
+/// Replaces the progress bar with the list of problems found in the LDtk project.
+pub fn show_ldtk_problems(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    problems: Res<LdtkProblems>,
+    loading_ui: Single<Entity, With<LoadingMenuUi>>,
+) {
+    let font = TextFont {
+        font: asset_server.load("fonts/Outfit-Medium.ttf"),
+        font_size: UiFontSize::TEXT,
+        ..default()
+    };
+
+    commands.entity(*loading_ui).despawn_related::<Children>();
+    commands.entity(*loading_ui).insert(Node {
+        width: Val::Percent(100.),
+        height: Val::Percent(100.),
+        display: Display::Flex,
+        flex_direction: FlexDirection::Column,
+        padding: UiRect::all(Val::Px(96.)),
+        row_gap: Val::Px(8.),
+        overflow: Overflow::scroll_y(),
+        ..default()
+    });
+
+    commands
+        .spawn(Text::new(format!(
+            "The level file has {} problem(s):",
+            problems.0.len()
+        )))
+        .insert(ChildOf(*loading_ui))
+        .insert(font.clone().with_font_size(UiFontSize::HEADER));
+
+    for problem in problems.0.iter() {
+        commands
+            .spawn(Text::new(problem.to_string()))
+            .insert(TextColor(Color::srgb(1.0, 0.4, 0.4)))
+            .insert(ChildOf(*loading_ui))
+            .insert(font.clone());
+    }
+}
+
 pub fn despawn_loading_ui(loading_ui: Single<Entity, With<LoadingMenuUi>>, mut commands: Commands) {
     info!("Despawning Loading UI!");
 
@@ -10,7 +10,8 @@ use crate::{
     shared::{GameState, PlayState},
     ui::{
         level_select::LevelSelectPlugin, light::LightUiPlugin, loading::LoadingUiPlugin,
-        settings::SettingsPlugin, speedrun::SpeedrunTimerPlugin, start_menu::StartMenuPlugin,
+        navigation::GamepadNavigationPlugin, results::ResultsPlugin, settings::SettingsPlugin,
+        speedrun::SpeedrunTimerPlugin, start_menu::StartMenuPlugin,
     },
 };
 
@@ -18,9 +19,11 @@ pub mod follow;
 pub mod level_select;
 mod light;
 mod loading;
+mod navigation;
 mod pause;
+mod results;
 pub mod settings;
-mod speedrun;
+pub mod speedrun;
 mod start_menu;
 pub mod tooltip;
 
@@ -41,6 +44,8 @@ impl Plugin for UiPlugin {
         app.add_plugins(StartMenuPlugin);
         app.add_plugins(SettingsPlugin);
         app.add_plugins(LevelSelectPlugin);
+        app.add_plugins(ResultsPlugin);
+        app.add_plugins(GamepadNavigationPlugin);
         app.add_systems(
             PreUpdate,
             button_sfx
@@ -0,0 +1,108 @@
+use bevy::{prelude::*, ui::UiGlobalTransform};
+
+use crate::{
+    input::STICK_DEADZONE,
+    ui::{UiButton, UiClick},
+};
+
+pub struct GamepadNavigationPlugin;
+
+impl Plugin for GamepadNavigationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<UiFocus>();
+        app.add_systems(
+            Update,
+            (navigate_ui_buttons, highlight_focused_button).chain(),
+        );
+    }
+}
+
+/// [`Resource`] holding the [`UiButton`] currently focused with a gamepad.
+#[derive(Resource, Default)]
+pub struct UiFocus {
+    focused: Option<Entity>,
+    /// Whether the stick was deflected last frame, so that holding it only moves the focus once.
+    stick_held: bool,
+}
+
+/// Moves the focus between the visible [`UiButton`]s in reading order with the d-pad or left
+/// stick, and clicks the focused button with the south button.
+pub fn navigate_ui_buttons(
+    mut commands: Commands,
+    mut focus: ResMut<UiFocus>,
+    gamepads: Query<&Gamepad>,
+    q_buttons: Query<(Entity, &UiGlobalTransform, &InheritedVisibility), With<UiButton>>,
+) {
+    let mut buttons = q_buttons
+        .iter()
+        .filter(|(_, _, visibility)| visibility.get())
+        .map(|(entity, transform, _)| (entity, transform.translation))
+        .collect::<Vec<_>>();
+    if buttons.is_empty() {
+        focus.focused = None;
+        return;
+    }
+    buttons.sort_by(|(_, a), (_, b)| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+
+    let mut step = 0;
+    let mut stick_held = false;
+    let mut click = false;
+    for gamepad in gamepads.iter() {
+        if gamepad.any_just_pressed([GamepadButton::DPadDown, GamepadButton::DPadRight]) {
+            step = 1;
+        }
+        if gamepad.any_just_pressed([GamepadButton::DPadUp, GamepadButton::DPadLeft]) {
+            step = -1;
+        }
+        let stick = gamepad.left_stick();
+        if stick.length() > STICK_DEADZONE {
+            stick_held = true;
+            if !focus.stick_held {
+                // stick y points up, but ui y points down
+                step = if stick.y.abs() > stick.x.abs() {
+                    -stick.y.signum() as i32
+                } else {
+                    stick.x.signum() as i32
+                };
+            }
+        }
+        click |= gamepad.just_pressed(GamepadButton::South);
+    }
+    focus.stick_held = stick_held;
+
+    let current = focus
+        .focused
+        .and_then(|focused| buttons.iter().position(|(entity, _)| *entity == focused));
+    let next = match (current, step) {
+        (None, 0) => None,
+        (None, _) => Some(0),
+        (Some(index), step) => {
+            Some((index as i32 + step).rem_euclid(buttons.len() as i32) as usize)
+        }
+    };
+    focus.focused = next.map(|index| buttons[index].0);
+
+    if let (true, Some(entity)) = (click, focus.focused) {
+        commands.trigger(UiClick { entity });
+    }
+}
+
+pub fn highlight_focused_button(
+    mut commands: Commands,
+    focus: Res<UiFocus>,
+    q_outlined: Query<Entity, (With<UiButton>, With<Outline>)>,
+) {
+    if !focus.is_changed() {
+        return;
+    }
+    for entity in q_outlined.iter() {
+        if Some(entity) != focus.focused {
+            commands.entity(entity).remove::<Outline>();
+        }
+    }
+    if let Some(entity) = focus.focused {
+        commands
+            .entity(entity)
+            .try_insert(Outline::new(Val::Px(2.), Val::Px(4.), Color::WHITE));
+    }
+}
@@ -1,7 +1,10 @@
-use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+use bevy::prelude::*;
 
 use crate::{
     asset::LoadResource,
+    game::ghost::GhostSettings,
+    input::{action_just_pressed, InputAction},
+    save::SaveData,
     shared::{GameState, PlayState, UiState},
     ui::{UiButton, UiClick, UiFont, UiFontSize},
 };
@@ -18,7 +21,7 @@ impl Plugin for PausePlugin {
             Update,
             toggle_pause
                 .run_if(in_state(GameState::InGame))
-                .run_if(input_just_pressed(KeyCode::Escape)),
+                .run_if(action_just_pressed(InputAction::Pause)),
         );
     }
 }
@@ -43,7 +46,20 @@ impl FromWorld for PauseAssets {
 #[derive(Component)]
 pub struct PauseMarker;
 
-fn spawn_pause(mut commands: Commands, ui_font: Res<UiFont>, _pause_assets: Res<PauseAssets>) {
+fn ghost_text(settings: &GhostSettings) -> Text {
+    Text::new(if settings.enabled {
+        "Ghost: On"
+    } else {
+        "Ghost: Off"
+    })
+}
+
+fn spawn_pause(
+    mut commands: Commands,
+    ui_font: Res<UiFont>,
+    ghost_settings: Res<GhostSettings>,
+    _pause_assets: Res<PauseAssets>,
+) {
     let container = commands
         .spawn(PauseMarker)
         .insert(Node {
@@ -118,6 +134,26 @@ fn spawn_pause(mut commands: Commands, ui_font: Res<UiFont>, _pause_assets: Res<
                 next_play_state.set(PlayState::Playing);
             },
         );
+
+    commands
+        .spawn(ghost_text(&ghost_settings))
+        .insert(Button)
+        .insert(UiButton)
+        .insert(ui_font.text_font().with_font_size(UiFontSize::BUTTON))
+        .insert(ChildOf(center_container))
+        .observe(
+            |event: On<UiClick>,
+             mut commands: Commands,
+             mut ghost_settings: ResMut<GhostSettings>,
+             mut save: ResMut<SaveData>| {
+                ghost_settings.enabled = !ghost_settings.enabled;
+                save.settings.ghost = ghost_settings.enabled;
+                save.write();
+                commands
+                    .entity(event.entity)
+                    .insert(ghost_text(&ghost_settings));
+            },
+        );
 }
 
 fn despawn_pause(mut commands: Commands, pause: Single<Entity, With<PauseMarker>>) {
@@ -0,0 +1,250 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    shared::UiState,
+    sound::{BgmTrack, ChangeBgmEvent},
+    ui::{
+        speedrun::{Split, Splits},
+        UiButton, UiClick, UiFont, UiFontSize,
+    },
+    utils::hhmmss::Hhmmss,
+};
+
+/// Where the splits of a finished run are exported, relative to the working directory.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+const LIVESPLIT_PATH: &str = "lightborne_splits.lss";
+
+const COLUMN_WIDTHS: [f32; 5] = [120., 260., 260., 140., 140.];
+
+pub struct ResultsPlugin;
+
+impl Plugin for ResultsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(UiState::Results), spawn_results);
+        app.add_systems(OnExit(UiState::Results), despawn_results);
+    }
+}
+
+#[derive(Component)]
+pub struct ResultsMarker;
+
+fn spawn_row<const N: usize>(
+    commands: &mut Commands,
+    parent: Entity,
+    ui_font: &UiFont,
+    cells: [(String, Color); N],
+) {
+    let row = commands
+        .spawn(Node {
+            flex_direction: FlexDirection::Row,
+            ..default()
+        })
+        .insert(ChildOf(parent))
+        .id();
+    for ((text, color), width) in cells.into_iter().zip(COLUMN_WIDTHS) {
+        commands
+            .spawn(Node {
+                width: Val::Px(width),
+                ..default()
+            })
+            .insert(Text::new(text))
+            .insert(TextColor(color))
+            .insert(ui_font.text_font().with_font_size(UiFontSize::TEXT))
+            .insert(ChildOf(row));
+    }
+}
+
+fn spawn_results(mut commands: Commands, ui_font: Res<UiFont>, splits: Res<Splits>) {
+    info!("Spawning Results!");
+
+    commands.trigger(ChangeBgmEvent(BgmTrack::LevelSelect));
+
+    let container = commands
+        .spawn(ResultsMarker)
+        .insert(Node {
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            padding: UiRect::all(Val::Px(96.)),
+            row_gap: Val::Px(8.),
+            ..default()
+        })
+        .insert(BackgroundColor(Color::BLACK))
+        .id();
+
+    commands
+        .spawn(Text::new("Results"))
+        .insert(Node {
+            margin: UiRect::bottom(Val::Px(32.)),
+            ..default()
+        })
+        .insert(ui_font.text_font().with_font_size(UiFontSize::HEADER))
+        .insert(ChildOf(container));
+
+    let header =
+        ["Level", "Time", "Best", "Deaths", "Resets"].map(|text| (text.into(), Color::WHITE));
+    spawn_row(&mut commands, container, &ui_font, header);
+
+    let mut total = Duration::ZERO;
+    let (mut deaths, mut resets) = (0, 0);
+    for split in splits.0.iter() {
+        total += split.time.unwrap_or_default();
+        deaths += split.deaths;
+        resets += split.resets;
+        let time = split
+            .time
+            .map_or("DNF".to_string(), |time| time.hhmmssxxx());
+        let delta = split.delta_text().unwrap_or_else(|| {
+            let best = split.best.map_or("-".to_string(), |best| best.hhmmssxxx());
+            (best, Color::WHITE)
+        });
+        spawn_row(
+            &mut commands,
+            container,
+            &ui_font,
+            [
+                (split.level_id.clone(), Color::WHITE),
+                (time, Color::WHITE),
+                delta,
+                (split.deaths.to_string(), Color::WHITE),
+                (split.resets.to_string(), Color::WHITE),
+            ],
+        );
+    }
+    spawn_row(
+        &mut commands,
+        container,
+        &ui_font,
+        [
+            ("Total".into(), Color::WHITE),
+            (total.hhmmssxxx(), Color::WHITE),
+            (String::new(), Color::WHITE),
+            (deaths.to_string(), Color::WHITE),
+            (resets.to_string(), Color::WHITE),
+        ],
+    );
+
+    if let Some(message) = export_livesplit(&splits.0) {
+        commands
+            .spawn(Text::new(message))
+            .insert(Node {
+                margin: UiRect::top(Val::Px(32.)),
+                ..default()
+            })
+            .insert(ui_font.text_font().with_font_size(24.))
+            .insert(ChildOf(container));
+    }
+
+    commands
+        .spawn(Text::new("Main Menu"))
+        .insert(Node {
+            margin: UiRect::top(Val::Px(32.)),
+            ..default()
+        })
+        .insert(Button)
+        .insert(UiButton)
+        .insert(ui_font.text_font().with_font_size(UiFontSize::BUTTON))
+        .insert(ChildOf(container))
+        .observe(
+            |_: On<UiClick>, mut next_ui_state: ResMut<NextState<UiState>>| {
+                next_ui_state.set(UiState::StartMenu);
+            },
+        );
+}
+
+fn despawn_results(mut commands: Commands, results: Single<Entity, With<ResultsMarker>>) {
+    info!("Despawning Results!");
+
+    commands.entity(*results).despawn();
+}
+
+/// Formats a duration the way LiveSplit stores times, e.g. `00:01:02.5000000`.
+fn livesplit_time(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}.{:07}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        duration.subsec_nanos() / 100
+    )
+}
+
+/// Escapes the characters that can't appear as-is in XML text.
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// A LiveSplit `.lss` splits file with one segment per completed split, using the times of this
+/// run as the personal best.
+fn livesplit_splits(splits: &[Split]) -> String {
+    let mut segments = String::new();
+    let mut cumulative = Duration::ZERO;
+    for split in splits {
+        let Some(time) = split.time else {
+            continue;
+        };
+        cumulative += time;
+        let best_segment = split.best.map_or(time, |best| best.min(time));
+        segments.push_str(&format!(
+            r#"    <Segment>
+      <Name>{}</Name>
+      <Icon />
+      <SplitTimes>
+        <SplitTime name="Personal Best">
+          <RealTime>{}</RealTime>
+        </SplitTime>
+      </SplitTimes>
+      <BestSegmentTime>
+        <RealTime>{}</RealTime>
+      </BestSegmentTime>
+      <SegmentHistory />
+    </Segment>
+"#,
+            xml_escape(&split.level_id),
+            livesplit_time(cumulative),
+            livesplit_time(best_segment)
+        ));
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<Run version="1.7.0">
+  <GameIcon />
+  <GameName>Lightborne</GameName>
+  <CategoryName>Any%</CategoryName>
+  <Offset>00:00:00</Offset>
+  <AttemptCount>1</AttemptCount>
+  <AttemptHistory />
+  <Segments>
+{}  </Segments>
+  <AutoSplitterSettings />
+</Run>
+"#,
+        segments
+    )
+}
+
+/// Writes the splits to [`LIVESPLIT_PATH`], returning a message for the results screen.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn export_livesplit(splits: &[Split]) -> Option<String> {
+    match std::fs::write(LIVESPLIT_PATH, livesplit_splits(splits)) {
+        Ok(()) => Some(format!("Splits exported to {}", LIVESPLIT_PATH)),
+        Err(err) => {
+            error!("Failed to write {}: {}", LIVESPLIT_PATH, err);
+            None
+        }
+    }
+}
+
+// NOTE: there is no file to write to on the web, so the splits go to the browser console instead
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+fn export_livesplit(splits: &[Split]) -> Option<String> {
+    info!("LiveSplit splits:\n{}", livesplit_splits(splits));
+    Some("Splits written to the browser console".to_string())
+}
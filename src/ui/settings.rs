@@ -1,53 +1,96 @@
 use bevy::prelude::*;
+use enum_map::Enum;
 
+use crate::config::Config;
+use crate::input::{first_just_pressed, Binding, InputAction, InputMap};
+use crate::save::SaveData;
 use crate::shared::UiState;
 use crate::sound::{BgmTrack, ChangeBgmEvent};
-use crate::ui::{UiButton, UiClick, UiFont, UiFontSize};
+use crate::ui::{speedrun::SpeedrunTimer, UiButton, UiClick, UiFont, UiFontSize};
 
 pub struct SettingsPlugin;
 
 impl Plugin for SettingsPlugin {
     fn build(&self, app: &mut App) {
+        app.init_resource::<Rebinding>();
         app.add_systems(OnEnter(UiState::Settings), spawn_settings);
         app.add_systems(OnExit(UiState::Settings), despawn_settings);
+        app.add_systems(
+            Update,
+            (capture_rebind, update_rebind_text)
+                .chain()
+                .run_if(in_state(UiState::Settings)),
+        );
     }
 }
 
 #[derive(Component)]
 struct SettingsUiMarker;
 
-const CONTROLS: [(&str, &str); 8] = [
-    ("Restart", "R"),
-    ("Jump", "Space"),
-    ("Movement", "WASD"),
-    ("Sneak", "Control"),
-    ("Snap Angles", "Shift"),
-    ("Aim Light", "Left Click (Press)"),
-    ("Shoot Light", "Left Click (Release)"),
-    ("Cancel Shoot Light", "Right Click"),
-];
-
-fn spawn_settings(mut commands: Commands, ui_font: Res<UiFont>) {
+/// Marks the text showing the bindings of an [`InputAction`]. Clicking it starts rebinding it.
+#[derive(Component)]
+struct RebindButton(InputAction);
+
+/// [`Resource`] holding the action currently waiting for a new binding.
+#[derive(Resource, Default)]
+struct Rebinding {
+    action: Option<InputAction>,
+    /// Set one frame after the rebind button was clicked, so that the click itself isn't taken as
+    /// the new binding.
+    armed: bool,
+}
+
+fn speedrun_timer_text(speedrun_timer: &SpeedrunTimer) -> Text {
+    Text::new(if speedrun_timer.enabled {
+        "Speedrun Timer: On"
+    } else {
+        "Speedrun Timer: Off"
+    })
+}
+
+fn spawn_settings(
+    mut commands: Commands,
+    ui_font: Res<UiFont>,
+    input_map: Res<InputMap>,
+    speedrun_timer: Res<SpeedrunTimer>,
+) {
     info!("Spawning Settings Menu");
 
     commands.trigger(ChangeBgmEvent(BgmTrack::None));
 
-    let controls_nodes = CONTROLS.map(|(action, control)| {
-        commands
-            .spawn(Node {
-                width: Val::Percent(100.0),
-                height: Val::Auto,
-                display: Display::Flex,
-                flex_direction: FlexDirection::Row,
-                justify_content: JustifyContent::SpaceBetween,
-                ..default()
-            })
-            .with_children(|parent| {
-                parent.spawn((Text::new(action), ui_font.text_font().with_font_size(24.0)));
-                parent.spawn((Text::new(control), ui_font.text_font().with_font_size(24.0)));
-            })
-            .id()
-    });
+    let controls_nodes = (0..InputAction::LENGTH)
+        .map(InputAction::from_usize)
+        .map(|action| {
+            commands
+                .spawn(Node {
+                    width: Val::Percent(100.0),
+                    height: Val::Auto,
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Row,
+                    justify_content: JustifyContent::SpaceBetween,
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new(action.name()),
+                        ui_font.text_font().with_font_size(24.0),
+                    ));
+                    parent
+                        .spawn((
+                            Text::new(input_map.describe(action)),
+                            ui_font.text_font().with_font_size(24.0),
+                            Button,
+                            UiButton,
+                            RebindButton(action),
+                        ))
+                        .observe(move |_: On<UiClick>, mut rebinding: ResMut<Rebinding>| {
+                            rebinding.action = Some(action);
+                            rebinding.armed = false;
+                        });
+                })
+                .id()
+        })
+        .collect::<Vec<_>>();
 
     let container = commands
         .spawn(SettingsUiMarker)
@@ -85,11 +128,50 @@ fn spawn_settings(mut commands: Commands, ui_font: Res<UiFont>) {
                 margin: UiRect::bottom(Val::Px(24.)),
                 ..default()
             },
-            Text::new("Controls (Fixed)"),
+            Text::new("Controls (Click to Rebind)"),
             ui_font.text_font().with_font_size(36.),
         ))
         .add_children(&controls_nodes);
 
+    commands
+        .spawn(speedrun_timer_text(&speedrun_timer))
+        .insert(Button)
+        .insert(UiButton)
+        .insert(ui_font.text_font().with_font_size(24.))
+        .insert(ChildOf(container))
+        .observe(
+            |event: On<UiClick>,
+             mut commands: Commands,
+             mut speedrun_timer: ResMut<SpeedrunTimer>,
+             mut save: ResMut<SaveData>| {
+                speedrun_timer.enabled = !speedrun_timer.enabled;
+                save.settings.speedrun_timer = speedrun_timer.enabled;
+                save.write();
+                commands
+                    .entity(event.entity)
+                    .insert(speedrun_timer_text(&speedrun_timer));
+            },
+        );
+
+    commands
+        .spawn(Text::new("Reset Controls"))
+        .insert(Button)
+        .insert(UiButton)
+        .insert(ui_font.text_font().with_font_size(24.))
+        .insert(ChildOf(container))
+        .observe(
+            |_: On<UiClick>,
+             config: Res<Config>,
+             mut input_map: ResMut<InputMap>,
+             mut save: ResMut<SaveData>,
+             mut rebinding: ResMut<Rebinding>| {
+                *input_map = InputMap::from_config(&config.controls_config);
+                save.settings.bindings.clear();
+                save.write();
+                rebinding.action = None;
+            },
+        );
+
     commands
         .spawn(Text::new("Back"))
         .insert(Button)
@@ -104,8 +186,62 @@ fn spawn_settings(mut commands: Commands, ui_font: Res<UiFont>) {
         );
 }
 
-fn despawn_settings(mut commands: Commands, settings_menu: Single<Entity, With<SettingsUiMarker>>) {
+fn despawn_settings(
+    mut commands: Commands,
+    settings_menu: Single<Entity, With<SettingsUiMarker>>,
+    mut rebinding: ResMut<Rebinding>,
+) {
     info!("Despawning Settings Menu");
 
+    rebinding.action = None;
     commands.entity(*settings_menu).despawn();
 }
+
+/// Binds the first key, mouse or gamepad button pressed to the action being rebound. Escape
+/// cancels.
+fn capture_rebind(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    gamepads: Query<&Gamepad>,
+    mut rebinding: ResMut<Rebinding>,
+    mut input_map: ResMut<InputMap>,
+    mut save: ResMut<SaveData>,
+) {
+    let Some(action) = rebinding.action else {
+        return;
+    };
+    if !rebinding.armed {
+        rebinding.armed = true;
+        return;
+    }
+    let Some(binding) = first_just_pressed(&keys, &mouse, &gamepads) else {
+        return;
+    };
+    rebinding.action = None;
+    if binding == Binding::Key(KeyCode::Escape) {
+        return;
+    }
+
+    input_map.rebind(action, binding);
+    save.settings
+        .bindings
+        .insert(action, input_map.0[action].clone());
+    save.write();
+}
+
+fn update_rebind_text(
+    rebinding: Res<Rebinding>,
+    input_map: Res<InputMap>,
+    mut q_rebind_buttons: Query<(&RebindButton, &mut Text)>,
+) {
+    if !rebinding.is_changed() && !input_map.is_changed() {
+        return;
+    }
+    for (RebindButton(action), mut text) in q_rebind_buttons.iter_mut() {
+        **text = if rebinding.action == Some(*action) {
+            "Press a key...".to_string()
+        } else {
+            input_map.describe(*action)
+        };
+    }
+}
@@ -1,21 +1,44 @@
+use std::time::Duration;
+
 use bevy::{prelude::*, time::Stopwatch};
+use bevy_ecs_ldtk::LevelIid;
 
 use crate::{
-    shared::{GameState, PlayState},
-    ui::UiFont,
+    game::{
+        level_completion::{LevelCompleted, LevelStarted},
+        lyra::kill::KillPlayer,
+    },
+    ldtk::{LdtkLevelParam, LevelExt},
+    save::SaveData,
+    shared::{GameState, PlayState, UiState},
+    ui::{level_select::LevelProgress, UiFont},
     utils::hhmmss::Hhmmss,
 };
 
+/// Color of split deltas that beat the saved best.
+pub const GOLD: Color = Color::srgb(1.0, 0.84, 0.0);
+/// Color of split deltas slower than the saved best.
+pub const RED: Color = Color::srgb(0.9, 0.2, 0.2);
+
 pub struct SpeedrunTimerPlugin;
 
 impl Plugin for SpeedrunTimerPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<SpeedrunTimer>();
-        app.add_systems(OnEnter(GameState::InGame), spawn_speedrun_timer);
+        app.init_resource::<Splits>();
+        app.add_observer(start_split);
+        app.add_observer(finish_split);
+        app.add_observer(count_split_deaths);
+        app.add_systems(
+            OnEnter(GameState::InGame),
+            (clear_splits, spawn_speedrun_timer),
+        );
         app.add_systems(OnExit(GameState::InGame), despawn_speedrun_timer);
         app.add_systems(
             Update,
-            tick_speedrun_timer.run_if(in_state(PlayState::Playing)),
+            (tick_speedrun_timer, update_speedrun_ui)
+                .chain()
+                .run_if(in_state(PlayState::Playing)),
         );
     }
 }
@@ -26,9 +49,149 @@ pub struct SpeedrunTimer {
     timer: Stopwatch,
 }
 
+impl SpeedrunTimer {
+    pub fn elapsed(&self) -> Duration {
+        self.timer.elapsed()
+    }
+}
+
+/// A level of the run, timed from its start marker to its end marker.
+#[derive(Clone, Debug)]
+pub struct Split {
+    pub level_id: String,
+    pub level_iid: LevelIid,
+    /// [`SpeedrunTimer`] time at which the start marker was last crossed.
+    started: Duration,
+    /// [`None`] until the end marker is reached.
+    pub time: Option<Duration>,
+    /// The saved best time of the level when the split started.
+    pub best: Option<Duration>,
+    pub deaths: u32,
+    pub resets: u32,
+}
+
+impl Split {
+    /// Difference with the saved best, negative if the split beat it.
+    pub fn delta(&self) -> Option<(Duration, bool)> {
+        let (time, best) = (self.time?, self.best?);
+        Some(if time < best {
+            (best - time, true)
+        } else {
+            (time - best, false)
+        })
+    }
+
+    /// Text and color of the delta with the saved best, e.g. "-00:00:01.250" in gold.
+    pub fn delta_text(&self) -> Option<(String, Color)> {
+        self.delta().map(|(delta, ahead)| {
+            if ahead {
+                (format!("-{}", delta.hhmmssxxx()), GOLD)
+            } else {
+                (format!("+{}", delta.hhmmssxxx()), RED)
+            }
+        })
+    }
+}
+
+/// [`Resource`] holding the splits of the current run, in the order the levels were started.
+#[derive(Resource, Default)]
+pub struct Splits(pub Vec<Split>);
+
 #[derive(Component)]
 pub struct SpeedrunUiMarker;
 
+#[derive(Component)]
+pub struct SplitUiMarker;
+
+#[derive(Component)]
+pub struct SplitDeltaUiMarker;
+
+/// Every time the game is entered from the menus is a new run. The splits of the last one are
+/// kept until then, for the results screen.
+pub fn clear_splits(mut splits: ResMut<Splits>) {
+    splits.0.clear();
+}
+
+/// Starts a split when Lyra crosses a start marker. Crossing it again after dying or restarting
+/// restarts the time of the same split.
+pub fn start_split(
+    event: On<LevelStarted>,
+    mut splits: ResMut<Splits>,
+    speedrun_timer: Res<SpeedrunTimer>,
+    ldtk_level_param: LdtkLevelParam,
+    save: Res<SaveData>,
+) {
+    if let Some(split) = splits.0.last_mut() {
+        if split.level_iid == event.level_iid && split.time.is_none() {
+            split.started = speedrun_timer.elapsed();
+            return;
+        }
+    }
+    let Some(level) = ldtk_level_param.level_by_iid(&event.level_iid) else {
+        return;
+    };
+    let level_id = level.raw().level_id().clone();
+    let best = save
+        .level(&level_id, &event.level_iid)
+        .and_then(|record| record.best_time_ms)
+        .map(Duration::from_millis);
+    splits.0.push(Split {
+        level_id,
+        level_iid: event.level_iid.clone(),
+        started: speedrun_timer.elapsed(),
+        time: None,
+        best,
+        deaths: 0,
+        resets: 0,
+    });
+}
+
+/// Stops the current split, and shows the results once the last level is completed.
+pub fn finish_split(
+    event: On<LevelCompleted>,
+    mut splits: ResMut<Splits>,
+    speedrun_timer: Res<SpeedrunTimer>,
+    level_progress: Res<LevelProgress>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut next_ui_state: ResMut<NextState<UiState>>,
+) {
+    let Some(split) = splits.0.last_mut() else {
+        return;
+    };
+    if split.level_iid != event.level_iid || split.time.is_some() {
+        return;
+    }
+    split.time = Some(event.time);
+
+    let is_last_level = level_progress
+        .0
+        .last()
+        .is_some_and(|level| level.level_iid == event.level_iid);
+    if speedrun_timer.enabled && is_last_level {
+        next_game_state.set(GameState::Ui);
+        next_ui_state.set(UiState::Results);
+    }
+}
+
+pub fn count_split_deaths(
+    event: On<KillPlayer>,
+    mut splits: ResMut<Splits>,
+    play_state: Res<State<PlayState>>,
+) {
+    // same check as the kill animation, so that deaths aren't counted twice
+    if *play_state != PlayState::Playing {
+        return;
+    }
+    let Some(split) = splits.0.last_mut().filter(|split| split.time.is_none()) else {
+        return;
+    };
+    if event.restart {
+        split.resets += 1;
+    } else {
+        split.deaths += 1;
+    }
+}
+
 pub fn spawn_speedrun_timer(
     mut commands: Commands,
     speedrun_timer: Res<SpeedrunTimer>,
@@ -42,26 +205,58 @@ pub fn spawn_speedrun_timer(
             width: Val::Percent(100.),
             height: Val::Percent(100.),
             padding: UiRect::all(Val::Px(32.)),
+            flex_direction: FlexDirection::Column,
             ..default()
         })
+        .insert(SpeedrunUiMarker)
         .with_child((
             Text::new(speedrun_timer.timer.elapsed().hhmmssxxx()),
-            SpeedrunUiMarker,
             ui_font.text_font().with_font_size(36.),
+        ))
+        .with_child((
+            Text::new(""),
+            SplitUiMarker,
+            ui_font.text_font().with_font_size(24.),
+        ))
+        .with_child((
+            Text::new(""),
+            SplitDeltaUiMarker,
+            ui_font.text_font().with_font_size(24.),
         ));
 }
 
-pub fn tick_speedrun_timer(
-    mut commands: Commands,
-    time: Res<Time>,
-    mut speedrun_timer: ResMut<SpeedrunTimer>,
-    speedrun_ui: Single<Entity, With<SpeedrunUiMarker>>,
-) {
+/// Ticks even when the timer isn't shown, since level times are saved either way.
+pub fn tick_speedrun_timer(time: Res<Time>, mut speedrun_timer: ResMut<SpeedrunTimer>) {
     speedrun_timer.timer.tick(time.delta());
+}
 
+pub fn update_speedrun_ui(
+    mut commands: Commands,
+    speedrun_timer: Res<SpeedrunTimer>,
+    splits: Res<Splits>,
+    speedrun_ui: Single<&Children, With<SpeedrunUiMarker>>,
+    split_ui: Single<Entity, With<SplitUiMarker>>,
+    split_delta_ui: Single<Entity, With<SplitDeltaUiMarker>>,
+) {
     commands
-        .entity(*speedrun_ui)
+        .entity(speedrun_ui[0])
         .insert(Text::new(speedrun_timer.timer.elapsed().hhmmssxxx()));
+
+    let Some(split) = splits.0.last() else {
+        return;
+    };
+    let split_time = split
+        .time
+        .unwrap_or_else(|| speedrun_timer.elapsed().saturating_sub(split.started));
+    commands.entity(*split_ui).insert(Text::new(format!(
+        "{}  {}",
+        split.level_id,
+        split_time.hhmmssxxx()
+    )));
+    let (delta, color) = split.delta_text().unwrap_or_default();
+    commands
+        .entity(*split_delta_ui)
+        .insert((Text::new(delta), TextColor(color)));
 }
 
 pub fn despawn_speedrun_timer(